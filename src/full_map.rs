@@ -1,17 +1,19 @@
 //! [`FullMap`] of bit string prefixes
 
-use core::{
-	cmp::Ordering,
-	marker::PhantomData,
-};
+use alloc::vec::Vec;
+use core::marker::PhantomData;
 
 use bitstring::BitString;
 
 use crate::tree::{
 	DefaultCompare,
+	InsertPosition,
+	InsertPositionWith,
+	IntoIterInOrder,
 	Node,
 	Tree,
 	TreeProperties,
+	TreeStats,
 	WalkedDirection,
 };
 
@@ -40,11 +42,22 @@ impl<K: BitString + Clone, V> TreeProperties for TpFullMap<K, V> {
 ///
 /// This is implemented as a [`crate::tree::Tree`] where all nodes can have an optional value;
 /// branches where no node has a value are pruned.
-#[derive(Clone)]
 pub struct FullMap<K: BitString + Clone, V> {
 	tree: Tree<TpFullMap<K, V>>,
 }
 
+impl<K: BitString + Clone, V: Clone> Clone for FullMap<K, V> {
+	fn clone(&self) -> Self {
+		Self {
+			tree: self.tree.clone(),
+		}
+	}
+
+	fn clone_from(&mut self, source: &Self) {
+		self.tree.clone_from(&source.tree);
+	}
+}
+
 impl<K: BitString + Clone, V> Default for FullMap<K, V> {
 	fn default() -> Self {
 		Self::new()
@@ -61,6 +74,49 @@ where
 	}
 }
 
+impl<K, V> core::fmt::Display for FullMap<K, V>
+where
+	K: BitString + Clone + core::fmt::Display,
+	V: core::fmt::Display,
+{
+	fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+		if f.alternate() {
+			for (key, value) in self.iter() {
+				writeln!(f, "{} => {}", key, value)?;
+			}
+			return Ok(());
+		}
+		f.write_str("{")?;
+		for (ndx, (key, value)) in self.iter().enumerate() {
+			if ndx > 0 {
+				f.write_str(", ")?;
+			}
+			write!(f, "{} => {}", key, value)?;
+		}
+		f.write_str("}")
+	}
+}
+
+impl<K: BitString + Clone, V> IntoIterator for FullMap<K, V> {
+	type IntoIter = IntoIterMap<K, V>;
+	type Item = (K, V);
+
+	fn into_iter(self) -> Self::IntoIter {
+		IntoIterMap {
+			iter: self.tree.into_iter_in_order(),
+		}
+	}
+}
+
+impl<'s, K: BitString + Clone, V> IntoIterator for &'s FullMap<K, V> {
+	type IntoIter = IterMap<'s, K, V>;
+	type Item = (&'s K, &'s V);
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter()
+	}
+}
+
 impl<K, V> FullMap<K, V>
 where
 	K: BitString + Clone,
@@ -72,23 +128,40 @@ where
 
 	/// Gets the given key's corresponding entry in the map for in-place manipulation.
 	pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+		Entry::new(self.tree.walk_mut(), key)
+	}
+
+	/// Gets the given key's corresponding entry in the map for in-place manipulation.
+	///
+	/// Unlike [`entry`](Self::entry) the key is only cloned when a value is inserted.
+	pub fn entry_ref<'s, 'k>(&'s mut self, key: &'k K) -> EntryRef<'s, 'k, K, V> {
 		let mut walk = self.tree.walk_mut();
-		walk.goto_insert(&key);
-		if let Some(node) = walk.current().node() {
-			if node.get_key().len() == key.len() && node.get_value().is_some() {
-				return Entry::Occupied(OccupiedEntry { walk });
-			}
+		if goto_occupied(&mut walk, key) {
+			return EntryRef::Occupied(OccupiedEntry { walk });
 		}
-		Entry::Vacant(VacantEntry { walk, key })
+		EntryRef::Vacant(VacantEntryRef { walk, key })
+	}
+
+	/// Returns a mutable reference to the value corresponding to the key, inserting the result
+	/// of `default` if the key isn't present.
+	///
+	/// Same as `entry(key).or_insert_with(default)`, but without building an [`Entry`].
+	pub fn get_or_insert_with<F: FnOnce() -> V>(&mut self, key: K, default: F) -> &mut V {
+		let mut walk: crate::tree::WalkMutOwned<_, WalkedDirection> = self.tree.walk_mut();
+		if walk.goto_insert(&key) != Some(InsertPosition::AlreadyExists) {
+			walk.insert(key);
+		}
+		let node = walk
+			.into_current_mut()
+			.node()
+			.expect("after insert walk should be at a node");
+		node.get_value_mut().get_or_insert_with(default)
 	}
 
 	fn occupied<'s>(&'s mut self, key: &K) -> Option<OccupiedEntry<'s, K, V>> {
 		let mut walk = self.tree.walk_mut();
-		walk.goto_insert(key);
-		if let Some(node) = walk.current().node() {
-			if node.get_key().len() == key.len() && node.get_value().is_some() {
-				return Some(OccupiedEntry { walk });
-			}
+		if goto_occupied(&mut walk, key) {
+			return Some(OccupiedEntry { walk });
 		}
 		None
 	}
@@ -102,12 +175,18 @@ where
 		self.entry(key).replace(value).1
 	}
 
-	/// Removes a key from the map, returning the stored key and value if the key
+	/// Removes a key from the map, returning the value if the key
 	/// was previously in the map.
 	pub fn remove(&mut self, key: &K) -> Option<V> {
 		Some(self.occupied(key)?.remove())
 	}
 
+	/// Removes a key from the map, returning the stored key and value if the key
+	/// was previously in the map.
+	pub fn remove_entry(&mut self, key: &K) -> Option<(K, V)> {
+		Some(self.occupied(key)?.remove_entry())
+	}
+
 	/// Returns a reference to the value corresponding to the key.
 	pub fn get(&self, key: &K) -> Option<&V> {
 		self.tree.get(key)?.get_value().as_ref()
@@ -118,36 +197,294 @@ where
 		self.tree.get_mut(key)?.get_value_mut().as_mut()
 	}
 
+	/// Returns the stored key and a reference to the value corresponding to the key.
+	pub fn get_key_value(&self, key: &K) -> Option<(&K, &V)> {
+		let node = self.tree.get(key)?;
+		Some((node.get_key(), node.get_value().as_ref()?))
+	}
+
+	/// Returns the stored key and a mutable reference to the value corresponding to the key.
+	pub fn get_key_value_mut(&mut self, key: &K) -> Option<(&K, &mut V)> {
+		let (key, value, _) = self.tree.get_mut(key)?.get_key_value_child_mut(false);
+		Some((key, value.as_mut()?))
+	}
+
+	/// Returns `true` if the map contains a value for the exact key.
+	pub fn contains_key(&self, key: &K) -> bool {
+		self.get(key).is_some()
+	}
+
+	/// Returns `true` if the map contains a value for the key or a shorter prefix of it.
+	///
+	/// See [`most_specific`](Self::most_specific) to find the longest matching prefix.
+	pub fn contains_prefix_of(&self, key: &K) -> bool {
+		self.tree
+			.get_longest_prefix_with(key, |node| node.get_value().is_some())
+			.is_some()
+	}
+
+	/// Structural statistics of the underlying tree
+	///
+	/// See [`Tree::stats`].
+	pub fn stats(&self) -> TreeStats {
+		self.tree.stats()
+	}
+
+	/// Longest bit string that is a prefix of all keys in the map
+	///
+	/// Returns `None` if the map is empty.
+	pub fn longest_common_prefix(&self) -> Option<K> {
+		let mut node = self.tree.root()?;
+		// skip inner nodes without value that only have one non-empty subtree
+		while node.get_value().is_none() {
+			let (left, right) = node.get_children()?;
+			node = if is_empty_subtree(left) {
+				right
+			} else if is_empty_subtree(right) {
+				left
+			} else {
+				break;
+			};
+		}
+		Some(node.get_key().clone())
+	}
+
+	/// Returns the first entry in prefix order
+	///
+	/// In prefix order an entry precedes all longer prefixes it contains; [`iter`](Self::iter)
+	/// returns entries in-order instead (longer prefixes in the left half first).
+	pub fn first_key_value(&self) -> Option<(&K, &V)> {
+		// pre-order search (node, left, right); subtrees might not contain any value
+		let mut stack: Vec<&FullMapNode<K, V>> = self.tree.root().into_iter().collect();
+		while let Some(node) = stack.pop() {
+			if let Some(value) = node.get_value() {
+				return Some((node.get_key(), value));
+			}
+			if let Some((left, right)) = node.get_children() {
+				stack.push(right);
+				stack.push(left);
+			}
+		}
+		None
+	}
+
+	/// Returns the last entry in prefix order
+	///
+	/// See [`first_key_value`](Self::first_key_value).
+	pub fn last_key_value(&self) -> Option<(&K, &V)> {
+		// reversed pre-order search (right, left, node)
+		let mut stack: Vec<(&FullMapNode<K, V>, bool)> = self
+			.tree
+			.root()
+			.map(|root| (root, false))
+			.into_iter()
+			.collect();
+		while let Some((node, visited_children)) = stack.pop() {
+			match node.get_children() {
+				Some((left, right)) if !visited_children => {
+					stack.push((node, true));
+					stack.push((left, false));
+					stack.push((right, false));
+				},
+				_ => {
+					if let Some(value) = node.get_value() {
+						return Some((node.get_key(), value));
+					}
+				},
+			}
+		}
+		None
+	}
+
+	/// Removes and returns the first entry in prefix order
+	///
+	/// See [`first_key_value`](Self::first_key_value).
+	pub fn pop_first(&mut self) -> Option<(K, V)> {
+		let key = self.first_key_value()?.0.clone();
+		self.remove_entry(&key)
+	}
+
+	/// Removes and returns the last entry in prefix order
+	///
+	/// See [`first_key_value`](Self::first_key_value).
+	pub fn pop_last(&mut self) -> Option<(K, V)> {
+		let key = self.last_key_value()?.0.clone();
+		self.remove_entry(&key)
+	}
+
 	/// Returns a reference to the key-value pair for the longest prefix of the key in the map.
 	pub fn most_specific(&self, key: &K) -> Option<(&K, &V)> {
-		// TODO: could probably also implement it using walk.goto + check, or manually
-		self.path(key.clone()).last()
+		let node = self
+			.tree
+			.get_longest_prefix_with(key, |node| node.get_value().is_some())?;
+		Some((node.get_key(), node.get_value().as_ref()?))
+	}
+
+	/// Returns a reference to the key-value pair for the longest prefix of the key in the map
+	/// with a value accepted by `f`.
+	pub fn most_specific_with<F>(&self, key: &K, mut f: F) -> Option<(&K, &V)>
+	where
+		F: FnMut(&K, &V) -> bool,
+	{
+		let node = self
+			.tree
+			.get_longest_prefix_with(key, |node| match node.get_value() {
+				Some(value) => f(node.get_key(), value),
+				None => false,
+			})?;
+		Some((node.get_key(), node.get_value().as_ref()?))
+	}
+
+	/// Returns the key and a mutable reference to the value for the longest prefix of the key in
+	/// the map with a value accepted by `f`.
+	pub fn most_specific_mut_with<F>(&mut self, key: &K, mut f: F) -> Option<(&K, &mut V)>
+	where
+		F: FnMut(&K, &V) -> bool,
+	{
+		let node = self
+			.tree
+			.get_longest_prefix_mut_with(key, |node| match node.get_value() {
+				Some(value) => f(node.get_key(), value),
+				None => false,
+			})?;
+		let (key, value, _) = node.get_key_value_child_mut(false);
+		Some((key, value.as_mut()?))
 	}
 
 	/// Remove all prefixes equal or longer than given key
+	///
+	/// Nodes without a value that were only needed to reach the removed
+	/// prefixes are removed too.
 	pub fn remove_tree(&mut self, key: K) {
-		let mut walk = self.tree.walk_mut();
-		walk.goto_insert(&key);
-		match walk.current().node() {
-			None => (), // empty tree
-			Some(node) => {
-				match node.get_key().len().cmp(&key.len()) {
-					Ordering::Less => {
-						// node is a leaf and covers key; need to split and remove key
-						// create explicit node with key we want to remove
-						walk.insert(key);
-						// now remove it
-						walk.delete_current();
+		self.tree.delete_compacting(&key, Option::is_none);
+	}
+
+	/// Move all entries of another map into this map
+	///
+	/// If a key is present in both maps `combine` is called with the key, the existing
+	/// value and the value from `other`.
+	///
+	/// Walks both trees in parallel, reusing the nodes of `other`.
+	pub fn merge<F>(&mut self, other: Self, mut combine: F)
+	where
+		F: FnMut(&K, &mut V, V),
+	{
+		let other_root = match other.tree.into_root() {
+			None => return,
+			Some(root) => root,
+		};
+		let root = match core::mem::take(&mut self.tree).into_root() {
+			None => other_root,
+			Some(root) => merge_nodes(root, other_root, &mut combine),
+		};
+		self.tree = Tree::from_root(Some(root));
+	}
+
+	/// Remove entries with the same value as the entry for their longest (shorter) prefix
+	///
+	/// This doesn't change the results of [`most_specific`](Self::most_specific).
+	pub fn aggregate(&mut self)
+	where
+		V: PartialEq,
+	{
+		// removing an entry doesn't change whether entries below it are redundant:
+		// their nearest entry above had the same value
+		//
+		// single walk in pre-order; the value of the nearest entry above is
+		// moved out of its node while its subtree is visited, and the walk
+		// stack remembers the one above it to restore it when leaving the
+		// subtree. empty leafs are pruned once both children are done.
+		let mut nearest: Option<V> = None;
+		let mut walk = self.tree.walk_mut::<WalkedDirection, Option<Option<V>>>();
+		if !walk.down_root_with(None) {
+			return;
+		}
+		'enter: loop {
+			let node = walk.current_mut().node().expect("should be at node");
+			// `Some(above)` if `nearest` is the value of this node now
+			let saved = match node.get_value_mut().take() {
+				Some(value) if nearest.as_ref() == Some(&value) => None, // redundant
+				Some(value) => Some(nearest.replace(value)),
+				None => None,
+			};
+			if !node.is_leaf() {
+				walk.down_left_with(saved);
+				continue;
+			}
+			restore_nearest(node, saved, &mut nearest);
+			// subtree at current node is done
+			loop {
+				match walk.up_with() {
+					Some((WalkedDirection::Left, saved)) => {
+						walk.down_right_with(saved);
+						continue 'enter;
 					},
-					Ordering::Equal | Ordering::Greater => {
-						// remove subtree
-						walk.delete_current();
+					Some((WalkedDirection::Right, saved)) => {
+						let node = walk.current_mut().node().expect("should be at node");
+						restore_nearest(node, saved, &mut nearest);
+						compact_empty_children(&mut walk);
 					},
+					Some((WalkedDirection::Down, _)) | None => break 'enter,
 				}
-			},
+			}
+		}
+		// root itself can't be a placeholder
+		walk.down_root_with(None);
+		if walk
+			.current()
+			.node()
+			.is_some_and(|root| root.is_leaf() && root.get_value().is_none())
+		{
+			walk.compact_if_empty(Option::is_none);
+		}
+	}
+
+	/// Number of entries [`aggregate`](Self::aggregate) would remove
+	pub fn count_redundant(&self) -> usize
+	where
+		V: PartialEq,
+	{
+		let mut count = 0;
+		self.visit_redundant(|is_redundant| count += usize::from(is_redundant));
+		count
+	}
+
+	// call `f` with whether the entry is redundant for each entry in pre-order
+	fn visit_redundant<F>(&self, mut f: F)
+	where
+		V: PartialEq,
+		F: FnMut(bool),
+	{
+		// nodes to visit with value of nearest entry above
+		let mut stack: Vec<(&FullMapNode<K, V>, Option<&V>)> = self
+			.tree
+			.root()
+			.map(|root| (root, None))
+			.into_iter()
+			.collect();
+		while let Some((node, mut parent_value)) = stack.pop() {
+			if let Some(value) = node.get_value() {
+				if parent_value == Some(value) {
+					f(true);
+				} else {
+					f(false);
+					parent_value = Some(value);
+				}
+			}
+			if let Some((left, right)) = node.get_children() {
+				stack.push((right, parent_value));
+				stack.push((left, parent_value));
+			}
 		}
 	}
 
+	/// Remove all prefixes (and their values) from map
+	///
+	/// Nodes are dropped without recursion; see [`Tree::clear`].
+	pub fn clear(&mut self) {
+		self.tree.clear();
+	}
+
 	/// Iterate over all prefixes and their values on the path to a key
 	pub fn path(&self, key: K) -> IterPath<'_, K, V> {
 		IterPath {
@@ -155,12 +492,65 @@ where
 		}
 	}
 
-	/// Iterate over all prefixes and their mutable values on the path to a key
+	/// Iterate over all prefixes and their values on the path to a key (including the key)
 	///
-	// TODO: return a `WalkMutPath` wrapper with IntoIterator impl?
-	pub fn path_mut(&mut self, key: K) -> IterPathMut<'_, K, V> {
-		IterPathMut {
-			iter: self.tree.iter_mut_path(key).into_iter(),
+	/// Like [`path`](Self::path), but collects the entries first; can be iterated
+	/// from the most specific entry too.
+	pub fn ancestors(&self, key: &K) -> IterAncestors<'_, K, V> {
+		let mut entries = Vec::new();
+		let mut next = self.tree.root();
+		while let Some(node) = next.take() {
+			if !is_prefix_of(node.get_key(), key) {
+				break;
+			}
+			if let Some(value) = node.get_value() {
+				entries.push((node.get_key(), value));
+			}
+			let len = node.get_key().len();
+			if len < key.len() {
+				next = node.get_child(key.get(len));
+			}
+		}
+		IterAncestors {
+			iter: entries.into_iter(),
+		}
+	}
+
+	/// Iterate over all prefixes and their mutable values on the path to a key (including the key)
+	///
+	/// See [`ancestors`](Self::ancestors).
+	pub fn ancestors_mut(&mut self, key: &K) -> IterAncestorsMut<'_, K, V> {
+		let mut entries = Vec::new();
+		let mut next = self.tree.root_mut();
+		while let Some(node) = next.take() {
+			if !is_prefix_of(node.get_key(), key) {
+				break;
+			}
+			let len = node.get_key().len();
+			let (node_key, value, child) = if len < key.len() {
+				node.get_key_value_child_mut(key.get(len))
+			} else {
+				let (node_key, value, _) = node.get_key_value_child_mut(false);
+				(node_key, value, None)
+			};
+			if let Some(value) = value {
+				entries.push((node_key, value));
+			}
+			next = child;
+		}
+		IterAncestorsMut {
+			iter: entries.into_iter(),
+		}
+	}
+
+	/// Walk all prefixes and their mutable values on the path to a key
+	///
+	/// Use [`PathMut::next`] to walk step by step, or iterate over `&mut path`.
+	/// After walking the path [`PathMut::into_entry`] gives the entry for the key.
+	pub fn path_mut(&mut self, key: K) -> PathMut<'_, K, V> {
+		PathMut {
+			walk: self.tree.walk_mut(),
+			key,
 		}
 	}
 
@@ -177,6 +567,100 @@ where
 			iter: self.tree.iter_mut_in_order(),
 		}
 	}
+
+	/// Iterate over all prefixes and their values that have the given prefix (including the prefix itself)
+	///
+	/// Entries for shorter prefixes covering the given prefix are not included;
+	/// see [`path`](Self::path) for those.
+	pub fn iter_prefix(&self, prefix: &K) -> IterPrefix<'_, K, V> {
+		IterPrefix::new(self.subtree(prefix))
+	}
+
+	/// Read-only view of the entries with the given prefix (including the prefix itself)
+	///
+	/// Entries for shorter prefixes covering the given prefix are only used by
+	/// [`FullMapView::most_specific`] if enabled with [`FullMapView::with_ancestors`].
+	pub fn view(&self, prefix: K) -> FullMapView<'_, K, V> {
+		let ancestor = self.most_specific_with(&prefix, |key, _| key.len() < prefix.len());
+		FullMapView {
+			node: self.subtree(&prefix),
+			ancestor,
+			with_ancestors: false,
+			prefix,
+		}
+	}
+
+	// root of the subtree containing all keys with the given prefix
+	fn subtree(&self, prefix: &K) -> Option<&FullMapNode<K, V>> {
+		match self.tree.goto_insert(prefix)? {
+			// leaf with shorter key: not in subtree of prefix
+			InsertPositionWith::BelowLeaf(_) => None,
+			InsertPositionWith::AlreadyExists(node) => Some(node),
+			InsertPositionWith::ReplaceNode(node) => {
+				if node.get_key().shared_prefix_len(prefix) == prefix.len() {
+					Some(node) // node in subtree of prefix
+				} else {
+					None
+				}
+			},
+		}
+	}
+
+	/// Iterate over all prefixes with a value that have no shorter prefix with a value in the map
+	///
+	/// See [`children`](Self::children) for the entries below a key.
+	pub fn roots(&self) -> IterChildren<'_, K, V> {
+		IterChildren {
+			stack: self.tree.root().into_iter().collect(),
+		}
+	}
+
+	/// Iterate over immediate more-specific entries of a key
+	///
+	/// These are the entries with prefixes longer than key that have no other entry
+	/// between them and key; the key itself doesn't need to be in the map.
+	pub fn children(&self, key: &K) -> IterChildren<'_, K, V> {
+		let start = match self.tree.goto_insert(key) {
+			None => None,
+			// leaf with shorter key: nothing below key
+			Some(InsertPositionWith::BelowLeaf(_)) => None,
+			Some(InsertPositionWith::AlreadyExists(node)) => {
+				return IterChildren {
+					stack: match node.get_children() {
+						Some((left, right)) => alloc::vec![right, left],
+						None => Vec::new(),
+					},
+				};
+			},
+			Some(InsertPositionWith::ReplaceNode(node)) => {
+				if node.get_key().shared_prefix_len(key) == key.len() {
+					Some(node) // node in subtree of key
+				} else {
+					None
+				}
+			},
+		};
+		IterChildren {
+			stack: start.into_iter().collect(),
+		}
+	}
+
+	/// Iterate over all prefixes with a value
+	pub fn keys(&self) -> Keys<'_, K, V> {
+		Keys { iter: self.iter() }
+	}
+
+	/// Iterate over all values
+	pub fn values(&self) -> Values<'_, K, V> {
+		Values { iter: self.iter() }
+	}
+
+	/// Iterate over all mutable values
+	pub fn values_mut(&mut self) -> ValuesMut<'_, K, V> {
+		ValuesMut {
+			iter: self.iter_mut(),
+		}
+	}
 }
 
 // basically copied from alloc::collections::btree::map::entry:
@@ -193,6 +677,17 @@ pub enum Entry<'s, K: BitString + Clone, V> {
 }
 
 impl<'s, K: BitString + Clone, V> Entry<'s, K, V> {
+	// walk can start anywhere; goto_insert walks up as needed
+	fn new(
+		mut walk: crate::tree::WalkMutOwned<'s, TpFullMap<K, V>, WalkedDirection>,
+		key: K,
+	) -> Self {
+		if goto_occupied(&mut walk, &key) {
+			return Self::Occupied(OccupiedEntry { walk });
+		}
+		Self::Vacant(VacantEntry { walk, key })
+	}
+
 	/// Ensures a value is in the entry by inserting the default if empty, and returns
 	/// a mutable reference to the value in the entry.
 	pub fn or_insert(self, default: V) -> &'s mut V {
@@ -301,6 +796,97 @@ impl<'s, K: BitString + Clone, V> Entry<'s, K, V> {
 	}
 }
 
+/// A view into a single entry in a map, which may either be vacant or occupied.
+///
+/// This enum is constructed from the [`entry_ref`] method on [`FullMap`].
+///
+/// [`entry_ref`]: FullMap::entry_ref
+pub enum EntryRef<'s, 'k, K: BitString + Clone, V> {
+	/// A vacant entry.
+	Vacant(VacantEntryRef<'s, 'k, K, V>),
+	/// An occupied entry.
+	Occupied(OccupiedEntry<'s, K, V>),
+}
+
+impl<'s, 'k, K: BitString + Clone, V> EntryRef<'s, 'k, K, V> {
+	/// Ensures a value is in the entry by inserting the default if empty, and returns
+	/// a mutable reference to the value in the entry.
+	pub fn or_insert(self, default: V) -> &'s mut V {
+		match self {
+			Self::Occupied(entry) => entry.into_mut(),
+			Self::Vacant(entry) => entry.insert(default),
+		}
+	}
+
+	/// Ensures a value is in the entry by inserting the result of the default function if empty,
+	/// and returns a mutable reference to the value in the entry.
+	pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'s mut V {
+		match self {
+			Self::Occupied(entry) => entry.into_mut(),
+			Self::Vacant(entry) => entry.insert(default()),
+		}
+	}
+
+	/// Ensures a value is in the entry by inserting, if empty, the result of the default function.
+	///
+	/// The default function gets a reference to the key.
+	#[inline]
+	pub fn or_insert_with_key<F: FnOnce(&K) -> V>(self, default: F) -> &'s mut V {
+		match self {
+			Self::Occupied(entry) => entry.into_mut(),
+			Self::Vacant(entry) => {
+				let value = default(entry.key());
+				entry.insert(value)
+			},
+		}
+	}
+
+	/// Returns a reference to this entry's key.
+	pub fn key(&self) -> &K {
+		match self {
+			Self::Occupied(entry) => entry.key(),
+			Self::Vacant(entry) => entry.key(),
+		}
+	}
+
+	/// Provides in-place mutable access to an occupied entry before any
+	/// potential inserts into the map.
+	pub fn and_modify<F>(mut self, f: F) -> Self
+	where
+		F: FnOnce(&mut V),
+	{
+		if let Self::Occupied(ref mut entry) = self {
+			f(entry.get_mut())
+		}
+		self
+	}
+
+	/// Ensures a value is in the entry by inserting the default value if empty,
+	/// and returns a mutable reference to the value in the entry.
+	pub fn or_default(self) -> &'s mut V
+	where
+		V: Default,
+	{
+		match self {
+			Self::Occupied(entry) => entry.into_mut(),
+			Self::Vacant(entry) => entry.insert(Default::default()),
+		}
+	}
+
+	/// Sets or inserts the value of the entry with the [`EntryRef`]'s key,
+	/// and returns a mutable reference to it.
+	pub fn insert(self, value: V) -> &'s mut V {
+		match self {
+			Self::Occupied(entry) => {
+				let vref = entry.into_mut();
+				*vref = value;
+				vref
+			},
+			Self::Vacant(entry) => entry.insert(value),
+		}
+	}
+}
+
 /// A view into a vacant entry in a [`FullMap`]. It is part of the [`Entry`] enum.
 pub struct VacantEntry<'s, K: BitString + Clone + 's, V: 's> {
 	walk: crate::tree::WalkMutOwned<'s, TpFullMap<K, V>, WalkedDirection>,
@@ -333,6 +919,30 @@ impl<'s, K: BitString + Clone, V> VacantEntry<'s, K, V> {
 	}
 }
 
+/// A view into a vacant entry in a [`FullMap`]. It is part of the [`EntryRef`] enum.
+pub struct VacantEntryRef<'s, 'k, K: BitString + Clone + 's, V: 's> {
+	walk: crate::tree::WalkMutOwned<'s, TpFullMap<K, V>, WalkedDirection>,
+	key: &'k K,
+}
+
+impl<'s, 'k, K: BitString + Clone, V> VacantEntryRef<'s, 'k, K, V> {
+	/// Gets a reference to the key that would be used when inserting a value
+	/// through the VacantEntryRef.
+	pub fn key(&self) -> &'k K {
+		self.key
+	}
+
+	/// Sets the value of the entry with a clone of the `VacantEntryRef`'s key,
+	/// and returns a mutable reference to it.
+	pub fn insert(self, value: V) -> &'s mut V {
+		VacantEntry {
+			walk: self.walk,
+			key: self.key.clone(),
+		}
+		.insert(value)
+	}
+}
+
 /// A view into an occupied entry in a [`FullMap`]. It is part of the [`Entry`] enum.
 pub struct OccupiedEntry<'s, K: BitString + Clone + 's, V: 's> {
 	walk: crate::tree::WalkMutOwned<'s, TpFullMap<K, V>, WalkedDirection>,
@@ -403,6 +1013,14 @@ impl<'s, K: BitString + Clone, V> OccupiedEntry<'s, K, V> {
 		core::mem::replace(self.get_mut(), value)
 	}
 
+	/// Takes the key and value of the entry out of the map, and returns them.
+	///
+	/// The key is cloned, as the node might stay in the tree.
+	pub fn remove_entry(self) -> (K, V) {
+		let key = self.key().clone();
+		(key, self.remove())
+	}
+
 	/// Takes the value of the entry out of the map, and returns it.
 	pub fn remove(mut self) -> V {
 		let value = self
@@ -434,25 +1052,249 @@ impl<'s, K: BitString + Clone, V> Iterator for IterPath<'s, K, V> {
 	}
 }
 
+type FullMapNode<K, V> = Node<TpFullMap<K, V>>;
+
+// merge subtree `b` into subtree `a`
+fn merge_nodes<K, V, F>(
+	a: FullMapNode<K, V>,
+	b: FullMapNode<K, V>,
+	combine: &mut F,
+) -> FullMapNode<K, V>
+where
+	K: BitString + Clone,
+	F: FnMut(&K, &mut V, V),
+{
+	let a_len = a.get_key().len();
+	let b_len = b.get_key().len();
+	let shared_len = a.get_key().shared_prefix_len(b.get_key());
+	if shared_len < a_len && shared_len < b_len {
+		// disjoint subtrees
+		return Node::new_parent(a, b);
+	}
+	if a_len == b_len {
+		let (key, a_value, a_children) = a.into_parts();
+		let (_, b_value, b_children) = b.into_parts();
+		let value = match (a_value, b_value) {
+			(Some(mut a_value), Some(b_value)) => {
+				combine(&key, &mut a_value, b_value);
+				Some(a_value)
+			},
+			(a_value, b_value) => a_value.or(b_value),
+		};
+		let children = match (a_children, b_children) {
+			(Some((a_left, a_right)), Some((b_left, b_right))) => Some((
+				merge_nodes(a_left, b_left, combine),
+				merge_nodes(a_right, b_right, combine),
+			)),
+			(a_children, b_children) => a_children.or(b_children),
+		};
+		return Node::from_parts(key, value, children);
+	}
+	// one node contains the other: merge the other into the matching child
+	let (outer, inner, inner_is_a) = if a_len < b_len {
+		(a, b, false)
+	} else {
+		(b, a, true)
+	};
+	let outer_len = outer.get_key().len();
+	let side = inner.get_key().get(outer_len);
+	let (key, value, children) = outer.into_parts();
+	let (same_side, other_side) = match children {
+		Some((left, right)) => {
+			let (same_side, other_side) = if side { (right, left) } else { (left, right) };
+			let same_side = if inner_is_a {
+				merge_nodes(inner, same_side, combine)
+			} else {
+				merge_nodes(same_side, inner, combine)
+			};
+			(same_side, other_side)
+		},
+		None => {
+			// leaf: need (empty) sibling leaf for inner node, like inserting would do
+			let mut other_key = inner.get_key().clone();
+			other_key.clip(outer_len + 1);
+			other_key.flip(outer_len);
+			(inner, Node::from_parts(other_key, None, None))
+		},
+	};
+	let children = if side {
+		(other_side, same_side)
+	} else {
+		(same_side, other_side)
+	};
+	Node::from_parts(key, value, Some(children))
+}
+
+// move walk to the node with exactly `key`; returns whether that node has a value
+//
+// the node at the insert position can have the length of `key` without
+// being the key: a sibling prefix of the same length only differs in bits.
+fn goto_occupied<K: BitString + Clone, V>(
+	walk: &mut crate::tree::WalkMutOwned<'_, TpFullMap<K, V>, WalkedDirection>,
+	key: &K,
+) -> bool {
+	walk.goto_insert(key) == Some(InsertPosition::AlreadyExists)
+		&& walk
+			.current()
+			.node()
+			.is_some_and(|node| node.get_value().is_some())
+}
+
+// remove empty leaf children of the current node (whose subtrees are
+// done); current points to the node (or its replacement) afterwards
+fn compact_empty_children<K: BitString + Clone, V, A: Default>(
+	walk: &mut crate::tree::WalkMutOwned<'_, TpFullMap<K, V>, WalkedDirection, A>,
+) {
+	for side in [false, true] {
+		let node = walk.current().node().expect("should be at node");
+		let child = match node.get_children() {
+			Some((left, right)) => {
+				if side {
+					right
+				} else {
+					left
+				}
+			},
+			None => return,
+		};
+		if child.is_leaf() && child.get_value().is_none() {
+			walk.down_with(side, A::default());
+			walk.compact_if_empty(Option::is_none);
+		}
+	}
+}
+
+// put the value of `node` back when leaving its subtree in `FullMap::aggregate`
+//
+// `saved` is the nearest entry above `node` if `nearest` holds the value of `node`.
+fn restore_nearest<K: BitString + Clone, V>(
+	node: &mut FullMapNode<K, V>,
+	saved: Option<Option<V>>,
+	nearest: &mut Option<V>,
+) {
+	if let Some(above) = saved {
+		*node.get_value_mut() = core::mem::replace(nearest, above);
+	}
+}
+
+// whether no node in the subtree has a value
+fn is_empty_subtree<K: BitString + Clone, V>(node: &FullMapNode<K, V>) -> bool {
+	node.get_value().is_none()
+		&& node
+			.get_children()
+			.is_none_or(|(left, right)| is_empty_subtree(left) && is_empty_subtree(right))
+}
+
+// whether `node` is a prefix of `key`
+fn is_prefix_of<K: BitString + Clone>(node: &K, key: &K) -> bool {
+	node.len() <= key.len() && node.shared_prefix_len(key) == node.len()
+}
+
+/// Walk all prefixes and their mutable values on the path to a key
+///
+/// See [`FullMap::path_mut`].
+pub struct PathMut<'s, K: BitString + Clone, V> {
+	walk: crate::tree::WalkMutOwned<'s, TpFullMap<K, V>, WalkedDirection>,
+	key: K,
+}
+
+impl<'s, K: BitString + Clone, V> PathMut<'s, K, V> {
+	/// Next prefix with a value towards the key
+	#[allow(clippy::should_implement_trait)] // iterator doesn't allow using lifetime of itself in item
+	pub fn next(&mut self) -> Option<(&K, &mut V)> {
+		self.into_iter().next()
+	}
+
+	/// Gets the entry for the key the path leads to
+	///
+	/// Doesn't need to walk the path again when it was (completely) walked already.
+	pub fn into_entry(self) -> Entry<'s, K, V> {
+		Entry::new(self.walk, self.key)
+	}
+}
+
+impl<'p, 's, K: BitString + Clone, V> IntoIterator for &'p mut PathMut<'s, K, V> {
+	type IntoIter = IterPathMut<'p, 's, K, V>;
+	type Item = (&'p K, &'p mut V);
+
+	fn into_iter(self) -> Self::IntoIter {
+		IterPathMut {
+			iter: self.walk.resume_path(self.key.clone()).into_iter(),
+		}
+	}
+}
+
+/// Iterate over the remaining prefixes and their mutable values on the path to a key
+///
+/// The [`PathMut`] continues after the last returned prefix.
+pub struct IterPathMut<'p, 's, K: BitString + Clone, V> {
+	iter: crate::tree::IterWalkMutOwnedPath<'s, 'p, TpFullMap<K, V>, WalkedDirection>,
+}
+
+impl<'p, 's, K: BitString + Clone, V> Iterator for IterPathMut<'p, 's, K, V> {
+	type Item = (&'p K, &'p mut V);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		// skip (inner) nodes that don't have a value
+		self.iter
+			.find_map(|(key, value, _)| Some((key, value.as_mut()?)))
+	}
+}
+
 /// Iterate over all prefixes and their values on the path to a key
-pub struct IterPathMut<'s, K: BitString + Clone, V> {
-	iter: crate::tree::IterMutPath<'s, TpFullMap<K, V>>,
+///
+/// See [`FullMap::ancestors`].
+pub struct IterAncestors<'s, K: BitString + Clone, V> {
+	iter: alloc::vec::IntoIter<(&'s K, &'s V)>,
 }
 
-impl<'s, K: BitString + Clone, V> Iterator for IterPathMut<'s, K, V> {
+impl<'s, K: BitString + Clone, V> Iterator for IterAncestors<'s, K, V> {
+	type Item = (&'s K, &'s V);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.iter.next()
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.iter.size_hint()
+	}
+}
+
+impl<K: BitString + Clone, V> DoubleEndedIterator for IterAncestors<'_, K, V> {
+	fn next_back(&mut self) -> Option<Self::Item> {
+		self.iter.next_back()
+	}
+}
+
+impl<K: BitString + Clone, V> ExactSizeIterator for IterAncestors<'_, K, V> {}
+
+/// Iterate over all prefixes and their mutable values on the path to a key
+///
+/// See [`FullMap::ancestors_mut`].
+pub struct IterAncestorsMut<'s, K: BitString + Clone, V> {
+	iter: alloc::vec::IntoIter<(&'s K, &'s mut V)>,
+}
+
+impl<'s, K: BitString + Clone, V> Iterator for IterAncestorsMut<'s, K, V> {
 	type Item = (&'s K, &'s mut V);
 
 	fn next(&mut self) -> Option<Self::Item> {
-		loop {
-			let (key, value, _) = self.iter.next()?;
-			// skip (inner) nodes that don't have a value
-			if let Some(value) = value {
-				return Some((key, value));
-			}
-		}
+		self.iter.next()
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.iter.size_hint()
+	}
+}
+
+impl<K: BitString + Clone, V> DoubleEndedIterator for IterAncestorsMut<'_, K, V> {
+	fn next_back(&mut self) -> Option<Self::Item> {
+		self.iter.next_back()
 	}
 }
 
+impl<K: BitString + Clone, V> ExactSizeIterator for IterAncestorsMut<'_, K, V> {}
+
 /// Iterate over all prefixes and their values
 pub struct IterMap<'s, K: BitString + Clone, V> {
 	iter: crate::tree::IterInOrder<'s, TpFullMap<K, V>>,
@@ -472,6 +1314,158 @@ impl<'s, K: BitString + Clone, V> Iterator for IterMap<'s, K, V> {
 	}
 }
 
+/// Iterate over all prefixes and their values below some prefix
+///
+/// See [`FullMap::iter_prefix`].
+pub struct IterPrefix<'s, K: BitString + Clone, V> {
+	// nodes not visited yet (right subtrees still pending); next node on top
+	stack: Vec<&'s Node<TpFullMap<K, V>>>,
+}
+
+impl<'s, K: BitString + Clone, V> IterPrefix<'s, K, V> {
+	fn new(start: Option<&'s Node<TpFullMap<K, V>>>) -> Self {
+		let mut iter = Self { stack: Vec::new() };
+		if let Some(node) = start {
+			iter.push_left(node);
+		}
+		iter
+	}
+
+	fn push_left(&mut self, mut node: &'s Node<TpFullMap<K, V>>) {
+		self.stack.push(node);
+		while let Some((left, _)) = node.get_children() {
+			self.stack.push(left);
+			node = left;
+		}
+	}
+}
+
+impl<'s, K: BitString + Clone, V> Iterator for IterPrefix<'s, K, V> {
+	type Item = (&'s K, &'s V);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			let node = self.stack.pop()?;
+			if let Some((_, right)) = node.get_children() {
+				self.push_left(right);
+			}
+			// skip (inner) nodes that don't have a value
+			if let Some(value) = node.get_value() {
+				return Some((node.get_key(), value));
+			}
+		}
+	}
+}
+
+/// Read-only view of the entries of a [`FullMap`] with some prefix
+///
+/// See [`FullMap::view`].
+pub struct FullMapView<'s, K: BitString + Clone, V> {
+	prefix: K,
+	// root of the subtree with the view prefix
+	node: Option<&'s Node<TpFullMap<K, V>>>,
+	// longest entry covering the view prefix (shorter than the prefix)
+	ancestor: Option<(&'s K, &'s V)>,
+	with_ancestors: bool,
+}
+
+impl<'s, K: BitString + Clone, V> FullMapView<'s, K, V> {
+	/// Prefix of the view
+	pub fn prefix(&self) -> &K {
+		&self.prefix
+	}
+
+	/// Whether [`most_specific`](Self::most_specific) falls back to the longest
+	/// entry covering the view prefix (disabled by default)
+	pub fn with_ancestors(mut self, with_ancestors: bool) -> Self {
+		self.with_ancestors = with_ancestors;
+		self
+	}
+
+	/// Returns a reference to the value corresponding to the key.
+	///
+	/// Returns `None` for keys outside the view.
+	pub fn get(&self, key: &K) -> Option<&'s V> {
+		let (node_key, value) = self.longest_prefix(key)?;
+		if node_key.len() == key.len() {
+			Some(value)
+		} else {
+			None
+		}
+	}
+
+	/// Returns a reference to the key-value pair for the longest prefix of the key in the view.
+	///
+	/// Returns `None` for keys outside the view. If no prefix of the key is in the
+	/// view and [`with_ancestors`](Self::with_ancestors) was enabled, the longest
+	/// entry covering the view prefix is returned.
+	pub fn most_specific(&self, key: &K) -> Option<(&'s K, &'s V)> {
+		if !is_prefix_of(&self.prefix, key) {
+			return None;
+		}
+		match self.longest_prefix(key) {
+			Some(entry) => Some(entry),
+			None if self.with_ancestors => self.ancestor,
+			None => None,
+		}
+	}
+
+	/// Iterate over all prefixes and their values in the view
+	///
+	/// See [`FullMap::iter_prefix`].
+	pub fn iter(&self) -> IterPrefix<'s, K, V> {
+		IterPrefix::new(self.node)
+	}
+
+	// longest entry in the view on the path to the key
+	fn longest_prefix(&self, key: &K) -> Option<(&'s K, &'s V)> {
+		if !is_prefix_of(&self.prefix, key) {
+			return None;
+		}
+		let mut result = None;
+		let mut next = self.node;
+		while let Some(node) = next.take() {
+			if !is_prefix_of(node.get_key(), key) {
+				break;
+			}
+			if let Some(value) = node.get_value() {
+				result = Some((node.get_key(), value));
+			}
+			let len = node.get_key().len();
+			if len < key.len() {
+				next = node.get_child(key.get(len));
+			}
+		}
+		result
+	}
+}
+
+/// Iterate over entries without other entries between them and some prefix
+///
+/// See [`FullMap::children`] and [`FullMap::roots`].
+pub struct IterChildren<'s, K: BitString + Clone, V> {
+	// subtrees not visited yet; next subtree on top
+	stack: Vec<&'s Node<TpFullMap<K, V>>>,
+}
+
+impl<'s, K: BitString + Clone, V> Iterator for IterChildren<'s, K, V> {
+	type Item = (&'s K, &'s V);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			let node = self.stack.pop()?;
+			if let Some(value) = node.get_value() {
+				// don't descend below entries
+				return Some((node.get_key(), value));
+			}
+			if let Some((left, right)) = node.get_children() {
+				self.stack.push(right);
+				self.stack.push(left);
+			}
+		}
+	}
+}
+
 /// Iterate over all (aggregated) prefixes and their mutable values
 pub struct IterMutMap<'s, K: BitString + Clone, V> {
 	iter: crate::tree::IterMutOwnedInOrder<'s, TpFullMap<K, V>>,
@@ -490,3 +1484,171 @@ impl<'s, K: BitString + Clone, V> Iterator for IterMutMap<'s, K, V> {
 		}
 	}
 }
+
+/// Owning iterator over all prefixes and their values
+///
+/// See [`IntoIterator`] for [`FullMap`].
+pub struct IntoIterMap<K: BitString + Clone, V> {
+	iter: IntoIterInOrder<TpFullMap<K, V>>,
+}
+
+impl<K: BitString + Clone, V> Iterator for IntoIterMap<K, V> {
+	type Item = (K, V);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			// skip nodes that don't have a value
+			if let (key, Some(value)) = self.iter.next()? {
+				return Some((key, value));
+			}
+		}
+	}
+}
+
+/// Iterate over all prefixes with a value
+pub struct Keys<'s, K: BitString + Clone, V> {
+	iter: IterMap<'s, K, V>,
+}
+
+impl<'s, K: BitString + Clone, V> Iterator for Keys<'s, K, V> {
+	type Item = &'s K;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		Some(self.iter.next()?.0)
+	}
+}
+
+/// Iterate over all values
+pub struct Values<'s, K: BitString + Clone, V> {
+	iter: IterMap<'s, K, V>,
+}
+
+impl<'s, K: BitString + Clone, V> Iterator for Values<'s, K, V> {
+	type Item = &'s V;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		Some(self.iter.next()?.1)
+	}
+}
+
+/// Iterate over all mutable values
+pub struct ValuesMut<'s, K: BitString + Clone, V> {
+	iter: IterMutMap<'s, K, V>,
+}
+
+impl<'s, K: BitString + Clone, V> Iterator for ValuesMut<'s, K, V> {
+	type Item = &'s mut V;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		Some(self.iter.next()?.1)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{
+		is_empty_subtree,
+		FullMap,
+	};
+	use alloc::vec::Vec;
+	use bitstring::BitLengthString;
+	use core::net::Ipv4Addr;
+
+	type Ipv4Cidr = BitLengthString<Ipv4Addr>;
+
+	fn c4(a: &str, net: usize) -> Ipv4Cidr {
+		Ipv4Cidr::new(a.parse().unwrap(), net)
+	}
+
+	// no empty node that `compact_if_empty` would remove is left: subtrees
+	// without values are only allowed as leaf placeholders next to a
+	// non-empty sibling below a node with a value
+	fn assert_compact(map: &FullMap<Ipv4Cidr, u32>) {
+		let root = match map.tree.root() {
+			Some(root) => root,
+			None => return,
+		};
+		assert!(!is_empty_subtree(root));
+		let mut stack = alloc::vec![root];
+		while let Some(node) = stack.pop() {
+			if let Some((left, right)) = node.get_children() {
+				for (child, sibling) in [(left, right), (right, left)] {
+					if is_empty_subtree(child) {
+						assert!(child.is_leaf());
+						assert!(node.get_value().is_some());
+						assert!(!is_empty_subtree(sibling));
+					}
+				}
+				stack.push(left);
+				stack.push(right);
+			}
+		}
+	}
+
+	#[test]
+	fn aggregate_prunes() {
+		let mut state = 0x5eed_u32;
+		for _ in 0..200 {
+			let mut map = FullMap::<Ipv4Cidr, u32>::new();
+			for _ in 0..16 {
+				// xorshift
+				state ^= state << 13;
+				state ^= state >> 17;
+				state ^= state << 5;
+				let len = 8 + (state >> 28) as usize;
+				let addr = Ipv4Addr::from(0x0a00_0000 | (state & 0x00ff_ff00));
+				map.insert(Ipv4Cidr::new(addr, len), state % 2);
+			}
+			// keep entries with a different value than the nearest entry above
+			let expected: Vec<(Ipv4Cidr, u32)> = map
+				.iter()
+				.filter(|(key, value)| {
+					let above = map.path((*key).clone()).filter(|(k, _)| k != key).last();
+					above.is_none_or(|(_, above)| above != *value)
+				})
+				.map(|(key, value)| (key.clone(), *value))
+				.collect();
+			map.aggregate();
+			assert_eq!(
+				map.iter()
+					.map(|(key, value)| (key.clone(), *value))
+					.collect::<Vec<_>>(),
+				expected
+			);
+			assert_compact(&map);
+			#[cfg(debug_assertions)]
+			map.tree.check_invariants().unwrap();
+		}
+	}
+
+	#[test]
+	fn invariants_after_mutation() {
+		let mut map = FullMap::<Ipv4Cidr, u32>::new();
+		let check = |map: &FullMap<Ipv4Cidr, u32>| {
+			#[cfg(debug_assertions)]
+			map.tree.check_invariants().unwrap();
+		};
+		map.insert(c4("10.0.0.0", 8), 1);
+		map.insert(c4("10.1.0.0", 16), 2);
+		map.insert(c4("10.1.2.0", 24), 3);
+		map.insert(c4("10.128.0.0", 9), 4);
+		check(&map);
+		assert_eq!(map.remove(&c4("10.1.0.0", 16)), Some(2));
+		check(&map);
+		*map.get_or_insert_with(c4("10.1.2.128", 25), || 5) += 1;
+		check(&map);
+		map.remove_tree(c4("10.1.0.0", 16));
+		check(&map);
+		assert_eq!(map.pop_first().map(|(_, value)| value), Some(1));
+		check(&map);
+		map.insert(c4("10.0.0.0", 8), 1);
+		map.insert(c4("10.1.0.0", 16), 1);
+		map.insert(c4("10.1.2.0", 24), 2);
+		map.insert(c4("10.1.3.0", 24), 1);
+		map.aggregate();
+		check(&map);
+		map.remove_tree(c4("0.0.0.0", 0));
+		check(&map);
+		assert!(map.pop_first().is_none());
+	}
+}
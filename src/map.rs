@@ -1,31 +1,53 @@
 //! [`Map`] of bit string prefixes
 
-use core::{
-	cmp::Ordering,
-	marker::PhantomData,
-};
-
+use alloc::vec::Vec;
 use bitstring::BitString;
 
-use crate::tree::{
-	DefaultCompare,
-	Tree,
-	TreeProperties,
+use crate::{
+	iter::{
+		iter_between,
+		iter_inclusive,
+	},
+	tree::{
+		DefaultCompare,
+		InsertPosition,
+		InsertPositionWith,
+		IntoIterLeaf,
+		LeafValueComparer,
+		Merging,
+		Node,
+		Tree,
+		TreeProperties,
+		TreeStats,
+		WalkedDirection,
+	},
 };
 
-struct TpMap<K, V>(PhantomData<*const K>, PhantomData<*const V>)
-where
-	K: BitString + Clone,
-	V: Default + Clone + Eq;
+mod hidden {
+	use bitstring::BitString;
+	use core::marker::PhantomData;
 
-impl<K, V> TreeProperties for TpMap<K, V>
+	/// make it public so we can use it in returned types, but don't make it directly accessible
+	pub struct TpMap<K, V, C = crate::tree::DefaultCompare>(
+		PhantomData<*const K>,
+		PhantomData<*const V>,
+		PhantomData<*const C>,
+	)
+	where
+		K: BitString + Clone,
+		V: Default + Clone + Eq;
+}
+use hidden::TpMap;
+
+impl<K, V, C> TreeProperties for TpMap<K, V, C>
 where
 	K: BitString + Clone,
 	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
 {
 	type Key = K;
 	type LeafValue = V;
-	type LeafValueComparer = DefaultCompare;
+	type LeafValueComparer = C;
 	type Value = ();
 
 	const EMPTY: bool = true;
@@ -33,77 +55,526 @@ where
 	const LEAF_EMPTY: bool = false;
 }
 
+/// Map with sibling prefixes merged by combining their values with a
+/// [`LeafValueMerger`](crate::tree::LeafValueMerger)
+///
+/// E.g. summing counters of sibling prefixes produces a coarser map on purpose.
+///
+/// Inserting a value for a prefix covered by a shorter prefix still clones
+/// the value of the shorter prefix to the remaining parts.
+pub type MergingMap<K, V, M> = Map<K, V, Merging<M>>;
+
 /// Map of bit strings (combined to prefixes) to values
 ///
 /// Each bit string can only have a single value; sibling bit strings
 /// mapping to the same value are automatically merged internally.
 ///
 /// This is implemented as a [`crate::tree::Tree`] where only leaf nodes carry values.
-#[derive(Clone)]
-pub struct Map<K, V>
+///
+/// Using a different [`LeafValueComparer`] than [`DefaultCompare`] allows
+/// merging sibling prefixes in other cases too; see [`MergingMap`].
+pub struct Map<K, V, C = DefaultCompare>
 where
 	K: BitString + Clone,
 	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
 {
-	tree: Tree<TpMap<K, V>>,
+	tree: Tree<TpMap<K, V, C>>,
 }
 
-impl<K, V> Default for Map<K, V>
+impl<K, V, C> Default for Map<K, V, C>
 where
 	K: BitString + Clone,
 	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
 {
 	fn default() -> Self {
-		Self::new()
+		Self { tree: Tree::new() }
+	}
+}
+
+impl<K, V, C> Clone for Map<K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	fn clone(&self) -> Self {
+		Self {
+			tree: self.tree.clone(),
+		}
+	}
+
+	fn clone_from(&mut self, source: &Self) {
+		self.tree.clone_from(&source.tree);
 	}
 }
 
-impl<K, V> core::fmt::Debug for Map<K, V>
+impl<K, V, C> core::fmt::Debug for Map<K, V, C>
 where
 	K: BitString + Clone + core::fmt::Debug,
 	V: Default + Clone + Eq + core::fmt::Debug,
+	C: LeafValueComparer<V>,
 {
 	fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
 		f.debug_map().entries(self.iter()).finish()
 	}
 }
 
+/// Prints prefixes and values as `{a => 1, b => 2}`; the alternate form (`{:#}`) prints one
+/// `a => 1` pair per line
+impl<K, V, C> core::fmt::Display for Map<K, V, C>
+where
+	K: BitString + Clone + core::fmt::Display,
+	V: Default + Clone + Eq + core::fmt::Display,
+	C: LeafValueComparer<V>,
+{
+	fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+		if f.alternate() {
+			for (key, value) in self.iter() {
+				writeln!(f, "{} => {}", key, value)?;
+			}
+			return Ok(());
+		}
+		f.write_str("{")?;
+		for (ndx, (key, value)) in self.iter().enumerate() {
+			if ndx > 0 {
+				f.write_str(", ")?;
+			}
+			write!(f, "{} => {}", key, value)?;
+		}
+		f.write_str("}")
+	}
+}
+
+// sibling leafs with equal values are merged: equal maps have the same leafs
+// (unless values were modified through `iter_mut`)
+impl<K, V, C> PartialEq for Map<K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	fn eq(&self, other: &Self) -> bool {
+		// `C` doesn't need to be reflexive (e.g. `NoEqual`); compare with `Eq`
+		self.tree.leaf_eq_by(&other.tree, |a, b| a == b)
+	}
+}
+
+impl<K, V, C> Eq for Map<K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+}
+
+/// Serialized as map of (aggregated) prefixes to values, in order
+#[cfg(feature = "serde")]
+impl<K, V, C> serde::Serialize for Map<K, V, C>
+where
+	K: BitString + Clone + serde::Serialize,
+	V: Default + Clone + Eq + serde::Serialize,
+	C: LeafValueComparer<V>,
+{
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.collect_map(self.iter())
+	}
+}
+
+/// Deserialized from a map of prefixes to values; entries are inserted in
+/// the given order, so overlapping or unaggregated input is fine.
+#[cfg(feature = "serde")]
+impl<'de, K, V, C> serde::Deserialize<'de> for Map<K, V, C>
+where
+	K: BitString + Clone + serde::Deserialize<'de>,
+	V: Default + Clone + Eq + serde::Deserialize<'de>,
+	C: LeafValueComparer<V>,
+{
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		struct MapVisitor<K, V, C>(core::marker::PhantomData<(K, V, C)>);
+
+		impl<'de, K, V, C> serde::de::Visitor<'de> for MapVisitor<K, V, C>
+		where
+			K: BitString + Clone + serde::Deserialize<'de>,
+			V: Default + Clone + Eq + serde::Deserialize<'de>,
+			C: LeafValueComparer<V>,
+		{
+			type Value = Map<K, V, C>;
+
+			fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+				f.write_str("a map of prefixes to values")
+			}
+
+			fn visit_map<A: serde::de::MapAccess<'de>>(
+				self,
+				mut access: A,
+			) -> Result<Self::Value, A::Error> {
+				let mut map = Map::default();
+				while let Some((key, value)) = access.next_entry()? {
+					map.insert(key, value);
+				}
+				Ok(map)
+			}
+		}
+
+		deserializer.deserialize_map(MapVisitor(core::marker::PhantomData))
+	}
+}
+
+impl<K, V, C> IntoIterator for Map<K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	type IntoIter = IntoIterMap<K, V, C>;
+	type Item = (K, V);
+
+	fn into_iter(self) -> Self::IntoIter {
+		IntoIterMap {
+			iter: self.tree.into_iter_leaf(),
+		}
+	}
+}
+
+impl<'s, K, V, C> IntoIterator for &'s Map<K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	type IntoIter = IterMap<'s, K, V, C>;
+	type Item = (&'s K, &'s V);
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter()
+	}
+}
+
 impl<K, V> Map<K, V>
 where
 	K: BitString + Clone,
 	V: Default + Clone + Eq,
 {
 	/// New (empty) map.
+	///
+	/// Use [`Default`] to create maps with other [`LeafValueComparer`]s.
 	pub const fn new() -> Self {
 		Self { tree: Tree::new() }
 	}
+}
+
+impl<K, V, C> Map<K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	/// Access raw tree of map
+	pub fn raw_tree(&self) -> &Tree<TpMap<K, V, C>> {
+		&self.tree
+	}
+
+	/// Mutable access to raw tree of map
+	///
+	/// You must keep leafs canonical yourself: sibling leafs with equal values
+	/// have to be merged, otherwise e.g. comparing maps will misbehave.
+	pub fn raw_tree_mut(&mut self) -> &mut Tree<TpMap<K, V, C>> {
+		&mut self.tree
+	}
 
 	/// Set new value for all bit strings with given prefix
-	pub fn insert(&mut self, prefix: K, value: V) {
-		self.tree.set_leaf_value(prefix, value);
+	///
+	/// Returns the (parts of) previous entries that got a different value
+	/// (as compared by `C`), in order; a shorter prefix covering `prefix` is reported as `prefix`
+	/// with the old value.
+	pub fn insert(&mut self, prefix: K, value: V) -> Vec<(K, V)> {
+		self.tree.set_leaf_value_replacing(prefix, value).collect()
+	}
+
+	/// Set new value for all bit strings between `first 0*` and `last 1*`
+	///
+	/// Inserts the prefixes generated by [`iter_inclusive`]; nothing is inserted
+	/// if `first` comes after `last`.
+	pub fn insert_range(&mut self, first: K, last: K, value: V) {
+		for key in iter_inclusive(first, last) {
+			self.tree.set_leaf_value(key, value.clone());
+		}
+	}
+
+	/// Set new value for all bit strings with given prefix, merging with
+	/// existing values
+	///
+	/// For each existing (aggregated) prefix overlapping the given prefix
+	/// `merge` is called with the overlapping part, the new and the
+	/// existing value to calculate the resulting value; parts of the given
+	/// prefix that weren't mapped get the new value.
+	pub fn insert_with<F>(&mut self, prefix: K, value: V, mut merge: F)
+	where
+		F: FnMut(&K, V, &V) -> V,
+	{
+		self.tree
+			.set_leaf_value_with(prefix, value.clone(), |key, old| {
+				merge(key, value.clone(), old)
+			});
+	}
+
+	/// Set value for all unmapped bit strings with given prefix
+	///
+	/// Existing (aggregated) prefixes overlapping the given prefix are kept
+	/// unchanged; only the gaps between them get the new value.
+	///
+	/// Returns whether anything was inserted.
+	pub fn insert_if_absent(&mut self, prefix: K, value: V) -> bool {
+		let gaps: Vec<K> = self
+			.view(prefix)
+			.iter_full()
+			.filter_map(|(key, old)| match old {
+				None => Some(key),
+				Some(_) => None,
+			})
+			.collect();
+		for key in &gaps {
+			self.tree.set_leaf_value(key.clone(), value.clone());
+		}
+		!gaps.is_empty()
+	}
+
+	/// Create map with values transformed by `f`
+	///
+	/// Keeps the structure of this map; only siblings which `f` maps to
+	/// equal values are merged.
+	pub fn map_values<W, F>(&self, mut f: F) -> Map<K, W>
+	where
+		W: Default + Clone + Eq,
+		F: FnMut(&K, &V) -> W,
+	{
+		Map {
+			tree: Tree::from_root(self.tree.root().map(|root| map_node(root, &mut f))),
+		}
+	}
+
+	/// Transform values by `f`, consuming the map
+	///
+	/// See [`map_values`](Self::map_values).
+	pub fn into_map_values<W, F>(self, mut f: F) -> Map<K, W>
+	where
+		W: Default + Clone + Eq,
+		F: FnMut(&K, V) -> W,
+	{
+		Map {
+			tree: Tree::from_root(
+				self.tree
+					.into_root()
+					.map(|root| into_map_node(root, &mut f)),
+			),
+		}
+	}
+
+	/// Merge values from another map
+	///
+	/// Where both maps have a value `combine` is called with the overlapping
+	/// prefix, the value in this map and the value in the other map to
+	/// calculate the resulting value; all other values are copied from the
+	/// map having them.
+	///
+	/// Overlapping prefixes are split along the leaf boundaries of both maps.
+	///
+	/// Walks both trees in parallel; subtrees only present in `other` are
+	/// copied as a whole.
+	pub fn merge<F>(&mut self, other: &Self, mut combine: F)
+	where
+		F: FnMut(&K, &V, &V) -> V,
+	{
+		let other_root = match other.tree.root() {
+			None => return,
+			Some(root) => root,
+		};
+		let mut len = self.len();
+		let root = match core::mem::take(&mut self.tree).into_root() {
+			None => {
+				len = other.len();
+				other_root.clone()
+			},
+			Some(root) => merge_nodes(root, other_root, &mut combine, &mut len),
+		};
+		self.tree = Tree::from_root_counted(Some(root), len);
+	}
+
+	/// Number of (aggregated) prefixes in the map
+	///
+	/// Sibling prefixes with equal values are merged, so this is the number
+	/// of items [`iter`](Self::iter) yields, not the number of inserted
+	/// prefixes.
+	///
+	/// See [`Tree::leaf_count`]: runs in constant time unless the tree was
+	/// modified through mutable node references (e.g. with
+	/// [`raw_tree_mut`](Self::raw_tree_mut)); the prefixes are counted once
+	/// then.
+	pub fn len(&self) -> usize {
+		self.tree.leaf_count()
+	}
+
+	/// Whether map is empty
+	///
+	/// Runs in constant time.
+	pub fn is_empty(&self) -> bool {
+		self.tree.root().is_none()
+	}
+
+	/// Structural statistics of the underlying tree
+	///
+	/// See [`Tree::stats`].
+	pub fn stats(&self) -> TreeStats {
+		self.tree.stats()
+	}
+
+	/// Longest bit string that is a prefix of all prefixes in the map
+	///
+	/// Returns `None` if the map is empty.
+	pub fn longest_common_prefix(&self) -> Option<K> {
+		Some(self.tree.root()?.get_key().clone())
+	}
+
+	/// Remove all prefixes (and their values) from map
+	pub fn clear(&mut self) {
+		self.tree.clear();
+	}
+
+	/// Unset values for all bit strings between `first 0*` and `last 1*`
+	///
+	/// Leafs crossing the range boundaries are split, keeping their value
+	/// outside the range. Nothing is removed if `first` comes after `last`.
+	pub fn remove_range(&mut self, first: K, last: K) {
+		for key in iter_inclusive(first, last) {
+			self.remove(key);
+		}
+	}
+
+	/// Modify values of all bit strings with given prefix
+	///
+	/// A shorter prefix covering `prefix` is split first, so only the value
+	/// for `prefix` is modified. Sibling prefixes ending up with equal values
+	/// are merged afterwards.
+	pub fn update_range<F>(&mut self, prefix: &K, mut f: F)
+	where
+		F: FnMut(&K, &mut V),
+	{
+		let mut walk = self.tree.walk_mut_counted::<WalkedDirection, ()>();
+		match walk.goto_insert(prefix) {
+			None => return, // empty tree
+			Some(InsertPosition::BelowLeaf) => {
+				// node is a leaf and covers prefix; split it
+				let value = walk
+					.current()
+					.node()
+					.and_then(Node::get_leaf_value)
+					.expect("should be at leaf")
+					.clone();
+				walk.insert(prefix.clone()).set_leaf_value(value);
+			},
+			Some(InsertPosition::AlreadyExists) => (),
+			Some(InsertPosition::ReplaceNode) => {
+				let node = walk.current().node().expect("should be at node");
+				if node.get_key().shared_prefix_len(prefix) < prefix.len() {
+					// node not in subtree of prefix
+					return;
+				}
+			},
+		}
+		let merged = update_node(
+			walk.current_mut().node().expect("should be at node"),
+			&mut f,
+		);
+		walk.leafs_changed(0, merged);
+		// values changed; parent nodes might be able to merge now
+		while walk.up().is_some() {
+			walk.compress();
+		}
+	}
+
+	/// Only keep prefixes (leafs) for which `f` returns `true`
+	///
+	/// `f` may also modify the values; sibling leafs ending up with equal
+	/// values are merged afterwards.
+	pub fn retain<F>(&mut self, mut f: F)
+	where
+		F: FnMut(&K, &mut V) -> bool,
+	{
+		let mut walk = self.tree.walk_mut_counted();
+		let mut next = walk.next_leaf().is_some();
+		while next {
+			let node = walk.current_mut().node().expect("should be at leaf");
+			let (key, value) = node.get_key_leaf_value_mut();
+			if f(key, value.expect("should be at leaf")) {
+				next = walk.next_leaf().is_some();
+				continue;
+			}
+			// replaces parent with sibling; current points to sibling afterwards
+			match walk.delete_current() {
+				None | Some(WalkedDirection::Down) => break, // tree empty now
+				Some(WalkedDirection::Left) => {
+					// sibling (right side) not visited yet
+					while walk.down_left() {}
+					next = true;
+				},
+				Some(WalkedDirection::Right) => {
+					// sibling (left side) already visited
+					next = walk.next_leaf().is_some();
+				},
+			}
+		}
+		self.normalize();
 	}
 
 	/// Unset values for all bit strings with given prefix
 	pub fn remove(&mut self, key: K) {
-		let mut walk = self.tree.walk_mut();
-		walk.goto_insert(&key);
-		match walk.current().node() {
-			None => (), // empty tree
-			Some(node) => {
-				match node.get_key().len().cmp(&key.len()) {
-					Ordering::Less => {
-						// node is a leaf and covers key; need to split and remove key
-						// create explicit node with key we want to remove
-						walk.insert(key);
-						// now remove it
-						walk.delete_current();
-					},
-					Ordering::Equal | Ordering::Greater => {
-						// remove subtree
-						walk.delete_current();
-					},
+		self.tree.delete(&key);
+	}
+
+	/// Move everything covered by prefix into a new map
+	///
+	/// A shorter prefix covering `prefix` is split: the returned map
+	/// maps exactly `prefix` to its value, and `self` keeps the rest.
+	pub fn split_off(&mut self, prefix: &K) -> Self {
+		let mut walk = self.tree.walk_mut_counted();
+		let subtree = match walk.goto_insert(prefix) {
+			None => return Self::default(), // empty tree
+			Some(InsertPosition::BelowLeaf) => {
+				// node is a leaf and covers prefix; need to split and detach prefix
+				let value = walk
+					.current()
+					.node()
+					.and_then(|node| node.get_leaf_value())
+					.expect("should be at leaf")
+					.clone();
+				// create explicit node with prefix we want to detach
+				walk.insert(prefix.clone());
+				walk.delete_current();
+				return Self {
+					tree: Tree::from_root(Some(Node::new_leaf(prefix.clone(), (), value))),
+				};
+			},
+			Some(InsertPosition::AlreadyExists) => {
+				walk.current_mut().node().expect("should be at node")
+			},
+			Some(InsertPosition::ReplaceNode) => {
+				let node = walk.current_mut().node().expect("should be at node");
+				if node.get_key().shared_prefix_len(prefix) < prefix.len() {
+					// node not in subtree of prefix
+					return Self::default();
 				}
+				node
 			},
+		};
+		// detach subtree, leaving a placeholder leaf to delete
+		let subtree = core::mem::replace(subtree, Node::new_leaf(prefix.clone(), (), V::default()));
+		let removed = subtree.leaf_count();
+		walk.leafs_changed(1, removed);
+		walk.delete_current();
+		Self {
+			tree: Tree::from_root_counted(Some(subtree), removed),
 		}
 	}
 
@@ -113,43 +584,296 @@ where
 	/// an aggregated value, i.e. lookups should usually be done
 	/// using a "full-length" bit string.
 	/// (E.g. lookup single hosts in a CIDR-map.)
+	///
+	/// This is a covering lookup: a value stored for a shorter prefix is
+	/// returned too; use [`get_key_value`](Self::get_key_value) to only
+	/// match exact entries.
 	pub fn get(&self, key: &K) -> Option<&V> {
-		let mut walk = self.tree.walk::<(), ()>();
-		walk.goto_insert(key);
-		match walk.current().node() {
-			None => None, // empty tree
-			Some(node) => {
-				match node.get_key().len().cmp(&key.len()) {
-					Ordering::Less => {
-						// node is a leaf and covers key
-						Some(node.get_leaf_value().expect("node must be a leaf"))
-					},
-					Ordering::Equal => node.get_leaf_value(),
-					Ordering::Greater => {
-						// key not fully contained
-						None
-					},
-				}
+		Some(self.get_covering(key)?.1)
+	}
+
+	/// Lookup (aggregated) prefix covering a bit string and its value
+	///
+	/// Like [`get`](Self::get), but also returns the prefix the value was
+	/// found for (either `key` itself or a shorter prefix).
+	///
+	/// Returns `None` if `key` is shorter than the (aggregated) prefixes
+	/// stored for it, i.e. if it isn't covered by a single entry.
+	pub fn get_covering(&self, key: &K) -> Option<(&K, &V)> {
+		let node = match self.tree.goto_insert(key)? {
+			InsertPositionWith::BelowLeaf(node) => node,
+			InsertPositionWith::AlreadyExists(node) => node,
+			InsertPositionWith::ReplaceNode(_) => return None,
+		};
+		Some((node.get_key(), node.get_leaf_value()?))
+	}
+
+	/// Get mutable reference to value of exact prefix, inserting it if needed
+	///
+	/// If `key` isn't stored as exact prefix its previous coverage is replaced
+	/// by a single entry for `key` with the value from `default`; a shorter
+	/// prefix covering `key` is split.
+	///
+	/// The entry for `key` is created even if it could be merged with its
+	/// sibling; call [`normalize`](Self::normalize) afterwards if needed.
+	pub fn get_or_insert_with<F>(&mut self, key: K, default: F) -> &mut V
+	where
+		F: FnOnce() -> V,
+	{
+		self.get_or_insert_exact(key, |_| Some(default()))
+			.expect("value inserted")
+	}
+
+	/// Get mutable reference to value of exact prefix, splitting a shorter
+	/// covering prefix if needed
+	///
+	/// The new entry for `key` starts with a clone of the covering value.
+	/// Returns `None` (without modifying the map) if `key` isn't covered.
+	///
+	/// The entry for `key` is created even if it could be merged with its
+	/// sibling; call [`normalize`](Self::normalize) afterwards if needed.
+	pub fn get_or_clone_covering(&mut self, key: K) -> Option<&mut V> {
+		self.get_or_insert_exact(key, |covering| covering.cloned())
+	}
+
+	// `value` gets the covering value (if there is one) and returns the value for
+	// a new leaf; not called if the exact leaf already exists.
+	fn get_or_insert_exact<F>(&mut self, key: K, value: F) -> Option<&mut V>
+	where
+		F: FnOnce(Option<&V>) -> Option<V>,
+	{
+		let mut walk = self.tree.walk_mut_counted::<WalkedDirection, ()>();
+		let value = match walk.goto_insert(&key) {
+			Some(InsertPosition::AlreadyExists)
+				if walk.current().node().is_some_and(Node::is_leaf) =>
+			{
+				None
+			},
+			Some(InsertPosition::BelowLeaf) => {
+				let covering = walk.current().node().and_then(Node::get_leaf_value);
+				Some(value(covering)?)
+			},
+			_ => Some(value(None)?),
+		};
+		// splits covering leaf into a chain if needed
+		let node = walk.insert(key);
+		if let Some(value) = value {
+			// an inner node gets replaced by a leaf
+			let removed = node.leaf_count();
+			node.set_leaf_value(value);
+			walk.leafs_changed(1, removed);
+		}
+		walk.into_current_mut()
+			.node()
+			.and_then(Node::get_leaf_value_mut)
+	}
+
+	/// Whether the given prefix is stored exactly as (aggregated) prefix in the map
+	///
+	/// See [`get_key_value`](Self::get_key_value).
+	pub fn contains_key(&self, key: &K) -> bool {
+		self.get_key_value(key).is_some()
+	}
+
+	/// Whether all bit strings with the given prefix map to a single value
+	///
+	/// Same as `get(key).is_some()`, see [`get_covering`](Self::get_covering).
+	pub fn covers(&self, key: &K) -> bool {
+		self.get_covering(key).is_some()
+	}
+
+	/// Longest-prefix lookup: find prefix containing a bit string and its value
+	///
+	/// Same as [`get_covering`](Self::get_covering); returns `None` if `key`
+	/// isn't fully contained in a single prefix of the map.
+	pub fn lookup(&self, key: &K) -> Option<(&K, &V)> {
+		self.get_covering(key)
+	}
+
+	/// Lookup (aggregated) prefix exactly matching a bit string and its value
+	///
+	/// Returns `None` if the bit string is only covered by a shorter prefix.
+	pub fn get_key_value(&self, key: &K) -> Option<(&K, &V)> {
+		let node = self.tree.get(key)?;
+		if !node.is_leaf() {
+			return None;
+		}
+		Some((node.get_key(), node.get_leaf_value()?))
+	}
+
+	/// Gets the given key's corresponding entry in the map for in-place manipulation.
+	pub fn entry(&mut self, key: K) -> Entry<'_, K, V, C> {
+		match self.tree.goto_insert(&key) {
+			Some(InsertPositionWith::AlreadyExists(node)) if node.is_leaf() => {
+				Entry::Occupied(OccupiedEntry {
+					tree: &mut self.tree,
+					key,
+				})
 			},
+			Some(InsertPositionWith::BelowLeaf(_)) => Entry::Covered(CoveredEntry {
+				tree: &mut self.tree,
+				key,
+			}),
+			_ => Entry::Vacant(VacantEntry {
+				tree: &mut self.tree,
+				key,
+			}),
+		}
+	}
+
+	/// Smallest (aggregated) prefix in map (in-order) and its value
+	pub fn first_key_value(&self) -> Option<(&K, &V)> {
+		let node = self.tree.first_leaf()?;
+		Some((node.get_key(), node.get_leaf_value().expect("leaf node")))
+	}
+
+	/// Largest (aggregated) prefix in map (in-order) and its value
+	pub fn last_key_value(&self) -> Option<(&K, &V)> {
+		let node = self.tree.last_leaf()?;
+		Some((node.get_key(), node.get_leaf_value().expect("leaf node")))
+	}
+
+	/// Remove and return the smallest (aggregated) prefix in map (in-order) and its value
+	pub fn pop_first(&mut self) -> Option<(K, V)> {
+		self.pop_side(false)
+	}
+
+	/// Remove and return the largest (aggregated) prefix in map (in-order) and its value
+	pub fn pop_last(&mut self) -> Option<(K, V)> {
+		self.pop_side(true)
+	}
+
+	// removing a leaf only hoists its sibling; that never creates new
+	// mergeable siblings.
+	fn pop_side(&mut self, right: bool) -> Option<(K, V)> {
+		let mut walk = self.tree.walk_mut_counted::<WalkedDirection, ()>();
+		if !walk.down_root() {
+			return None; // empty tree
 		}
+		while walk.down(right) {}
+		let node = walk.current_mut().node().expect("should be at leaf");
+		// move leaf out, leaving a placeholder to delete
+		let leaf = core::mem::replace(node, Node::new_leaf(K::null(), (), V::default()));
+		walk.delete_current();
+		let (key, value) = leaf.into_key_leaf_value();
+		Some((key, value.expect("leaf node")))
 	}
 
 	/// Iterate over all (aggregated) prefixes and their values
-	pub fn iter(&self) -> IterMap<'_, K, V> {
+	pub fn iter(&self) -> IterMap<'_, K, V, C> {
 		IterMap {
 			iter: self.tree.iter_leaf(),
 		}
 	}
 
 	/// Iterate over all (aggregated) prefixes and their mutable values
-	pub fn iter_mut(&mut self) -> IterMutMap<'_, K, V> {
+	///
+	/// Sibling prefixes modified to have equal values are not merged; call
+	/// [`normalize`](Self::normalize) afterwards.
+	pub fn iter_mut(&mut self) -> IterMutMap<'_, K, V, C> {
 		IterMutMap {
 			iter: self.tree.iter_mut_leaf(),
 		}
 	}
 
+	/// Iterate over smallest list of bit strings that cover everything with a mutable value or None if not mapped
+	///
+	/// Sibling prefixes modified to have equal values are not merged; call
+	/// [`normalize`](Self::normalize) afterwards.
+	pub fn iter_full_mut(&mut self) -> IterMutMapFull<'_, K, V, C> {
+		IterMutMapFull {
+			iter: self.tree.iter_mut_leaf_full(),
+		}
+	}
+
+	/// Merge sibling prefixes with equal values
+	///
+	/// Only needed after modifying values through mutable references (e.g.
+	/// [`iter_mut`](Self::iter_mut)); all other operations keep the map
+	/// aggregated.
+	pub fn normalize(&mut self) {
+		if let (Some(root), leaf_count) = self.tree.root_mut_counted() {
+			*leaf_count -= compress_node(root);
+		}
+	}
+
+	/// Iterate over smallest list of bit strings where the maps differ
+	///
+	/// Changes are reported from this map to `other`, i.e. [`Change::Added`]
+	/// means the value is only present in `other`. Nothing is returned for
+	/// identical maps.
+	///
+	/// Walks both trees simultaneously; subtrees with the same keys and leaf
+	/// values are skipped without descending into them.
+	pub fn diff<'s>(&'s self, other: &'s Self) -> IterMapDiff<'s, K, V, C> {
+		let mut stack = Vec::new();
+		let equal = match (self.tree.root(), other.tree.root()) {
+			(None, None) => true,
+			(Some(a), Some(b)) => a.subtree_leaf_eq(b),
+			_ => false,
+		};
+		if !equal {
+			let null = K::null();
+			stack.push((
+				K::null(),
+				MapPart::new_in(&self.tree, &null),
+				MapPart::new_in(&other.tree, &null),
+			));
+		}
+		IterMapDiff { stack }
+	}
+
+	/// Read-only view of the part of the map with the given prefix
+	pub fn view(&self, prefix: K) -> MapView<'_, K, V, C> {
+		MapView { map: self, prefix }
+	}
+
+	/// Iterate over all (aggregated) prefixes and their values that have the given prefix
+	///
+	/// If the given prefix is covered by a shorter prefix in the map, only that
+	/// covering (shorter) prefix and its value is returned.
+	pub fn iter_in(&self, prefix: &K) -> IterMapIn<'_, K, V, C> {
+		IterMapIn {
+			iter: self.tree.iter_leaf_in(prefix),
+		}
+	}
+
+	/// Iterate over maximal ranges of consecutive bit strings of length `full_len` with equal values
+	///
+	/// Each range is returned as its (inclusive) first and last bit string and
+	/// the value; adjacent prefixes with equal values are combined even if they
+	/// aren't siblings in the tree.
+	///
+	/// # Panics
+	///
+	/// Panics if the map contains a prefix longer than `full_len`.
+	pub fn iter_ranges(&self, full_len: usize) -> IterMapRanges<'_, K, V, C> {
+		IterMapRanges {
+			iter: self.iter(),
+			full_len,
+			next: None,
+		}
+	}
+
+	/// Iterate over all (aggregated) prefixes
+	pub fn keys(&self) -> Keys<'_, K, V, C> {
+		Keys { iter: self.iter() }
+	}
+
+	/// Iterate over values of all (aggregated) prefixes
+	pub fn values(&self) -> Values<'_, K, V, C> {
+		Values { iter: self.iter() }
+	}
+
+	/// Iterate over mutable values of all (aggregated) prefixes
+	pub fn values_mut(&mut self) -> ValuesMut<'_, K, V, C> {
+		ValuesMut {
+			iter: self.iter_mut(),
+		}
+	}
+
 	/// Iterate over smallest list of bit strings that cover everything with a value or None if not mapped
-	pub fn iter_full(&self) -> IterMapFull<'_, K, V> {
+	pub fn iter_full(&self) -> IterMapFull<'_, K, V, C> {
 		IterMapFull {
 			iter: self.tree.iter_leaf_full(),
 		}
@@ -157,18 +881,20 @@ where
 }
 
 /// Iterate over all (aggregated) prefixes and their values
-pub struct IterMap<'s, K, V>
+pub struct IterMap<'s, K, V, C>
 where
 	K: BitString + Clone,
 	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
 {
-	iter: crate::tree::IterLeaf<'s, TpMap<K, V>>,
+	iter: crate::tree::IterLeaf<'s, TpMap<K, V, C>>,
 }
 
-impl<'s, K, V> Iterator for IterMap<'s, K, V>
+impl<'s, K, V, C> Iterator for IterMap<'s, K, V, C>
 where
 	K: BitString + Clone,
 	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
 {
 	type Item = (&'s K, &'s V);
 
@@ -176,46 +902,1115 @@ where
 		let (node, value) = self.iter.next()?;
 		Some((node.get_key(), value))
 	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.iter.size_hint()
+	}
 }
 
-/// Iterate over all (aggregated) prefixes and their mutable values
-pub struct IterMutMap<'s, K, V>
+impl<'s, K, V, C> ExactSizeIterator for IterMap<'s, K, V, C>
 where
 	K: BitString + Clone,
 	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
 {
-	iter: crate::tree::IterMutOwnedLeaf<'s, TpMap<K, V>>,
 }
 
-impl<'s, K, V> Iterator for IterMutMap<'s, K, V>
+impl<'s, K, V, C> DoubleEndedIterator for IterMap<'s, K, V, C>
 where
 	K: BitString + Clone,
 	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
 {
-	type Item = (&'s K, &'s mut V);
-
-	fn next(&mut self) -> Option<Self::Item> {
-		self.iter.next()
+	fn next_back(&mut self) -> Option<Self::Item> {
+		let (node, value) = self.iter.next_back()?;
+		Some((node.get_key(), value))
 	}
 }
 
-/// Iterate over smallest list of bit strings that cover everything with a value or None if not mapped
-pub struct IterMapFull<'s, K, V>
+/// Iterate over all (aggregated) prefixes and their values below some prefix
+///
+/// See [`Map::iter_in`].
+pub struct IterMapIn<'s, K, V, C>
 where
 	K: BitString + Clone,
 	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
 {
-	iter: crate::tree::IterLeafFull<'s, TpMap<K, V>>,
+	iter: crate::tree::IterLeafIn<'s, TpMap<K, V, C>>,
 }
 
-impl<'s, K, V> Iterator for IterMapFull<'s, K, V>
+impl<'s, K, V, C> Iterator for IterMapIn<'s, K, V, C>
 where
 	K: BitString + Clone,
 	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
 {
-	type Item = (K, Option<&'s V>);
+	type Item = (&'s K, &'s V);
 
 	fn next(&mut self) -> Option<Self::Item> {
-		self.iter.next()
+		let (node, value) = self.iter.next()?;
+		Some((node.get_key(), value))
+	}
+}
+
+/// Iterate over maximal ranges of consecutive bit strings with equal values in a map
+///
+/// See [`Map::iter_ranges`].
+pub struct IterMapRanges<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	iter: IterMap<'s, K, V, C>,
+	full_len: usize,
+	// first and last prefix of range not returned yet, and its value
+	next: Option<(&'s K, &'s K, &'s V)>,
+}
+
+impl<'s, K, V, C> IterMapRanges<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	fn extend(&self, key: &K, bit: bool) -> K {
+		assert!(key.len() <= self.full_len, "prefix longer than full_len");
+		let mut key = key.clone();
+		while key.len() < self.full_len {
+			key.append(bit);
+		}
+		key
+	}
+
+	fn range(&self, first: &K, last: &K, value: &'s V) -> (K, K, &'s V) {
+		(self.extend(first, false), self.extend(last, true), value)
+	}
+}
+
+impl<'s, K, V, C> Iterator for IterMapRanges<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	type Item = (K, K, &'s V);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			match self.iter.next() {
+				None => {
+					let (first, last, value) = self.next.take()?;
+					return Some(self.range(first, last, value));
+				},
+				Some((key, key_value)) => match self.next {
+					None => self.next = Some((key, key, key_value)),
+					Some((first, last, value)) => {
+						if C::eq(value, key_value)
+							&& iter_between(Some(last.clone()), Some(key.clone()))
+								.next()
+								.is_none()
+						{
+							// nothing between previous and current prefix: extend range
+							self.next = Some((first, key, value));
+						} else {
+							self.next = Some((key, key, key_value));
+							return Some(self.range(first, last, value));
+						}
+					},
+				},
+			}
+		}
+	}
+}
+
+/// Iterate over all (aggregated) prefixes and their mutable values
+pub struct IterMutMap<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	iter: crate::tree::IterMutOwnedLeaf<'s, TpMap<K, V, C>>,
+}
+
+impl<'s, K, V, C> Iterator for IterMutMap<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	type Item = (&'s K, &'s mut V);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.iter.next()
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.iter.size_hint()
+	}
+}
+
+/// Iterate over all (aggregated) prefixes and their values, consuming the map
+pub struct IntoIterMap<K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	iter: IntoIterLeaf<TpMap<K, V, C>>,
+}
+
+impl<K, V, C> Iterator for IntoIterMap<K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	type Item = (K, V);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.iter.next()
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.iter.size_hint()
+	}
+}
+
+impl<K, V, C> ExactSizeIterator for IntoIterMap<K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+}
+
+/// Difference for a prefix between two maps
+///
+/// See [`Map::diff`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Change<'s, V> {
+	/// Only mapped in the other map
+	Added(&'s V),
+	/// Only mapped in the first map
+	Removed(&'s V),
+	/// Mapped to different values (first map, other map)
+	Modified(&'s V, &'s V),
+}
+
+// part of a map within some prefix
+enum MapPart<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	// everything has the same value (or is unmapped)
+	Uniform(Option<&'s V>),
+	// subtree with key having the prefix; rest is unmapped
+	Tree(&'s Node<TpMap<K, V, C>>),
+}
+
+impl<'s, K, V, C> MapPart<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	// part of tree within prefix
+	fn new_in(tree: &'s Tree<TpMap<K, V, C>>, prefix: &K) -> Self {
+		match tree.goto_insert(prefix) {
+			None => Self::Uniform(None),
+			Some(InsertPositionWith::BelowLeaf(node)) => Self::Uniform(node.get_leaf_value()),
+			Some(InsertPositionWith::AlreadyExists(node)) => Self::Tree(node),
+			Some(InsertPositionWith::ReplaceNode(node)) => {
+				if node.get_key().shared_prefix_len(prefix) == prefix.len() {
+					Self::Tree(node)
+				} else {
+					Self::Uniform(None)
+				}
+			},
+		}
+	}
+
+	// treat leaf with exactly the prefix as uniform
+	fn resolve(self, prefix: &K) -> Self {
+		match self {
+			Self::Tree(node) if node.get_key().len() == prefix.len() => {
+				match node.get_leaf_value() {
+					Some(value) => Self::Uniform(Some(value)),
+					None => self,
+				}
+			},
+			_ => self,
+		}
+	}
+
+	// split into parts for both halves of the prefix
+	fn split(self, prefix: &K) -> (Self, Self) {
+		match self {
+			Self::Uniform(value) => (Self::Uniform(value), Self::Uniform(value)),
+			Self::Tree(node) => match node.get_children() {
+				Some((left, right)) if node.get_key().len() == prefix.len() => {
+					(Self::Tree(left), Self::Tree(right))
+				},
+				_ => {
+					if node.get_key().get(prefix.len()) {
+						(Self::Uniform(None), self)
+					} else {
+						(self, Self::Uniform(None))
+					}
+				},
+			},
+		}
+	}
+}
+
+// prefix with the parts of both maps within it
+type DiffStep<'s, K, V, C> = (K, MapPart<'s, K, V, C>, MapPart<'s, K, V, C>);
+
+/// Read-only view of the part of a map with some prefix
+///
+/// See [`Map::view`]. Keys are reported as stored in the map; if a shorter
+/// prefix covers the view prefix it is the only entry.
+pub struct MapView<'s, K, V, C = DefaultCompare>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	map: &'s Map<K, V, C>,
+	prefix: K,
+}
+
+impl<'s, K, V, C> MapView<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	/// Prefix of the view
+	pub fn prefix(&self) -> &K {
+		&self.prefix
+	}
+
+	/// Lookup value for a bit string with the view prefix
+	///
+	/// Returns `None` for bit strings outside the view; see [`Map::get`].
+	pub fn get(&self, key: &K) -> Option<&'s V> {
+		if key.shared_prefix_len(&self.prefix) < self.prefix.len() {
+			return None;
+		}
+		self.map.get(key)
+	}
+
+	/// Iterate over all (aggregated) prefixes and their values in the view
+	///
+	/// See [`Map::iter_in`].
+	pub fn iter(&self) -> IterMapIn<'s, K, V, C> {
+		self.map.iter_in(&self.prefix)
+	}
+
+	/// Iterate over smallest list of bit strings that cover the view prefix with a value or None if not mapped
+	///
+	/// If a shorter prefix covers the view prefix this only returns the view
+	/// prefix with its value.
+	pub fn iter_full(&self) -> IterMapViewFull<'s, K, V, C> {
+		IterMapViewFull {
+			stack: alloc::vec![(
+				self.prefix.clone(),
+				MapPart::new_in(&self.map.tree, &self.prefix),
+			)],
+		}
+	}
+}
+
+/// Iterate over smallest list of bit strings that cover a view with a value or None if not mapped
+///
+/// See [`MapView::iter_full`].
+pub struct IterMapViewFull<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	// prefixes still to visit; next on top
+	stack: Vec<(K, MapPart<'s, K, V, C>)>,
+}
+
+impl<'s, K, V, C> Iterator for IterMapViewFull<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	type Item = (K, Option<&'s V>);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		while let Some((prefix, part)) = self.stack.pop() {
+			match part.resolve(&prefix) {
+				MapPart::Uniform(value) => return Some((prefix, value)),
+				part => {
+					let (left_part, right_part) = part.split(&prefix);
+					let mut left = prefix.clone();
+					left.append(false);
+					let mut right = prefix;
+					right.append(true);
+					self.stack.push((right, right_part));
+					self.stack.push((left, left_part));
+				},
+			}
+		}
+		None
+	}
+}
+
+/// Iterate over smallest list of bit strings where two maps differ
+///
+/// See [`Map::diff`].
+pub struct IterMapDiff<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	// prefixes still to compare; next on top
+	stack: Vec<DiffStep<'s, K, V, C>>,
+}
+
+impl<'s, K, V, C> Iterator for IterMapDiff<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	type Item = (K, Change<'s, V>);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		while let Some((prefix, a, b)) = self.stack.pop() {
+			// both maps are unmapped outside of the subtrees: jump straight to the
+			// longest prefix covering them instead of splitting bit by bit
+			let prefix = match (&a, &b) {
+				(MapPart::Tree(a), MapPart::Tree(b)) => {
+					let mut key = a.get_key().clone();
+					key.clip(key.shared_prefix_len(b.get_key()));
+					key
+				},
+				(MapPart::Tree(node), MapPart::Uniform(None))
+				| (MapPart::Uniform(None), MapPart::Tree(node)) => node.get_key().clone(),
+				_ => prefix,
+			};
+			let change = match (a.resolve(&prefix), b.resolve(&prefix)) {
+				(MapPart::Uniform(a), MapPart::Uniform(b)) => match (a, b) {
+					(None, None) => continue,
+					(Some(a), None) => Change::Removed(a),
+					(None, Some(b)) => Change::Added(b),
+					(Some(a), Some(b)) if C::eq(a, b) => continue,
+					(Some(a), Some(b)) => Change::Modified(a, b),
+				},
+				// equal subtrees (same keys, shape and leaf values)
+				(MapPart::Tree(a), MapPart::Tree(b)) if a.subtree_leaf_eq(b) => continue,
+				(a, b) => {
+					let (a_left, a_right) = a.split(&prefix);
+					let (b_left, b_right) = b.split(&prefix);
+					let mut left = prefix.clone();
+					left.append(false);
+					let mut right = prefix;
+					right.append(true);
+					self.stack.push((right, a_right, b_right));
+					self.stack.push((left, a_left, b_left));
+					continue;
+				},
+			};
+			return Some((prefix, change));
+		}
+		None
+	}
+}
+
+/// Iterate over all (aggregated) prefixes of a map
+pub struct Keys<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	iter: IterMap<'s, K, V, C>,
+}
+
+impl<'s, K, V, C> Iterator for Keys<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	type Item = &'s K;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		Some(self.iter.next()?.0)
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.iter.size_hint()
+	}
+}
+
+impl<'s, K, V, C> ExactSizeIterator for Keys<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+}
+
+/// Iterate over values of all (aggregated) prefixes of a map
+pub struct Values<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	iter: IterMap<'s, K, V, C>,
+}
+
+impl<'s, K, V, C> Iterator for Values<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	type Item = &'s V;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		Some(self.iter.next()?.1)
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.iter.size_hint()
+	}
+}
+
+impl<'s, K, V, C> ExactSizeIterator for Values<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+}
+
+/// Iterate over mutable values of all (aggregated) prefixes of a map
+pub struct ValuesMut<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	iter: IterMutMap<'s, K, V, C>,
+}
+
+impl<'s, K, V, C> Iterator for ValuesMut<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	type Item = &'s mut V;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		Some(self.iter.next()?.1)
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.iter.size_hint()
+	}
+}
+
+/// Iterate over smallest list of bit strings that cover everything with a value or None if not mapped
+pub struct IterMapFull<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	iter: crate::tree::IterLeafFull<'s, TpMap<K, V, C>>,
+}
+
+impl<'s, K, V, C> Iterator for IterMapFull<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	type Item = (K, Option<&'s V>);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.iter.next()
+	}
+}
+
+/// Iterate over smallest list of bit strings that cover everything with a mutable value or None if not mapped
+pub struct IterMutMapFull<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	iter: crate::tree::IterMutOwnedLeafFull<'s, TpMap<K, V, C>>,
+}
+
+impl<'s, K, V, C> Iterator for IterMutMapFull<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	type Item = (K, Option<&'s mut V>);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.iter.next()
+	}
+}
+
+// copy structure with transformed values; `new_parent` merges leafs with equal values
+fn map_node<K, V, C, W, F>(node: &Node<TpMap<K, V, C>>, f: &mut F) -> Node<TpMap<K, W>>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+	W: Default + Clone + Eq,
+	F: FnMut(&K, &V) -> W,
+{
+	match node.get_children() {
+		Some((left, right)) => Node::new_parent(map_node(left, f), map_node(right, f)),
+		None => {
+			let key = node.get_key();
+			let value = f(key, node.get_leaf_value().expect("leaf node"));
+			Node::new_leaf(key.clone(), (), value)
+		},
+	}
+}
+
+fn into_map_node<K, V, C, W, F>(node: Node<TpMap<K, V, C>>, f: &mut F) -> Node<TpMap<K, W>>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+	W: Default + Clone + Eq,
+	F: FnMut(&K, V) -> W,
+{
+	match node.into_children() {
+		Ok((left, right)) => Node::new_parent(into_map_node(left, f), into_map_node(right, f)),
+		Err(leaf) => {
+			let (key, value) = leaf.into_key_leaf_value();
+			let value = f(&key, value.expect("leaf node"));
+			Node::new_leaf(key, (), value)
+		},
+	}
+}
+
+type MapNode<K, V, C> = Node<TpMap<K, V, C>>;
+
+// merge (canonical) subtree `b` into subtree `a`
+//
+// leafs overlapping leafs in the other subtree are split until both have the
+// same prefix; disjoint subtrees of `b` are copied. `len` tracks the number
+// of leafs in the result.
+fn merge_nodes<K, V, C, F>(
+	a: MapNode<K, V, C>,
+	b: &MapNode<K, V, C>,
+	combine: &mut F,
+	len: &mut usize,
+) -> MapNode<K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+	F: FnMut(&K, &V, &V) -> V,
+{
+	let a_len = a.get_key().len();
+	let b_len = b.get_key().len();
+	let shared_len = a.get_key().shared_prefix_len(b.get_key());
+	if shared_len < a_len && shared_len < b_len {
+		// disjoint subtrees
+		*len += b.leaf_count();
+		return new_parent_counted(a, b.clone(), len);
+	}
+	if a_len > b_len {
+		// `b` contains `a`: only one side of `b` overlaps `a`
+		let b_split;
+		let (b_left, b_right) = match b.get_children() {
+			Some(children) => children,
+			None => {
+				b_split = split_leaf(b.clone());
+				(&b_split.0, &b_split.1)
+			},
+		};
+		return if a.get_key().get(b_len) {
+			*len += b_left.leaf_count();
+			let right = merge_nodes(a, b_right, combine, len);
+			new_parent_counted(b_left.clone(), right, len)
+		} else {
+			*len += b_right.leaf_count();
+			let left = merge_nodes(a, b_left, combine, len);
+			new_parent_counted(left, b_right.clone(), len)
+		};
+	}
+	// `a` contains `b`
+	let (a_left, a_right) = match a.into_children() {
+		Ok(children) => children,
+		Err(a_leaf) => match b.get_leaf_value() {
+			Some(b_value) if a_len == b_len => {
+				let (key, a_value) = a_leaf.into_key_leaf_value();
+				let value = combine(&key, &a_value.expect("leaf node"), b_value);
+				return Node::new_leaf(key, (), value);
+			},
+			_ => {
+				*len += 1;
+				split_leaf(a_leaf)
+			},
+		},
+	};
+	if a_len < b_len {
+		if b.get_key().get(a_len) {
+			let right = merge_nodes(a_right, b, combine, len);
+			new_parent_counted(a_left, right, len)
+		} else {
+			let left = merge_nodes(a_left, b, combine, len);
+			new_parent_counted(left, a_right, len)
+		}
+	} else {
+		let b_split;
+		let (b_left, b_right) = match b.get_children() {
+			Some(children) => children,
+			None => {
+				b_split = split_leaf(b.clone());
+				(&b_split.0, &b_split.1)
+			},
+		};
+		let left = merge_nodes(a_left, b_left, combine, len);
+		let right = merge_nodes(a_right, b_right, combine, len);
+		new_parent_counted(left, right, len)
+	}
+}
+
+// split a leaf into leafs for both halves with the same value
+fn split_leaf<K, V, C>(leaf: MapNode<K, V, C>) -> (MapNode<K, V, C>, MapNode<K, V, C>)
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	let (key, value) = leaf.into_key_leaf_value();
+	let value = value.expect("leaf node");
+	let mut left = key.clone();
+	left.append(false);
+	let mut right = key;
+	right.append(true);
+	(
+		Node::new_leaf(left, (), value.clone()),
+		Node::new_leaf(right, (), value),
+	)
+}
+
+// `Node::new_parent`, counting merged leafs
+fn new_parent_counted<K, V, C>(
+	a: MapNode<K, V, C>,
+	b: MapNode<K, V, C>,
+	len: &mut usize,
+) -> MapNode<K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	let node = Node::new_parent(a, b);
+	if node.is_leaf() {
+		// two leafs with equal values got merged
+		*len -= 1;
+	}
+	node
+}
+
+// modify all leaf values, merging sibling leafs with equal values afterwards (bottom-up)
+//
+// returns the number of merged leafs
+fn update_node<K, V, C, F>(node: &mut Node<TpMap<K, V, C>>, f: &mut F) -> usize
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+	F: FnMut(&K, &mut V),
+{
+	match node.get_children_mut() {
+		Some((left, right)) => {
+			let merged = update_node(left, f) + update_node(right, f);
+			merged + usize::from(node.compress())
+		},
+		None => {
+			let (key, value) = node.get_key_leaf_value_mut();
+			f(key, value.expect("leaf node"));
+			0
+		},
+	}
+}
+
+// merge sibling leafs with equal values (bottom-up)
+//
+// returns the number of merged leafs
+fn compress_node<K, V, C>(node: &mut Node<TpMap<K, V, C>>) -> usize
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	match node.get_children_mut() {
+		Some((left, right)) => {
+			let merged = compress_node(left) + compress_node(right);
+			merged + usize::from(node.compress())
+		},
+		None => 0,
+	}
+}
+
+fn covering_leaf_mut<'r, K, V, C>(
+	tree: &'r mut Tree<TpMap<K, V, C>>,
+	key: &K,
+) -> Option<&'r mut Node<TpMap<K, V, C>>>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	// only values get modified
+	let mut walk = tree.walk_mut_counted::<(), ()>();
+	match walk.goto_insert(key)? {
+		InsertPosition::BelowLeaf | InsertPosition::AlreadyExists => {
+			walk.into_current_mut().node().filter(|node| node.is_leaf())
+		},
+		InsertPosition::ReplaceNode => None,
+	}
+}
+
+fn covering_value_mut<'r, K, V, C>(tree: &'r mut Tree<TpMap<K, V, C>>, key: &K) -> &'r mut V
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	covering_leaf_mut(tree, key)
+		.and_then(Node::get_leaf_value_mut)
+		.expect("key should be covered by a leaf")
+}
+
+/// A view into a single entry in a [`Map`], which may either be occupied,
+/// covered by a shorter prefix or vacant.
+///
+/// This enum is constructed from the [`entry`] method on [`Map`].
+///
+/// Values modified through mutable references aren't merged with their
+/// siblings (just like with [`Map::iter_mut`]); inserting a value goes
+/// through [`Tree::set_leaf_value`] and aggregates as usual.
+///
+/// [`entry`]: Map::entry
+pub enum Entry<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	/// Key is a (aggregated) prefix in the map
+	Occupied(OccupiedEntry<'s, K, V, C>),
+	/// Key is covered by a shorter (aggregated) prefix in the map
+	Covered(CoveredEntry<'s, K, V, C>),
+	/// Key isn't (completely) mapped to a single value
+	Vacant(VacantEntry<'s, K, V, C>),
+}
+
+impl<'s, K, V, C> Entry<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	/// Ensures a value is in the entry by inserting the default if empty, and returns
+	/// a mutable reference to the value in the entry.
+	///
+	/// For a covered entry this returns the value of the covering prefix.
+	pub fn or_insert(self, default: V) -> &'s mut V {
+		match self {
+			Self::Occupied(entry) => entry.into_mut(),
+			Self::Covered(entry) => entry.into_mut(),
+			Self::Vacant(entry) => entry.insert(default),
+		}
+	}
+
+	/// Ensures a value is in the entry by inserting the result of the default function if empty,
+	/// and returns a mutable reference to the value in the entry.
+	///
+	/// For a covered entry this returns the value of the covering prefix.
+	pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'s mut V {
+		match self {
+			Self::Occupied(entry) => entry.into_mut(),
+			Self::Covered(entry) => entry.into_mut(),
+			Self::Vacant(entry) => entry.insert(default()),
+		}
+	}
+
+	/// Ensures a value is in the entry by inserting, if empty, the result of the default function.
+	/// This method allows for generating key-derived values for insertion by providing the default
+	/// function a reference to the key that was moved during the `.entry(key)` method call.
+	pub fn or_insert_with_key<F: FnOnce(&K) -> V>(self, default: F) -> &'s mut V {
+		match self {
+			Self::Occupied(entry) => entry.into_mut(),
+			Self::Covered(entry) => entry.into_mut(),
+			Self::Vacant(entry) => {
+				let value = default(entry.key());
+				entry.insert(value)
+			},
+		}
+	}
+
+	/// Ensures a value is in the entry by inserting the default value if empty,
+	/// and returns a mutable reference to the value in the entry.
+	pub fn or_default(self) -> &'s mut V {
+		self.or_insert_with(V::default)
+	}
+
+	/// Returns a reference to this entry's key.
+	pub fn key(&self) -> &K {
+		match self {
+			Self::Occupied(entry) => entry.key(),
+			Self::Covered(entry) => entry.key(),
+			Self::Vacant(entry) => entry.key(),
+		}
+	}
+
+	/// Provides in-place mutable access to an occupied (or covered) entry before any
+	/// potential inserts into the map.
+	///
+	/// For a covered entry this modifies the value of the covering prefix.
+	pub fn and_modify<F>(mut self, f: F) -> Self
+	where
+		F: FnOnce(&mut V),
+	{
+		match self {
+			Self::Occupied(ref mut entry) => f(entry.get_mut()),
+			Self::Covered(ref mut entry) => f(entry.get_mut()),
+			Self::Vacant(_) => (),
+		}
+		self
+	}
+}
+
+/// A view into an occupied entry in a [`Map`]. It is part of the [`Entry`] enum.
+pub struct OccupiedEntry<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	tree: &'s mut Tree<TpMap<K, V, C>>,
+	key: K,
+}
+
+impl<'s, K, V, C> OccupiedEntry<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	/// Gets a reference to the key in the entry.
+	pub fn key(&self) -> &K {
+		&self.key
+	}
+
+	/// Gets a reference to the value in the entry.
+	pub fn get(&self) -> &V {
+		self.tree
+			.get(&self.key)
+			.and_then(Node::get_leaf_value)
+			.expect("OccupiedEntry should have a value")
+	}
+
+	/// Gets a mutable reference to the value in the entry.
+	pub fn get_mut(&mut self) -> &mut V {
+		covering_value_mut(self.tree, &self.key)
+	}
+
+	/// Converts the entry into a mutable reference to its value.
+	pub fn into_mut(self) -> &'s mut V {
+		covering_value_mut(self.tree, &self.key)
+	}
+
+	/// Sets the value of the entry, and returns the entry's old value.
+	pub fn insert(self, value: V) -> V {
+		let old = self.get().clone();
+		self.tree.set_leaf_value(self.key, value);
+		old
+	}
+
+	/// Takes the value out of the entry, and returns it.
+	pub fn remove(self) -> V {
+		let old = self.get().clone();
+		let mut walk = self.tree.walk_mut_counted();
+		if let Some(InsertPosition::AlreadyExists) = walk.goto_insert(&self.key) {
+			walk.delete_current();
+		}
+		old
+	}
+}
+
+/// A view into an entry in a [`Map`] covered by a shorter prefix. It is
+/// part of the [`Entry`] enum.
+pub struct CoveredEntry<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	tree: &'s mut Tree<TpMap<K, V, C>>,
+	key: K,
+}
+
+impl<'s, K, V, C> CoveredEntry<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	fn leaf(&self) -> &Node<TpMap<K, V, C>> {
+		match self.tree.goto_insert(&self.key) {
+			Some(InsertPositionWith::BelowLeaf(node)) => node,
+			_ => panic!("CoveredEntry should be below a leaf"),
+		}
+	}
+
+	/// Gets a reference to the key used to create the entry.
+	pub fn key(&self) -> &K {
+		&self.key
+	}
+
+	/// Gets a reference to the (aggregated) prefix covering the key.
+	pub fn covering_key(&self) -> &K {
+		self.leaf().get_key()
+	}
+
+	/// Gets a reference to the value of the covering prefix.
+	pub fn get(&self) -> &V {
+		self.leaf()
+			.get_leaf_value()
+			.expect("CoveredEntry should have a value")
+	}
+
+	/// Gets a mutable reference to the value of the covering prefix.
+	///
+	/// Modifying it changes the value for the complete covering prefix.
+	pub fn get_mut(&mut self) -> &mut V {
+		covering_value_mut(self.tree, &self.key)
+	}
+
+	/// Converts the entry into a mutable reference to the value of the
+	/// covering prefix.
+	pub fn into_mut(self) -> &'s mut V {
+		covering_value_mut(self.tree, &self.key)
+	}
+
+	/// Sets the value for the entry's key only (splitting the covering
+	/// prefix), and returns a mutable reference to it.
+	pub fn insert(self, value: V) -> &'s mut V {
+		let Self { tree, key } = self;
+		tree.set_leaf_value(key.clone(), value);
+		covering_value_mut(tree, &key)
+	}
+}
+
+/// A view into a vacant entry in a [`Map`]. It is part of the [`Entry`] enum.
+///
+/// Parts of the key might still be mapped; inserting replaces them.
+pub struct VacantEntry<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	tree: &'s mut Tree<TpMap<K, V, C>>,
+	key: K,
+}
+
+impl<'s, K, V, C> VacantEntry<'s, K, V, C>
+where
+	K: BitString + Clone,
+	V: Default + Clone + Eq,
+	C: LeafValueComparer<V>,
+{
+	/// Gets a reference to the key that would be used when inserting a value
+	/// through the VacantEntry.
+	pub fn key(&self) -> &K {
+		&self.key
+	}
+
+	/// Take ownership of the key.
+	pub fn into_key(self) -> K {
+		self.key
+	}
+
+	/// Sets the value of the entry with the `VacantEntry`'s key,
+	/// and returns a mutable reference to it.
+	///
+	/// If the value got merged with a sibling the reference points to the
+	/// value of the aggregated prefix.
+	pub fn insert(self, value: V) -> &'s mut V {
+		let Self { tree, key } = self;
+		tree.set_leaf_value(key.clone(), value);
+		covering_value_mut(tree, &key)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Map;
+	use alloc::vec::Vec;
+	use bitstring::BitLengthString;
+	use core::net::Ipv4Addr;
+
+	type Ipv4Cidr = BitLengthString<Ipv4Addr>;
+
+	fn random_entries(count: usize) -> Vec<(Ipv4Cidr, u32)> {
+		let mut state = 0x5eed_u32;
+		(0..count)
+			.map(|_| {
+				// xorshift
+				state ^= state << 13;
+				state ^= state >> 17;
+				state ^= state << 5;
+				let len = 8 + (state >> 28) as usize;
+				let addr = Ipv4Addr::from(0x0a00_0000 | (state & 0x00ff_ff00));
+				(Ipv4Cidr::new(addr, len), state % 4)
+			})
+			.collect()
+	}
+
+	#[test]
+	fn diff_equal_maps_takes_no_steps() {
+		let entries = random_entries(200);
+		let mut a = Map::<Ipv4Cidr, u32>::new();
+		for (key, value) in &entries {
+			a.insert(key.clone(), *value);
+		}
+		// same content, built in a different order
+		let mut b = Map::<Ipv4Cidr, u32>::new();
+		for (key, value) in a.iter().collect::<Vec<_>>().into_iter().rev() {
+			b.insert(key.clone(), *value);
+		}
+		assert!(!core::ptr::eq(
+			a.tree.root().unwrap(),
+			b.tree.root().unwrap()
+		));
+
+		let mut diff = a.diff(&b);
+		assert_eq!(diff.stack.capacity(), 0);
+		assert!(diff.next().is_none());
 	}
 }
@@ -1,14 +1,27 @@
 //! [`Set`] of bit string prefixes
 
-use core::cmp::Ordering;
+use alloc::vec::Vec;
 
 use bitstring::BitString;
 
-use crate::tree::{
-	DefaultCompare,
-	InsertPositionWith,
-	Tree,
-	TreeProperties,
+use crate::{
+	iter::{
+		iter_between,
+		iter_inclusive,
+		IterBetween,
+	},
+	tree::{
+		DefaultCompare,
+		Deleted,
+		InsertPosition,
+		InsertPositionWith,
+		IntoIterLeaf,
+		Node,
+		Tree,
+		TreeProperties,
+		TreeStats,
+		WalkedDirection,
+	},
 };
 
 mod hidden {
@@ -31,6 +44,28 @@ impl<K: BitString + Clone> TreeProperties for TpSet<K> {
 	const LEAF_EMPTY: bool = true;
 }
 
+/// What [`Set::remove`] removed
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Removed {
+	/// Nothing was covered by the removed prefix
+	Nothing,
+	/// Removed prefix was exactly one of the (aggregated) prefixes in the set
+	Exact,
+	/// Removed (possibly multiple) longer prefixes contained in the removed prefix
+	Contained,
+	/// Split a shorter prefix covering the removed prefix
+	Split,
+}
+
+/// Which set covers a prefix returned by [`Set::diff`]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DiffSide {
+	/// Only covered by the set `diff` was called on
+	OnlyLeft,
+	/// Only covered by the other set
+	OnlyRight,
+}
+
 /// Set of bit string prefixes
 ///
 /// Sibling prefixes are automatically merged.
@@ -38,11 +73,22 @@ impl<K: BitString + Clone> TreeProperties for TpSet<K> {
 /// This is implemented as a [`crate::tree::Tree`] where nodes don't carry
 /// values at all, buf leaf nodes represent set membership of the associated
 /// key.
-#[derive(Clone)]
 pub struct Set<K: BitString + Clone> {
 	tree: Tree<TpSet<K>>,
 }
 
+impl<K: BitString + Clone> Clone for Set<K> {
+	fn clone(&self) -> Self {
+		Self {
+			tree: self.tree.clone(),
+		}
+	}
+
+	fn clone_from(&mut self, source: &Self) {
+		self.tree.clone_from(&source.tree);
+	}
+}
+
 impl<K: BitString + Clone> Default for Set<K> {
 	fn default() -> Self {
 		Self::new()
@@ -55,56 +101,734 @@ impl<K: BitString + Clone + core::fmt::Debug> core::fmt::Debug for Set<K> {
 	}
 }
 
+/// Prints prefixes as `{a, b, c}`; the alternate form (`{:#}`) prints one prefix per line
+impl<K: BitString + Clone + core::fmt::Display> core::fmt::Display for Set<K> {
+	fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+		if f.alternate() {
+			for key in self.iter() {
+				writeln!(f, "{}", key)?;
+			}
+			return Ok(());
+		}
+		f.write_str("{")?;
+		for (ndx, key) in self.iter().enumerate() {
+			if ndx > 0 {
+				f.write_str(", ")?;
+			}
+			write!(f, "{}", key)?;
+		}
+		f.write_str("}")
+	}
+}
+
+// leafs are canonical: equal sets have the same leafs
+impl<K: BitString + Clone> PartialEq for Set<K> {
+	fn eq(&self, other: &Self) -> bool {
+		self.len() == other.len() && self.tree.leaf_eq(&other.tree)
+	}
+}
+
+impl<K: BitString + Clone> Eq for Set<K> {}
+
+// consistent with `PartialEq`: only depends on the (canonical) leafs
+impl<K: BitString + Clone + core::hash::Hash> core::hash::Hash for Set<K> {
+	fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+		state.write_usize(self.len());
+		for key in self.iter() {
+			key.hash(state);
+		}
+	}
+}
+
+/// Serialized as sequence of the (aggregated) prefixes in order
+#[cfg(feature = "serde")]
+impl<K: BitString + Clone + serde::Serialize> serde::Serialize for Set<K> {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.collect_seq(self.iter())
+	}
+}
+
+/// Deserialized from a sequence of prefixes; all of them are inserted, so
+/// overlapping or unaggregated input is fine.
+#[cfg(feature = "serde")]
+impl<'de, K: BitString + Clone + serde::Deserialize<'de>> serde::Deserialize<'de> for Set<K> {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		struct SetVisitor<K>(core::marker::PhantomData<K>);
+
+		impl<'de, K: BitString + Clone + serde::Deserialize<'de>> serde::de::Visitor<'de>
+			for SetVisitor<K>
+		{
+			type Value = Set<K>;
+
+			fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+				f.write_str("a sequence of prefixes")
+			}
+
+			fn visit_seq<A: serde::de::SeqAccess<'de>>(
+				self,
+				mut seq: A,
+			) -> Result<Self::Value, A::Error> {
+				let mut set = Set::new();
+				while let Some(key) = seq.next_element()? {
+					set.insert(key);
+				}
+				Ok(set)
+			}
+		}
+
+		deserializer.deserialize_seq(SetVisitor(core::marker::PhantomData))
+	}
+}
+
+/// Union of two sets, see [`Set::union`]
+///
+/// ```
+/// use bitstring_trees::set::Set;
+/// use cidr::Ipv4Cidr;
+///
+/// let a: Set<Ipv4Cidr> = ["192.168.0.0/25".parse().unwrap()].into_iter().collect();
+/// let b: Set<Ipv4Cidr> = ["192.168.0.128/25".parse().unwrap()].into_iter().collect();
+/// assert_eq!((&a | &b).to_string(), "{192.168.0.0/24}");
+/// assert_eq!((&(&a | &b) - &a), b);
+/// assert!((&a & &b).is_empty());
+/// ```
+impl<K: BitString + Clone> core::ops::BitOr<&Set<K>> for &Set<K> {
+	type Output = Set<K>;
+
+	fn bitor(self, rhs: &Set<K>) -> Set<K> {
+		self.union(rhs)
+	}
+}
+
+impl<K: BitString + Clone> core::ops::BitOr<&Set<K>> for Set<K> {
+	type Output = Set<K>;
+
+	fn bitor(mut self, rhs: &Set<K>) -> Set<K> {
+		self.union_with(rhs);
+		self
+	}
+}
+
+impl<K: BitString + Clone> core::ops::BitOrAssign<&Set<K>> for Set<K> {
+	fn bitor_assign(&mut self, rhs: &Set<K>) {
+		self.union_with(rhs);
+	}
+}
+
+/// Intersection of two sets, see [`Set::intersection`]
+impl<K: BitString + Clone> core::ops::BitAnd<&Set<K>> for &Set<K> {
+	type Output = Set<K>;
+
+	fn bitand(self, rhs: &Set<K>) -> Set<K> {
+		self.intersection(rhs)
+	}
+}
+
+impl<K: BitString + Clone> core::ops::BitAnd<&Set<K>> for Set<K> {
+	type Output = Set<K>;
+
+	fn bitand(self, rhs: &Set<K>) -> Set<K> {
+		self.intersection(rhs)
+	}
+}
+
+impl<K: BitString + Clone> core::ops::BitAndAssign<&Set<K>> for Set<K> {
+	fn bitand_assign(&mut self, rhs: &Set<K>) {
+		*self = self.intersection(rhs);
+	}
+}
+
+/// Difference of two sets, see [`Set::difference`]
+impl<K: BitString + Clone> core::ops::Sub<&Set<K>> for &Set<K> {
+	type Output = Set<K>;
+
+	fn sub(self, rhs: &Set<K>) -> Set<K> {
+		self.difference(rhs)
+	}
+}
+
+impl<K: BitString + Clone> core::ops::Sub<&Set<K>> for Set<K> {
+	type Output = Set<K>;
+
+	fn sub(mut self, rhs: &Set<K>) -> Set<K> {
+		self.subtract_set(rhs);
+		self
+	}
+}
+
+impl<K: BitString + Clone> core::ops::SubAssign<&Set<K>> for Set<K> {
+	fn sub_assign(&mut self, rhs: &Set<K>) {
+		self.subtract_set(rhs);
+	}
+}
+
+/// Symmetric difference of two sets, see [`Set::symmetric_difference`]
+impl<K: BitString + Clone> core::ops::BitXor<&Set<K>> for &Set<K> {
+	type Output = Set<K>;
+
+	fn bitxor(self, rhs: &Set<K>) -> Set<K> {
+		self.symmetric_difference(rhs)
+	}
+}
+
+impl<K: BitString + Clone> core::ops::BitXor<&Set<K>> for Set<K> {
+	type Output = Set<K>;
+
+	fn bitxor(self, rhs: &Set<K>) -> Set<K> {
+		self.symmetric_difference(rhs)
+	}
+}
+
+impl<K: BitString + Clone> core::ops::BitXorAssign<&Set<K>> for Set<K> {
+	fn bitxor_assign(&mut self, rhs: &Set<K>) {
+		*self = self.symmetric_difference(rhs);
+	}
+}
+
+impl<K: BitString + Clone> IntoIterator for Set<K> {
+	type IntoIter = IntoIterSet<K>;
+	type Item = K;
+
+	fn into_iter(self) -> Self::IntoIter {
+		IntoIterSet {
+			iter: self.tree.into_iter_leaf(),
+		}
+	}
+}
+
+impl<'s, K: BitString + Clone> IntoIterator for &'s Set<K> {
+	type IntoIter = IterSet<'s, K>;
+	type Item = &'s K;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter()
+	}
+}
+
+impl<K: BitString + Clone> FromIterator<K> for Set<K> {
+	fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+		let mut set = Self::new();
+		set.extend(iter);
+		set
+	}
+}
+
+impl<K: BitString + Clone> Extend<K> for Set<K> {
+	fn extend<I: IntoIterator<Item = K>>(&mut self, iter: I) {
+		for key in iter {
+			self.insert(key);
+		}
+	}
+}
+
+impl<'a, K: BitString + Clone> Extend<&'a K> for Set<K> {
+	fn extend<I: IntoIterator<Item = &'a K>>(&mut self, iter: I) {
+		self.extend(iter.into_iter().cloned());
+	}
+}
+
 impl<K: BitString + Clone> Set<K> {
 	/// New (empty) set.
 	pub const fn new() -> Self {
 		Self { tree: Tree::new() }
 	}
 
+	// set with given (canonical) root node
+	fn from_root(root: Option<SetNode<K>>) -> Self {
+		Self {
+			tree: Tree::from_root(root),
+		}
+	}
+
+	/// Number of (aggregated) prefixes in the set
+	///
+	/// Sibling prefixes are merged, so this is the number of items [`iter`](Self::iter)
+	/// yields, not the number of inserted prefixes.
+	///
+	/// See [`Tree::leaf_count`]: runs in constant time unless the tree was
+	/// modified through mutable node references (e.g. with
+	/// [`tree_mut`](Self::tree_mut)); the prefixes are counted once then.
+	pub fn len(&self) -> usize {
+		self.tree.leaf_count()
+	}
+
+	/// Whether set is empty
+	pub fn is_empty(&self) -> bool {
+		self.tree.root().is_none()
+	}
+
+	/// Structural statistics of the underlying tree
+	///
+	/// See [`Tree::stats`].
+	pub fn stats(&self) -> TreeStats {
+		self.tree.stats()
+	}
+
+	/// Longest bit string that is a prefix of all prefixes in the set
+	///
+	/// Returns `None` if the set is empty.
+	pub fn longest_common_prefix(&self) -> Option<K> {
+		Some(self.tree.root()?.get_key().clone())
+	}
+
+	/// Remove all prefixes from set
+	pub fn clear(&mut self) {
+		self.tree.clear();
+	}
+
+	/// Remove all prefixes from set, returning them (in-order) through an iterator
+	///
+	/// The set is empty right away; prefixes not consumed are dropped
+	/// with the iterator.
+	pub fn drain(&mut self) -> DrainSet<'_, K> {
+		let set = core::mem::take(self);
+		DrainSet {
+			iter: set.into_iter(),
+			_set: core::marker::PhantomData,
+		}
+	}
+
+	/// Build set from in-order sorted, non-overlapping prefixes
+	///
+	/// Builds the tree bottom-up in linear time, merging sibling prefixes on the way.
+	///
+	/// Starting with the first prefix that isn't sorted after (and disjoint from) its
+	/// predecessor all remaining prefixes are inserted normally, i.e. the result is
+	/// always correct, but unsorted input is slower.
+	pub fn from_sorted<I: IntoIterator<Item = K>>(iter: I) -> Self {
+		let mut iter = iter.into_iter();
+		let mut builder = SortedBuilder::new();
+		let mut set = loop {
+			match iter.next() {
+				None => return Self::from_root(builder.finish()),
+				Some(key) => {
+					if let Err(key) = builder.push(key) {
+						let mut set = Self::from_root(builder.finish());
+						set.insert(key);
+						break set;
+					}
+				},
+			}
+		};
+		set.extend(iter);
+		set
+	}
+
+	/// Build set from in-order sorted, non-overlapping prefixes
+	///
+	/// Like [`from_sorted`](Self::from_sorted), but fails with the first prefix
+	/// that isn't sorted after (and disjoint from) its predecessor.
+	pub fn try_from_sorted<I: IntoIterator<Item = K>>(iter: I) -> Result<Self, K> {
+		let mut builder = SortedBuilder::new();
+		for key in iter {
+			builder.push(key)?;
+		}
+		Ok(Self::from_root(builder.finish()))
+	}
+
 	/// Access raw tree of set
 	pub fn tree(&self) -> &Tree<TpSet<K>> {
 		&self.tree
 	}
 
+	/// Mutable access to raw tree of set
+	///
+	/// You must keep leafs canonical yourself: sibling leafs covering their
+	/// parent prefix completely have to be merged, otherwise other set operations
+	/// will misbehave.
+	///
+	/// The number of prefixes (see [`len`](Self::len)) is recomputed lazily
+	/// after modifying nodes through it.
+	pub fn tree_mut(&mut self) -> &mut Tree<TpSet<K>> {
+		&mut self.tree
+	}
+
 	/// Insert prefix into set
-	pub fn insert(&mut self, key: K) {
-		self.tree.set_leaf_value(key, ());
+	///
+	/// Returns whether the set changed, i.e. `false` if the prefix was
+	/// already covered (see [`contains`](Self::contains)).
+	///
+	/// Inserting a prefix of already contained prefixes always changes the
+	/// set, as sibling prefixes are merged and the prefix therefore can't
+	/// have been covered completely before.
+	pub fn insert(&mut self, key: K) -> bool {
+		self.tree.set_leaf_value_counted(key, ()).changed
+	}
+
+	/// Insert all bit strings between `first 0*` and `last 1*` into set
+	///
+	/// Inserts the prefixes generated by [`iter_inclusive`]; nothing is inserted
+	/// if `first` comes after `last`.
+	pub fn insert_range(&mut self, first: K, last: K) {
+		for key in iter_inclusive(first, last) {
+			self.insert(key);
+		}
 	}
 
 	/// Remove everything covered by prefix from set
-	pub fn remove(&mut self, key: K) {
-		let mut walk = self.tree.walk_mut();
-		walk.goto_insert(&key);
-		match walk.current().node() {
-			None => (), // empty tree
-			Some(node) => {
-				match node.get_key().len().cmp(&key.len()) {
-					Ordering::Less => {
-						// node is a leaf and covers key; need to split and remove key
-						// create explicit node with key we want to remove
-						walk.insert(key);
-						// now remove it
-						walk.delete_current();
-					},
-					Ordering::Equal | Ordering::Greater => {
-						// remove subtree
-						walk.delete_current();
-					},
+	///
+	/// Returns what kind of coverage was removed.
+	pub fn remove(&mut self, key: K) -> Removed {
+		match self.tree.delete_counted(&key) {
+			Deleted::Nothing => Removed::Nothing,
+			Deleted::Split { .. } => Removed::Split,
+			// single leaf: removed node was a leaf
+			Deleted::Exact { removed: 1 } => Removed::Exact,
+			Deleted::Exact { .. } | Deleted::Contained { .. } => Removed::Contained,
+		}
+	}
+
+	/// Move everything covered by prefix into a new set
+	///
+	/// A shorter prefix covering `prefix` is split: the returned set
+	/// contains exactly `prefix`, and `self` keeps the rest.
+	pub fn split_off(&mut self, prefix: &K) -> Self {
+		let mut walk = self.tree.walk_mut_counted();
+		let subtree = match walk.goto_insert(prefix) {
+			None => return Self::new(), // empty tree
+			Some(InsertPosition::BelowLeaf) => {
+				// node is a leaf and covers prefix; need to split and detach prefix
+				// create explicit node with prefix we want to detach
+				walk.insert(prefix.clone());
+				walk.delete_current();
+				return Self::from_root(Some(Node::new_leaf(prefix.clone(), (), ())));
+			},
+			Some(InsertPosition::AlreadyExists) => {
+				walk.current_mut().node().expect("should be at node")
+			},
+			Some(InsertPosition::ReplaceNode) => {
+				let node = walk.current_mut().node().expect("should be at node");
+				if node.get_key().shared_prefix_len(prefix) < prefix.len() {
+					// node not in subtree of prefix
+					return Self::new();
 				}
+				node
 			},
+		};
+		// detach subtree, leaving a placeholder leaf to delete
+		let subtree = core::mem::replace(subtree, Node::new_leaf(prefix.clone(), (), ()));
+		let removed = subtree.leaf_count();
+		walk.leafs_changed(1, removed);
+		walk.delete_current();
+		Self {
+			tree: Tree::from_root_counted(Some(subtree), removed),
+		}
+	}
+
+	/// Flip membership of everything covered by prefix
+	///
+	/// Parts of `key` covered by the set are removed, and parts not covered are
+	/// inserted; i.e. this is the symmetric difference with the set only
+	/// containing `key`.
+	pub fn toggle(&mut self, key: K) {
+		let covered = self.split_off(&key);
+		let uncovered = match covered.tree.root() {
+			None => Self::from_root(Some(Node::new_leaf(key, (), ()))),
+			Some(root) => Self::from_root(complement_nodes(key.len(), root)),
+		};
+		self.union_with(&uncovered);
+	}
+
+	/// Remove and return the smallest prefix in set (in-order)
+	pub fn pop_first(&mut self) -> Option<K> {
+		self.pop_side(false)
+	}
+
+	/// Remove and return the largest prefix in set (in-order)
+	pub fn pop_last(&mut self) -> Option<K> {
+		self.pop_side(true)
+	}
+
+	fn pop_side(&mut self, right: bool) -> Option<K> {
+		let mut walk = self.tree.walk_mut_counted::<WalkedDirection, ()>();
+		if !walk.down_root() {
+			return None; // empty tree
+		}
+		while walk.down(right) {}
+		let node = walk.current_mut().node().expect("should be at leaf");
+		// move leaf out, leaving a placeholder to delete
+		let leaf = core::mem::replace(node, Node::new_leaf(K::null(), (), ()));
+		walk.delete_current();
+		Some(leaf.into_key())
+	}
+
+	/// Remove all bit strings between `first 0*` and `last 1*` from set
+	///
+	/// Nothing is removed if `first` comes after `last`.
+	///
+	/// Covering leafs are only split once (see [`subtract`](Self::subtract)).
+	pub fn remove_range(&mut self, first: K, last: K) {
+		let mut range = Self::new();
+		range.insert_range(first, last);
+		self.subtract(&range);
+	}
+
+	/// Only keep prefixes (leafs) for which `f` returns `true`
+	///
+	/// Removing leafs never creates new full coverage, so there is
+	/// nothing to merge afterwards.
+	pub fn retain<F>(&mut self, mut f: F)
+	where
+		F: FnMut(&K) -> bool,
+	{
+		let mut walk = self.tree.walk_mut_counted();
+		let mut next = walk.next_leaf().is_some();
+		while next {
+			let node = walk.current().node().expect("should be at leaf");
+			if f(node.get_key()) {
+				next = walk.next_leaf().is_some();
+				continue;
+			}
+			// replaces parent with sibling; current points to sibling afterwards
+			match walk.delete_current() {
+				None | Some(WalkedDirection::Down) => break, // tree empty now
+				Some(WalkedDirection::Left) => {
+					// sibling (right side) not visited yet
+					while walk.down_left() {}
+					next = true;
+				},
+				Some(WalkedDirection::Right) => {
+					// sibling (left side) already visited
+					next = walk.next_leaf().is_some();
+				},
+			}
+		}
+	}
+
+	/// Number of bit strings of length `full_len` covered by the set
+	///
+	/// E.g. the number of addresses covered by a set of IPv4 prefixes with `full_len = 32`.
+	///
+	/// The result saturates at `u128::MAX`; this only happens when
+	/// all `2^128` bit strings of length `full_len >= 128` are covered.
+	///
+	/// # Panics
+	///
+	/// Panics if the set contains a prefix longer than `full_len`.
+	pub fn covered_count(&self, full_len: usize) -> u128 {
+		let mut count: u128 = 0;
+		for key in self.iter() {
+			assert!(key.len() <= full_len, "prefix longer than full_len");
+			let covered = match u32::try_from(full_len - key.len()) {
+				Ok(shift) if shift < u128::BITS => 1 << shift,
+				_ => u128::MAX,
+			};
+			count = count.saturating_add(covered);
+		}
+		count
+	}
+
+	/// Replace all prefixes longer than `max_len` by their prefix of length `max_len`
+	///
+	/// The result covers everything covered before (and possibly more); sibling
+	/// prefixes are merged again afterwards.
+	pub fn aggregate_to(&mut self, max_len: usize) {
+		if let (Some(root), len) = self.tree.root_mut_counted() {
+			aggregate_node(root, max_len, len);
 		}
 	}
 
 	/// Whether prefix is (completely) contained in set
 	pub fn contains(&self, key: &K) -> bool {
-		match self.tree.goto_insert(key) {
-			Some(InsertPositionWith::BelowLeaf(_)) => true,
-			Some(InsertPositionWith::AlreadyExists(_)) => true,
-			Some(InsertPositionWith::ReplaceNode(_)) => false,
+		self.covering_prefix(key).is_some()
+	}
+
+	/// Whether all given prefixes are (completely) contained in set
+	///
+	/// Keeps a cursor in the tree between lookups: keys sharing long prefixes
+	/// with their predecessor (e.g. sorted keys) don't need to start at the root.
+	pub fn contains_all<I: IntoIterator<Item = K>>(&self, keys: I) -> bool {
+		let mut walk = self.tree.walk::<(), ()>();
+		keys.into_iter().all(|key| match walk.goto_insert(&key) {
+			None => false, // empty tree
+			Some(InsertPosition::BelowLeaf) => true,
+			Some(InsertPosition::AlreadyExists) => {
+				walk.current().node().expect("should be at node").is_leaf()
+			},
+			Some(InsertPosition::ReplaceNode) => false,
+		})
+	}
+
+	/// Whether set covers any bit string between `first 0*` and `last 1*`
+	///
+	/// Returns false if `first` comes after `last`.
+	pub fn intersects_range(&self, first: &K, last: &K) -> bool {
+		if before(last, first) {
+			return false;
+		}
+		match self.tree.root() {
 			None => false,
+			Some(root) => range_overlap_node(root, first, last),
+		}
+	}
+
+	/// Find the prefix in the set covering the given prefix
+	///
+	/// Returns either `key` itself or a shorter prefix in the set.
+	pub fn covering_prefix(&self, key: &K) -> Option<&K> {
+		match self.tree.goto_insert(key)? {
+			InsertPositionWith::BelowLeaf(node) => Some(node.get_key()),
+			InsertPositionWith::AlreadyExists(node) => {
+				// an inner node with exactly `key` isn't fully covered
+				node.is_leaf().then(|| node.get_key())
+			},
+			InsertPositionWith::ReplaceNode(_) => None,
+		}
+	}
+
+	/// Whether all prefixes in this set are also covered by `other`
+	///
+	/// The empty set is a subset of every set.
+	pub fn is_subset(&self, other: &Self) -> bool {
+		match (self.tree.root(), other.tree.root()) {
+			(None, _) => true,
+			(Some(_), None) => false,
+			(Some(a), Some(b)) => subset_nodes(a, b),
+		}
+	}
+
+	/// Whether all prefixes in `other` are also covered by this set
+	pub fn is_superset(&self, other: &Self) -> bool {
+		other.is_subset(self)
+	}
+
+	/// Whether any bit string is covered by both sets
+	///
+	/// Same as checking whether the [`intersection`](Self::intersection) is not empty, but
+	/// without building it.
+	pub fn overlaps(&self, other: &Self) -> bool {
+		match (self.tree.root(), other.tree.root()) {
+			(Some(a), Some(b)) => overlap_nodes(a, b),
+			_ => false,
+		}
+	}
+
+	/// Union of two sets
+	///
+	/// Returns a new set containing all prefixes covered by either set.
+	pub fn union(&self, other: &Self) -> Self {
+		let mut result = self.clone();
+		result.union_with(other);
+		result
+	}
+
+	/// Insert all prefixes of another set
+	///
+	/// Walks both trees in parallel; subtrees of `other` already covered by a leaf in
+	/// `self` are skipped.
+	pub fn union_with(&mut self, other: &Self) {
+		let other_root = match other.tree.root() {
+			None => return,
+			Some(root) => root,
+		};
+		let mut len = self.len();
+		let root = match core::mem::take(&mut self.tree).into_root() {
+			None => {
+				len = other.len();
+				other_root.clone()
+			},
+			Some(root) => union_nodes(root, other_root, &mut len),
+		};
+		self.tree = Tree::from_root_counted(Some(root), len);
+	}
+
+	/// Intersection of two sets
+	///
+	/// Returns a new set containing all prefixes covered by both sets.
+	pub fn intersection(&self, other: &Self) -> Self {
+		let root = match (self.tree.root(), other.tree.root()) {
+			(Some(a), Some(b)) => intersect_nodes(a, b),
+			_ => None,
+		};
+		Self::from_root(root)
+	}
+
+	/// Difference of two sets
+	///
+	/// Returns a new set containing all prefixes covered by `self` but not by `other`.
+	pub fn difference(&self, other: &Self) -> Self {
+		let mut result = self.clone();
+		result.subtract(other);
+		result
+	}
+
+	/// Remove everything covered by another set
+	///
+	/// Same as [`subtract_set`](Self::subtract_set).
+	pub fn subtract(&mut self, other: &Self) {
+		self.subtract_set(other);
+	}
+
+	/// Remove everything covered by another set in place
+	///
+	/// Walks both trees in parallel, but only where they overlap: subtracting a small
+	/// set from a large one only touches the paths to the prefixes of the small set.
+	/// Leafs of `self` are only split where `other` actually covers parts of them.
+	pub fn subtract_set(&mut self, other: &Self) {
+		let (root, len, other_root) = match (self.tree.root_mut_counted(), other.tree.root()) {
+			((Some(root), len), Some(other_root)) => (root, len, other_root),
+			_ => return,
+		};
+		if subtract_nodes(root, other_root, len) {
+			self.tree.clear();
+		}
+	}
+
+	/// Symmetric difference of two sets
+	///
+	/// Returns a new set containing all prefixes covered by exactly one of the sets.
+	pub fn symmetric_difference(&self, other: &Self) -> Self {
+		let root = match (self.tree.root(), other.tree.root()) {
+			(Some(a), Some(b)) => xor_nodes(a, b),
+			(Some(a), None) => Some(a.clone()),
+			(None, b) => b.cloned(),
+		};
+		Self::from_root(root)
+	}
+
+	/// Complement of the set
+	///
+	/// Returns a new set containing exactly the bit strings not covered by `self`.
+	pub fn complement(&self) -> Self {
+		let root = match self.tree.root() {
+			None => Some(Node::new_leaf(K::null(), (), ())),
+			Some(root) => complement_nodes(0, root),
+		};
+		Self::from_root(root)
+	}
+
+	/// Replace set with its complement
+	///
+	/// See [`complement`](Self::complement).
+	pub fn invert(&mut self) {
+		*self = self.complement();
+	}
+
+	/// Whether prefix is exactly one of the (aggregated) prefixes in the set
+	///
+	/// Unlike [`contains`](Self::contains) this is false for prefixes that are only
+	/// covered by a shorter prefix.
+	///
+	/// As sibling prefixes are merged, inserting `192.168.0.0/25` and `192.168.0.128/25`
+	/// stores `192.168.0.0/24`; neither of the inserted `/25` prefixes is contained
+	/// exactly afterwards.
+	pub fn contains_exact(&self, key: &K) -> bool {
+		match self.tree.goto_insert(key) {
+			Some(InsertPositionWith::AlreadyExists(node)) => node.is_leaf(),
+			_ => false,
 		}
 	}
 
+	/// Smallest prefix in set (in-order)
+	pub fn first(&self) -> Option<&K> {
+		Some(self.tree.first_leaf()?.get_key())
+	}
+
+	/// Largest prefix in set (in-order)
+	pub fn last(&self) -> Option<&K> {
+		Some(self.tree.last_leaf()?.get_key())
+	}
+
 	/// Iterate over all contained prefixes
 	pub fn iter(&self) -> IterSet<'_, K> {
 		IterSet {
@@ -112,6 +836,61 @@ impl<K: BitString + Clone> Set<K> {
 		}
 	}
 
+	/// Iterate over all contained prefixes that have the given prefix
+	///
+	/// If the given prefix is covered by a shorter prefix in the set, only that
+	/// covering (shorter) prefix is returned.
+	pub fn iter_in(&self, prefix: &K) -> IterSetIn<'_, K> {
+		IterSetIn {
+			iter: self.tree.iter_leaf_in(prefix),
+		}
+	}
+
+	/// Iterate over maximal ranges of consecutive bit strings of length `full_len` in set
+	///
+	/// Each range is returned as its (inclusive) first and last bit string; this is the
+	/// inverse of [`iter_inclusive`].
+	///
+	/// Ranges don't wrap around: a set containing both the first and the last bit
+	/// string (but not everything) yields at least two ranges.
+	///
+	/// # Panics
+	///
+	/// Panics if the set contains a prefix longer than `full_len`.
+	pub fn iter_ranges(&self, full_len: usize) -> IterSetRanges<'_, K> {
+		IterSetRanges {
+			iter: self.iter(),
+			full_len,
+			next: None,
+		}
+	}
+
+	/// Iterate over smallest list of bit strings that cover everything not in the set
+	///
+	/// Like [`iter_full`](Self::iter_full), but skipping the contained prefixes.
+	pub fn iter_uncovered(&self) -> IterSetUncovered<'_, K> {
+		IterSetUncovered {
+			iter: Some(self.iter()),
+			previous_key: None,
+			uncovered: Default::default(),
+		}
+	}
+
+	/// Iterate over smallest list of bit strings covered by exactly one of the sets
+	///
+	/// Walks both trees simultaneously; subtrees with the same keys and shape are
+	/// skipped without descending into them. Nothing is returned for identical
+	/// sets.
+	pub fn diff<'s>(&'s self, other: &'s Self) -> IterSetDiff<'s, K> {
+		let mut stack = Vec::new();
+		match (self.tree.root(), other.tree.root()) {
+			(None, None) => (),
+			(Some(a), Some(b)) if a.subtree_leaf_eq(b) => (),
+			(a, b) => stack.push((K::null(), SetPart::new(a), SetPart::new(b))),
+		}
+		IterSetDiff { stack }
+	}
+
 	/// Iterate over smallest list of bit strings that cover everything with information whether they are part of the set or not
 	pub fn iter_full(&self) -> IterSetFull<'_, K> {
 		IterSetFull {
@@ -120,6 +899,434 @@ impl<K: BitString + Clone> Set<K> {
 	}
 }
 
+type SetNode<K> = Node<TpSet<K>>;
+
+// whether (canonical) subtree `a` is covered by (canonical) subtree `b`
+fn subset_nodes<K: BitString + Clone>(a: &SetNode<K>, b: &SetNode<K>) -> bool {
+	let a_len = a.get_key().len();
+	let b_len = b.get_key().len();
+	let shared_len = a.get_key().shared_prefix_len(b.get_key());
+	if shared_len < b_len {
+		// either disjoint subtrees, or `a` covers something outside of `b`
+		// (a leaf covers all of its key, an inner node both sides)
+		return false;
+	}
+	// `b` contains `a`
+	let (b_left, b_right) = match b.get_children() {
+		None => return true, // `b` leaf covers `a`
+		Some(children) => children,
+	};
+	if b_len < a_len {
+		if a.get_key().get(b_len) {
+			subset_nodes(a, b_right)
+		} else {
+			subset_nodes(a, b_left)
+		}
+	} else {
+		match a.get_children() {
+			// inner node `b` doesn't cover everything
+			None => false,
+			Some((a_left, a_right)) => {
+				subset_nodes(a_left, b_left) && subset_nodes(a_right, b_right)
+			},
+		}
+	}
+}
+
+// whether two (canonical) subtrees share any covered bit string
+fn overlap_nodes<K: BitString + Clone>(a: &SetNode<K>, b: &SetNode<K>) -> bool {
+	let a_len = a.get_key().len();
+	let b_len = b.get_key().len();
+	let shared_len = a.get_key().shared_prefix_len(b.get_key());
+	if shared_len < a_len && shared_len < b_len {
+		// disjoint subtrees
+		return false;
+	}
+	if a_len > b_len {
+		return overlap_nodes(b, a);
+	}
+	// `a` contains `b`
+	let (a_left, a_right) = match a.get_children() {
+		None => return true, // `a` leaf covers `b`
+		Some(children) => children,
+	};
+	if a_len < b_len {
+		if b.get_key().get(a_len) {
+			overlap_nodes(a_right, b)
+		} else {
+			overlap_nodes(a_left, b)
+		}
+	} else {
+		match b.get_children() {
+			None => true, // `b` leaf covers `a`
+			Some((b_left, b_right)) => {
+				overlap_nodes(a_left, b_left) || overlap_nodes(a_right, b_right)
+			},
+		}
+	}
+}
+
+// union of two (canonical) subtrees
+//
+// `len` tracks the number of leafs in the result
+fn union_nodes<K: BitString + Clone>(a: SetNode<K>, b: &SetNode<K>, len: &mut usize) -> SetNode<K> {
+	let a_len = a.get_key().len();
+	let b_len = b.get_key().len();
+	let shared_len = a.get_key().shared_prefix_len(b.get_key());
+	if shared_len < a_len && shared_len < b_len {
+		// disjoint subtrees
+		*len += b.leaf_count();
+		return new_parent_counted(a, b.clone(), len);
+	}
+	if a_len <= b_len {
+		// `a` contains `b`
+		let (a_left, a_right) = match a.into_children() {
+			Ok(children) => children,
+			Err(a_leaf) => return a_leaf, // already covers `b`
+		};
+		if a_len < b_len {
+			if b.get_key().get(a_len) {
+				let right = union_nodes(a_right, b, len);
+				new_parent_counted(a_left, right, len)
+			} else {
+				let left = union_nodes(a_left, b, len);
+				new_parent_counted(left, a_right, len)
+			}
+		} else {
+			match b.get_children() {
+				None => {
+					*len = *len + 1 - a_left.leaf_count() - a_right.leaf_count();
+					b.clone()
+				},
+				Some((b_left, b_right)) => {
+					let left = union_nodes(a_left, b_left, len);
+					let right = union_nodes(a_right, b_right, len);
+					new_parent_counted(left, right, len)
+				},
+			}
+		}
+	} else {
+		// `b` contains `a`
+		match b.get_children() {
+			None => {
+				*len = *len + 1 - a.leaf_count();
+				b.clone()
+			},
+			Some((b_left, b_right)) => {
+				if a.get_key().get(b_len) {
+					*len += b_left.leaf_count();
+					let right = union_nodes(a, b_right, len);
+					new_parent_counted(b_left.clone(), right, len)
+				} else {
+					*len += b_right.leaf_count();
+					let left = union_nodes(a, b_left, len);
+					new_parent_counted(left, b_right.clone(), len)
+				}
+			},
+		}
+	}
+}
+
+// `Node::new_parent`, counting merged leafs
+fn new_parent_counted<K: BitString + Clone>(
+	a: SetNode<K>,
+	b: SetNode<K>,
+	len: &mut usize,
+) -> SetNode<K> {
+	let node = Node::new_parent(a, b);
+	if node.is_leaf() {
+		// two leafs got merged
+		*len -= 1;
+	}
+	node
+}
+
+// intersection of two (canonical) subtrees
+fn intersect_nodes<K: BitString + Clone>(a: &SetNode<K>, b: &SetNode<K>) -> Option<SetNode<K>> {
+	let a_len = a.get_key().len();
+	let b_len = b.get_key().len();
+	let shared_len = a.get_key().shared_prefix_len(b.get_key());
+	if shared_len < a_len && shared_len < b_len {
+		// disjoint subtrees
+		return None;
+	}
+	if a_len > b_len {
+		return intersect_nodes(b, a);
+	}
+	// `a` contains `b`
+	let (a_left, a_right) = match a.get_children() {
+		None => return Some(b.clone()), // `a` leaf covers `b`
+		Some(children) => children,
+	};
+	if a_len < b_len {
+		if b.get_key().get(a_len) {
+			intersect_nodes(a_right, b)
+		} else {
+			intersect_nodes(a_left, b)
+		}
+	} else {
+		match b.get_children() {
+			None => Some(a.clone()),
+			Some((b_left, b_right)) => join_nodes(
+				intersect_nodes(a_left, b_left),
+				intersect_nodes(a_right, b_right),
+			),
+		}
+	}
+}
+
+// remove (canonical) subtree `b` from subtree `a` in place
+//
+// returns true if nothing is left of `a`; the caller has to remove it then.
+//
+// `len` tracks the number of leafs in the result; removing coverage never
+// creates mergeable siblings.
+fn subtract_nodes<K: BitString + Clone>(
+	a: &mut SetNode<K>,
+	b: &SetNode<K>,
+	len: &mut usize,
+) -> bool {
+	let a_len = a.get_key().len();
+	let b_len = b.get_key().len();
+	let shared_len = a.get_key().shared_prefix_len(b.get_key());
+	if shared_len < a_len && shared_len < b_len {
+		// disjoint subtrees
+		return false;
+	}
+	if a_len > b_len {
+		// `b` contains `a`
+		return match b.get_children() {
+			None => {
+				*len -= a.leaf_count();
+				true
+			},
+			Some((b_left, b_right)) => {
+				let b_child = if a.get_key().get(b_len) {
+					b_right
+				} else {
+					b_left
+				};
+				subtract_nodes(a, b_child, len)
+			},
+		};
+	}
+	// `a` contains `b`
+	if a.is_leaf() {
+		// split `a` leaf
+		return match complement_nodes(a_len, b) {
+			None => {
+				*len -= 1;
+				true
+			},
+			Some(rest) => {
+				*len = *len + rest.leaf_count() - 1;
+				*a = rest;
+				false
+			},
+		};
+	}
+	if a_len < b_len {
+		let side = b.get_key().get(a_len);
+		let a_child = a.get_child_mut(side).expect("inner node");
+		if subtract_nodes(a_child, b, len) {
+			a.delete_side(side);
+		}
+		return false;
+	}
+	let (b_left, b_right) = match b.get_children() {
+		None => {
+			*len -= a.leaf_count();
+			return true;
+		},
+		Some(children) => children,
+	};
+	let (a_left, a_right) = a.get_children_mut().expect("inner node");
+	let left_empty = subtract_nodes(a_left, b_left, len);
+	let right_empty = subtract_nodes(a_right, b_right, len);
+	match (left_empty, right_empty) {
+		(true, true) => return true,
+		(true, false) => a.delete_side(false),
+		(false, true) => a.delete_side(true),
+		(false, false) => (),
+	}
+	false
+}
+
+// symmetric difference of two (canonical) subtrees
+fn xor_nodes<K: BitString + Clone>(a: &SetNode<K>, b: &SetNode<K>) -> Option<SetNode<K>> {
+	let a_len = a.get_key().len();
+	let b_len = b.get_key().len();
+	let shared_len = a.get_key().shared_prefix_len(b.get_key());
+	if shared_len < a_len && shared_len < b_len {
+		// disjoint subtrees
+		return Some(Node::new_parent(a.clone(), b.clone()));
+	}
+	if a_len > b_len {
+		return xor_nodes(b, a);
+	}
+	// `a` contains `b`
+	let (a_left, a_right) = match a.get_children() {
+		None => return complement_nodes(a_len, b), // `a` leaf covers `b`
+		Some(children) => children,
+	};
+	if a_len < b_len {
+		if b.get_key().get(a_len) {
+			join_nodes(Some(a_left.clone()), xor_nodes(a_right, b))
+		} else {
+			join_nodes(xor_nodes(a_left, b), Some(a_right.clone()))
+		}
+	} else {
+		match b.get_children() {
+			None => complement_nodes(b_len, a), // `b` leaf covers `a`
+			Some((b_left, b_right)) => {
+				join_nodes(xor_nodes(a_left, b_left), xor_nodes(a_right, b_right))
+			},
+		}
+	}
+}
+
+// everything not covered by (canonical) subtree `b` below the prefix of its key of length `len`
+fn complement_nodes<K: BitString + Clone>(len: usize, b: &SetNode<K>) -> Option<SetNode<K>> {
+	let b_key = b.get_key();
+	let b_len = b_key.len();
+	let mut result = match b.get_children() {
+		None => None,
+		Some((b_left, b_right)) => join_nodes(
+			complement_nodes(b_len + 1, b_left),
+			complement_nodes(b_len + 1, b_right),
+		),
+	};
+	// siblings of the path from prefix down to `b` are not covered
+	for l in (len..b_len).rev() {
+		let mut sibling = b_key.clone();
+		sibling.clip(l + 1);
+		sibling.flip(l);
+		result = join_nodes(Some(Node::new_leaf(sibling, (), ())), result);
+	}
+	result
+}
+
+// whether everything covered by `a` comes before everything covered by `b`
+fn before<K: BitString>(a: &K, b: &K) -> bool {
+	let shared_len = a.shared_prefix_len(b);
+	shared_len < a.len() && shared_len < b.len() && !a.get(shared_len)
+}
+
+// whether (canonical) subtree covers anything between `first 0*` and `last 1*`
+fn range_overlap_node<K: BitString + Clone>(node: &SetNode<K>, first: &K, last: &K) -> bool {
+	let key = node.get_key();
+	if before(key, first) || before(last, key) {
+		// outside range
+		return false;
+	}
+	let inside_first = key.shared_prefix_len(first) == first.len() || before(first, key);
+	let inside_last = key.shared_prefix_len(last) == last.len() || before(key, last);
+	if inside_first && inside_last {
+		// completely inside range; subtrees always contain a leaf
+		return true;
+	}
+	match node.get_children() {
+		None => true, // leaf overlapping range
+		Some((left, right)) => {
+			range_overlap_node(left, first, last) || range_overlap_node(right, first, last)
+		},
+	}
+}
+
+// build tree bottom-up from sorted, disjoint leafs
+struct SortedBuilder<K: BitString + Clone> {
+	// completed subtrees along the right spine of the tree built so far;
+	// the keys of neighbors share longer prefixes towards the top
+	stack: Vec<SetNode<K>>,
+	previous: Option<K>,
+}
+
+impl<K: BitString + Clone> SortedBuilder<K> {
+	fn new() -> Self {
+		Self {
+			stack: Vec::new(),
+			previous: None,
+		}
+	}
+
+	// returns key as error if it isn't sorted after and disjoint from previous key
+	fn push(&mut self, key: K) -> Result<(), K> {
+		if let Some(previous) = &self.previous {
+			let shared_len = previous.shared_prefix_len(&key);
+			if shared_len == previous.len() || shared_len == key.len() || !key.get(shared_len) {
+				return Err(key);
+			}
+		}
+		// all subtrees sharing a longer prefix with each other than with the new key
+		// are complete
+		while self.stack.len() >= 2 {
+			let n = self.stack.len();
+			let shared_len = self.stack[n - 1].get_key().shared_prefix_len(&key);
+			if self.stack[n - 2]
+				.get_key()
+				.shared_prefix_len(self.stack[n - 1].get_key())
+				< shared_len
+			{
+				break;
+			}
+			self.join_top();
+		}
+		self.previous = Some(key.clone());
+		self.stack.push(Node::new_leaf(key, (), ()));
+		Ok(())
+	}
+
+	fn join_top(&mut self) {
+		let b = self.stack.pop().expect("two subtrees");
+		let a = self.stack.pop().expect("two subtrees");
+		// merges sibling leafs
+		self.stack.push(Node::new_parent(a, b));
+	}
+
+	fn finish(mut self) -> Option<SetNode<K>> {
+		while self.stack.len() >= 2 {
+			self.join_top();
+		}
+		self.stack.pop()
+	}
+}
+
+// replace everything in (canonical) subtree longer than `max_len` by its prefix
+//
+// `len` tracks the number of leafs in the result
+fn aggregate_node<K: BitString + Clone>(node: &mut SetNode<K>, max_len: usize, len: &mut usize) {
+	let key_len = node.get_key().len();
+	if key_len >= max_len {
+		if key_len > max_len || !node.is_leaf() {
+			*len = *len + 1 - node.leaf_count();
+			let mut key = node.get_key().clone();
+			key.clip(max_len);
+			*node = Node::new_leaf(key, (), ());
+		}
+		return;
+	}
+	if let Some((left, right)) = node.get_children_mut() {
+		aggregate_node(left, max_len, len);
+		aggregate_node(right, max_len, len);
+		if node.compress() {
+			// two leafs got merged
+			*len -= 1;
+		}
+	}
+}
+
+// combine (optional) subtrees from different sides of a parent node
+fn join_nodes<K: BitString + Clone>(
+	a: Option<SetNode<K>>,
+	b: Option<SetNode<K>>,
+) -> Option<SetNode<K>> {
+	match (a, b) {
+		(Some(a), Some(b)) => Some(Node::new_parent(a, b)),
+		(a, None) => a,
+		(None, b) => b,
+	}
+}
+
 /// Iterate over all prefixes contained in a set
 pub struct IterSet<'s, K: BitString + Clone> {
 	iter: super::tree::IterLeaf<'s, TpSet<K>>,
@@ -131,6 +1338,258 @@ impl<'s, K: BitString + Clone> Iterator for IterSet<'s, K> {
 	fn next(&mut self) -> Option<Self::Item> {
 		Some(self.iter.next()?.0.get_key())
 	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.iter.size_hint()
+	}
+}
+
+impl<'s, K: BitString + Clone> ExactSizeIterator for IterSet<'s, K> {}
+
+impl<'s, K: BitString + Clone> DoubleEndedIterator for IterSet<'s, K> {
+	fn next_back(&mut self) -> Option<Self::Item> {
+		Some(self.iter.next_back()?.0.get_key())
+	}
+}
+
+/// Iterate over all prefixes contained in a set below some prefix
+///
+/// See [`Set::iter_in`].
+pub struct IterSetIn<'s, K: BitString + Clone> {
+	iter: super::tree::IterLeafIn<'s, TpSet<K>>,
+}
+
+impl<'s, K: BitString + Clone> Iterator for IterSetIn<'s, K> {
+	type Item = &'s K;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		Some(self.iter.next()?.0.get_key())
+	}
+}
+
+/// Iterate over all prefixes contained in a set, consuming the set
+pub struct IntoIterSet<K: BitString + Clone> {
+	iter: IntoIterLeaf<TpSet<K>>,
+}
+
+impl<K: BitString + Clone> Iterator for IntoIterSet<K> {
+	type Item = K;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		Some(self.iter.next()?.0)
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.iter.size_hint()
+	}
+}
+
+impl<K: BitString + Clone> ExactSizeIterator for IntoIterSet<K> {}
+
+/// Draining iterator over all prefixes of a set
+///
+/// See [`Set::drain`].
+pub struct DrainSet<'s, K: BitString + Clone> {
+	iter: IntoIterSet<K>,
+	_set: core::marker::PhantomData<&'s mut Set<K>>,
+}
+
+impl<'s, K: BitString + Clone> Iterator for DrainSet<'s, K> {
+	type Item = K;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.iter.next()
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.iter.size_hint()
+	}
+}
+
+impl<'s, K: BitString + Clone> ExactSizeIterator for DrainSet<'s, K> {}
+
+/// Iterate over maximal ranges of consecutive bit strings in a set
+///
+/// See [`Set::iter_ranges`].
+pub struct IterSetRanges<'s, K: BitString + Clone> {
+	iter: IterSet<'s, K>,
+	full_len: usize,
+	// first and last prefix of range not returned yet
+	next: Option<(&'s K, &'s K)>,
+}
+
+impl<'s, K: BitString + Clone> IterSetRanges<'s, K> {
+	fn extend(&self, key: &K, bit: bool) -> K {
+		assert!(key.len() <= self.full_len, "prefix longer than full_len");
+		let mut key = key.clone();
+		while key.len() < self.full_len {
+			key.append(bit);
+		}
+		key
+	}
+}
+
+impl<'s, K: BitString + Clone> Iterator for IterSetRanges<'s, K> {
+	type Item = (K, K);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			match self.iter.next() {
+				None => {
+					let (first, last) = self.next.take()?;
+					return Some((self.extend(first, false), self.extend(last, true)));
+				},
+				Some(key) => match self.next {
+					None => self.next = Some((key, key)),
+					Some((first, last)) => {
+						if iter_between(Some(last.clone()), Some(key.clone()))
+							.next()
+							.is_none()
+						{
+							// nothing between previous and current prefix: extend range
+							self.next = Some((first, key));
+						} else {
+							self.next = Some((key, key));
+							return Some((self.extend(first, false), self.extend(last, true)));
+						}
+					},
+				},
+			}
+		}
+	}
+}
+
+/// Iterate over smallest list of bit strings that cover everything not in a set
+///
+/// See [`Set::iter_uncovered`].
+pub struct IterSetUncovered<'s, K: BitString + Clone> {
+	iter: Option<IterSet<'s, K>>,
+	previous_key: Option<K>,
+	uncovered: IterBetween<K>,
+}
+
+impl<'s, K: BitString + Clone> Iterator for IterSetUncovered<'s, K> {
+	type Item = K;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			if let Some(k) = self.uncovered.next() {
+				return Some(k);
+			}
+			match self.iter.as_mut()?.next() {
+				None => {
+					self.iter = None;
+					// return final uncovered prefixes
+					self.uncovered = iter_between(self.previous_key.take(), None);
+				},
+				Some(key) => {
+					// return uncovered prefixes before
+					let start = self.previous_key.replace(key.clone());
+					self.uncovered = iter_between(start, Some(key.clone()));
+				},
+			}
+		}
+	}
+}
+
+// part of a set within some prefix
+enum SetPart<'s, K: BitString + Clone> {
+	// everything is covered or not
+	Uniform(bool),
+	// subtree with key having the prefix; rest is not covered
+	Tree(&'s SetNode<K>),
+}
+
+impl<'s, K: BitString + Clone> SetPart<'s, K> {
+	fn new(root: Option<&'s SetNode<K>>) -> Self {
+		match root {
+			None => Self::Uniform(false),
+			Some(node) => Self::Tree(node),
+		}
+	}
+
+	// treat leaf with exactly the prefix as uniform
+	fn resolve(self, prefix: &K) -> Self {
+		match self {
+			Self::Tree(node) if node.is_leaf() && node.get_key().len() == prefix.len() => {
+				Self::Uniform(true)
+			},
+			_ => self,
+		}
+	}
+
+	// split into parts for both halves of the prefix
+	fn split(self, prefix: &K) -> (Self, Self) {
+		match self {
+			Self::Uniform(covered) => (Self::Uniform(covered), Self::Uniform(covered)),
+			Self::Tree(node) => match node.get_children() {
+				Some((left, right)) if node.get_key().len() == prefix.len() => {
+					(Self::Tree(left), Self::Tree(right))
+				},
+				_ => {
+					if node.get_key().get(prefix.len()) {
+						(Self::Uniform(false), self)
+					} else {
+						(self, Self::Uniform(false))
+					}
+				},
+			},
+		}
+	}
+}
+
+// prefix with the parts of both sets within it
+type SetDiffStep<'s, K> = (K, SetPart<'s, K>, SetPart<'s, K>);
+
+/// Iterate over bit strings covered by exactly one of two sets
+///
+/// See [`Set::diff`].
+pub struct IterSetDiff<'s, K: BitString + Clone> {
+	// prefixes still to compare; next on top
+	stack: Vec<SetDiffStep<'s, K>>,
+}
+
+impl<'s, K: BitString + Clone> Iterator for IterSetDiff<'s, K> {
+	type Item = (K, DiffSide);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		while let Some((prefix, a, b)) = self.stack.pop() {
+			// neither set covers anything outside of the subtrees: jump straight to
+			// the longest prefix covering them instead of splitting bit by bit
+			let prefix = match (&a, &b) {
+				(SetPart::Tree(a), SetPart::Tree(b)) => {
+					let mut key = a.get_key().clone();
+					key.clip(key.shared_prefix_len(b.get_key()));
+					key
+				},
+				(SetPart::Tree(node), SetPart::Uniform(false))
+				| (SetPart::Uniform(false), SetPart::Tree(node)) => node.get_key().clone(),
+				_ => prefix,
+			};
+			let side = match (a.resolve(&prefix), b.resolve(&prefix)) {
+				(SetPart::Uniform(a), SetPart::Uniform(b)) => match (a, b) {
+					(true, false) => DiffSide::OnlyLeft,
+					(false, true) => DiffSide::OnlyRight,
+					_ => continue,
+				},
+				// equal subtrees (same keys and shape)
+				(SetPart::Tree(a), SetPart::Tree(b)) if a.subtree_leaf_eq(b) => continue,
+				(a, b) => {
+					let (a_left, a_right) = a.split(&prefix);
+					let (b_left, b_right) = b.split(&prefix);
+					let mut left = prefix.clone();
+					left.append(false);
+					let mut right = prefix;
+					right.append(true);
+					self.stack.push((right, a_right, b_right));
+					self.stack.push((left, a_left, b_left));
+					continue;
+				},
+			};
+			return Some((prefix, side));
+		}
+		None
+	}
 }
 
 /// Iterate over smallest list of bit strings that cover everything with information whether they are part of the set or not
@@ -146,3 +1605,41 @@ impl<'s, K: BitString + Clone> Iterator for IterSetFull<'s, K> {
 		Some((key, value.is_some()))
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::Set;
+	use alloc::vec::Vec;
+	use bitstring::BitLengthString;
+	use core::net::Ipv4Addr;
+
+	type Ipv4Cidr = BitLengthString<Ipv4Addr>;
+
+	#[test]
+	fn diff_equal_sets_does_not_allocate() {
+		let mut state = 0x5eed_u32;
+		let mut a = Set::<Ipv4Cidr>::new();
+		for _ in 0..200 {
+			// xorshift
+			state ^= state << 13;
+			state ^= state >> 17;
+			state ^= state << 5;
+			let len = 8 + (state >> 28) as usize;
+			let addr = Ipv4Addr::from(0x0a00_0000 | (state & 0x00ff_ff00));
+			a.insert(Ipv4Cidr::new(addr, len));
+		}
+		// same content, built in a different order
+		let mut b = Set::<Ipv4Cidr>::new();
+		for key in a.iter().collect::<Vec<_>>().into_iter().rev() {
+			b.insert(key.clone());
+		}
+		assert!(!core::ptr::eq(
+			a.tree.root().unwrap(),
+			b.tree.root().unwrap()
+		));
+
+		let mut diff = a.diff(&b);
+		assert_eq!(diff.stack.capacity(), 0);
+		assert!(diff.next().is_none());
+	}
+}
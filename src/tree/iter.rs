@@ -1,4 +1,10 @@
+#![allow(clippy::mem_replace_option_with_some)]
+
+use alloc::vec::Vec;
+use bitstring::BitString as _;
+
 use super::{
+	InsertPositionWith,
 	Node,
 	Tree,
 	TreeProperties,
@@ -70,19 +76,113 @@ impl<'r, TP: TreeProperties> Iterator for IterPostOrder<'r, TP> {
 /// Iterate over nodes and leaf values of tree in-order
 pub struct IterLeaf<'r, TP: TreeProperties> {
 	walk: Walk<'r, TP, WalkedDirection>,
+	walk_back: Walk<'r, TP, WalkedDirection>,
+	// last nodes returned from front and back; both cursors must not pass each other
+	front: Option<&'r Node<TP>>,
+	back: Option<&'r Node<TP>>,
+	done: bool,
+	// leafs not returned yet
+	remaining: usize,
 }
 
 impl<'r, TP: TreeProperties> IterLeaf<'r, TP> {
 	pub(in crate::tree) fn new(tree: &'r Tree<TP>) -> Self {
-		Self { walk: tree.walk() }
+		Self {
+			walk: tree.walk(),
+			walk_back: tree.walk(),
+			front: None,
+			back: None,
+			done: false,
+			remaining: tree.leaf_count(),
+		}
 	}
 }
 
+fn same_node<TP: TreeProperties>(a: &Node<TP>, b: Option<&Node<TP>>) -> bool {
+	b.is_some_and(|b| core::ptr::eq(a, b))
+}
+
 impl<'r, TP: TreeProperties> Iterator for IterLeaf<'r, TP> {
 	type Item = (&'r Node<TP>, &'r TP::LeafValue);
 
 	fn next(&mut self) -> Option<Self::Item> {
-		let node = self.walk.next_leaf()?;
+		if self.done {
+			return None;
+		}
+		let node = match self.walk.next_leaf() {
+			Some(node) if !same_node(node, self.back) => node,
+			_ => {
+				self.done = true;
+				return None;
+			},
+		};
+		self.front = Some(node);
+		self.remaining -= 1;
+		Some((node, node.get_leaf_value().expect("leaf node")))
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		(self.remaining, Some(self.remaining))
+	}
+}
+
+impl<'r, TP: TreeProperties> DoubleEndedIterator for IterLeaf<'r, TP> {
+	fn next_back(&mut self) -> Option<Self::Item> {
+		if self.done {
+			return None;
+		}
+		let node = match self.walk_back.next_leaf_back() {
+			Some(node) if !same_node(node, self.front) => node,
+			_ => {
+				self.done = true;
+				return None;
+			},
+		};
+		self.back = Some(node);
+		self.remaining -= 1;
+		Some((node, node.get_leaf_value().expect("leaf node")))
+	}
+}
+
+impl<'r, TP: TreeProperties> ExactSizeIterator for IterLeaf<'r, TP> {}
+
+/// Iterate over nodes and leaf values of a subtree in-order
+///
+/// See [`Tree::iter_leaf_in`].
+pub struct IterLeafIn<'r, TP: TreeProperties> {
+	// subtrees not visited yet; next subtree on top
+	stack: Vec<&'r Node<TP>>,
+}
+
+impl<'r, TP: TreeProperties> IterLeafIn<'r, TP> {
+	pub(in crate::tree) fn new(tree: &'r Tree<TP>, key: &TP::Key) -> Self {
+		let start = match tree.goto_insert(key) {
+			None => None,
+			Some(InsertPositionWith::BelowLeaf(node)) => Some(node),
+			Some(InsertPositionWith::AlreadyExists(node)) => Some(node),
+			Some(InsertPositionWith::ReplaceNode(node)) => {
+				if node.get_key().shared_prefix_len(key) == key.len() {
+					Some(node) // node in subtree of key
+				} else {
+					None
+				}
+			},
+		};
+		Self {
+			stack: start.into_iter().collect(),
+		}
+	}
+}
+
+impl<'r, TP: TreeProperties> Iterator for IterLeafIn<'r, TP> {
+	type Item = (&'r Node<TP>, &'r TP::LeafValue);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let mut node = self.stack.pop()?;
+		while let Some((left, right)) = node.get_children() {
+			self.stack.push(right);
+			node = left;
+		}
 		Some((node, node.get_leaf_value().expect("leaf node")))
 	}
 }
@@ -136,3 +236,138 @@ impl<'r, TP: TreeProperties> Iterator for IterLeafFull<'r, TP> {
 		}
 	}
 }
+
+/// Owning iterator over keys and leaf values of a tree in-order
+///
+/// See [`Tree::into_iter_leaf`].
+pub struct IntoIterLeaf<TP: TreeProperties> {
+	// next subtree; a single leaf doesn't need the stack
+	next: Option<Node<TP>>,
+	// right subtrees still pending; next subtree on top
+	stack: Vec<Node<TP>>,
+	// leafs not returned yet
+	remaining: usize,
+}
+
+impl<TP: TreeProperties> IntoIterLeaf<TP> {
+	pub(in crate::tree) fn new(node: Option<Node<TP>>, leaf_count: usize) -> Self {
+		Self {
+			next: node,
+			stack: Vec::new(),
+			remaining: leaf_count,
+		}
+	}
+}
+
+impl<TP: TreeProperties> Iterator for IntoIterLeaf<TP> {
+	type Item = (TP::Key, TP::LeafValue);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let mut node = self.next.take().or_else(|| self.stack.pop())?;
+		loop {
+			match node.into_children() {
+				Ok((left, right)) => {
+					self.stack.push(right);
+					node = left;
+				},
+				Err(leaf) => {
+					self.remaining -= 1;
+					let (key, value) = leaf.into_key_leaf_value();
+					return Some((key, value.expect("should be a leaf")));
+				},
+			}
+		}
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		(self.remaining, Some(self.remaining))
+	}
+}
+
+impl<TP: TreeProperties> ExactSizeIterator for IntoIterLeaf<TP> {}
+
+impl<TP: TreeProperties> Drop for IntoIterLeaf<TP> {
+	fn drop(&mut self) {
+		// take remaining nodes apart without recursion
+		while self.next().is_some() {}
+	}
+}
+
+enum IntoIterStep<TP: TreeProperties> {
+	// subtree not visited yet
+	Node(Node<TP>),
+	// node with visited left subtree
+	Entry(TP::Key, TP::Value),
+}
+
+/// Owning iterator over keys and values of all nodes of a tree in-order
+///
+/// See [`Tree::into_iter_in_order`].
+pub struct IntoIterInOrder<TP: TreeProperties> {
+	// next step on top
+	stack: Vec<IntoIterStep<TP>>,
+}
+
+impl<TP: TreeProperties> IntoIterInOrder<TP> {
+	pub(in crate::tree) fn new(node: Option<Node<TP>>) -> Self {
+		Self {
+			stack: node.into_iter().map(IntoIterStep::Node).collect(),
+		}
+	}
+}
+
+impl<TP: TreeProperties> Iterator for IntoIterInOrder<TP> {
+	type Item = (TP::Key, TP::Value);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let mut node = match self.stack.pop()? {
+			IntoIterStep::Node(node) => node,
+			IntoIterStep::Entry(key, value) => return Some((key, value)),
+		};
+		loop {
+			let (key, value, children) = node.into_parts();
+			match children {
+				Some((left, right)) => {
+					self.stack.push(IntoIterStep::Node(right));
+					self.stack.push(IntoIterStep::Entry(key, value));
+					node = left;
+				},
+				None => return Some((key, value)),
+			}
+		}
+	}
+}
+
+impl<TP: TreeProperties> Drop for IntoIterInOrder<TP> {
+	fn drop(&mut self) {
+		// take remaining nodes apart without recursion
+		while self.next().is_some() {}
+	}
+}
+
+/// Iterate over replaced leafs (keys and leaf values) in-order
+///
+/// See [`Tree::set_leaf_value_replacing`].
+pub struct ReplacedLeaves<TP: TreeProperties> {
+	iter: alloc::vec::IntoIter<(TP::Key, TP::LeafValue)>,
+}
+
+impl<TP: TreeProperties> ReplacedLeaves<TP> {
+	pub(in crate::tree) fn new(replaced: Vec<(TP::Key, TP::LeafValue)>) -> Self {
+		Self {
+			iter: replaced.into_iter(),
+		}
+	}
+}
+
+impl<TP: TreeProperties> Iterator for ReplacedLeaves<TP> {
+	type Item = (TP::Key, TP::LeafValue);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.iter.next()
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.iter.size_hint()
+	}
+}
@@ -4,10 +4,14 @@
 //!
 //! Look for other containers in this crate that offer specific use cases.
 
-use alloc::boxed::Box;
+use alloc::{
+	boxed::Box,
+	vec::Vec,
+};
 use bitstring::BitString;
 use core::{
 	fmt,
+	marker::PhantomData,
 	mem::{
 		replace,
 		swap,
@@ -27,11 +31,15 @@ pub use self::{
 		InsertPositionWith,
 	},
 	iter::{
+		IntoIterInOrder,
+		IntoIterLeaf,
 		IterInOrder,
 		IterLeaf,
 		IterLeafFull,
+		IterLeafIn,
 		IterPostOrder,
 		IterPreOrder,
+		ReplacedLeaves,
 	},
 	mut_borrowed::{
 		IterMutBorrowedInOrder,
@@ -126,6 +134,21 @@ const fn tp_valid<TP: TreeProperties>() -> bool {
 pub trait LeafValueComparer<V> {
 	/// Whether two leaf values are equal and can be merged if they are neighbors keys
 	fn eq(a: &V, b: &V) -> bool;
+
+	/// Combine values of two neighbor leafs (left and right) to merge them
+	///
+	/// Returns `None` if the leafs can't be merged; by default leafs with
+	/// equal values (see [`eq`](Self::eq)) are merged.
+	fn merge(a: &V, b: &V) -> Option<V>
+	where
+		V: Clone,
+	{
+		if Self::eq(a, b) {
+			Some(a.clone())
+		} else {
+			None
+		}
+	}
 }
 
 /// Use [`Eq`] for [`LeafValueComparer`]
@@ -148,6 +171,37 @@ impl<V> LeafValueComparer<V> for NoEqual {
 	}
 }
 
+/// Define how to combine values of neighbor leafs in tree
+///
+/// Use with [`Merging`] as [`LeafValueComparer`].
+pub trait LeafValueMerger<V> {
+	/// Combine values of two neighbor leafs (left and right) to merge them
+	///
+	/// Returns `None` if the leafs should be kept separate.
+	fn merge(a: &V, b: &V) -> Option<V>;
+}
+
+/// Merge neighbor leafs with values combined by a [`LeafValueMerger`]
+///
+/// Leaf values are still compared with [`Eq`] (e.g. to detect whether an
+/// insert changes anything).
+pub struct Merging<M>(PhantomData<M>);
+
+impl<V: Eq, M: LeafValueMerger<V>> LeafValueComparer<V> for Merging<M> {
+	#[inline]
+	fn eq(a: &V, b: &V) -> bool {
+		a == b
+	}
+
+	#[inline]
+	fn merge(a: &V, b: &V) -> Option<V>
+	where
+		V: Clone,
+	{
+		M::merge(a, b)
+	}
+}
+
 /// Node in tree
 pub struct Node<TP: TreeProperties> {
 	key: TP::Key,
@@ -161,10 +215,87 @@ where
 	TP::Value: Clone,
 {
 	fn clone(&self) -> Self {
+		let mut root = self.clone_shallow();
+		// clone subtrees with an explicit stack instead of recursion
+		let mut stack: Vec<(&mut Self, &Self)> = Vec::new();
+		stack.push((&mut root, self));
+		while let Some((target, source)) = stack.pop() {
+			if let NodeState::InnerNode { children } = &source.state {
+				target.state = NodeState::new_inner_shallow(children);
+				if let NodeState::InnerNode {
+					children: target_children,
+				} = &mut target.state
+				{
+					let Children { left, right } = &mut **target_children;
+					stack.push((right, &children.right));
+					stack.push((left, &children.left));
+				}
+			}
+		}
+		root
+	}
+
+	fn clone_from(&mut self, source: &Self) {
+		// clone subtrees with an explicit stack instead of recursion, reusing
+		// existing allocations where both trees have inner nodes
+		let mut stack: Vec<(&mut Self, &Self)> = Vec::new();
+		// replaced subtrees; dropped without recursion too
+		let mut dropped: Vec<Self> = Vec::new();
+		stack.push((self, source));
+		while let Some((target, source)) = stack.pop() {
+			target.key.clone_from(&source.key);
+			target.value.clone_from(&source.value);
+			match &source.state {
+				NodeState::Leaf { value } => match &mut target.state {
+					NodeState::Leaf {
+						value: target_value,
+					} => target_value.clone_from(value),
+					NodeState::InnerNode { .. } => {
+						let old_state = replace(
+							&mut target.state,
+							NodeState::Leaf {
+								value: value.clone(),
+							},
+						);
+						if let NodeState::InnerNode { children } = old_state {
+							let Children { left, right } = *children;
+							dropped.push(left);
+							dropped.push(right);
+						}
+					},
+				},
+				NodeState::InnerNode { children } => {
+					if let NodeState::Leaf { .. } = target.state {
+						target.state = NodeState::new_inner_shallow(children);
+					}
+					if let NodeState::InnerNode {
+						children: target_children,
+					} = &mut target.state
+					{
+						let Children { left, right } = &mut **target_children;
+						stack.push((right, &children.right));
+						stack.push((left, &children.left));
+					}
+				},
+			}
+		}
+		drop_nodes(dropped);
+	}
+}
+
+impl<TP> Node<TP>
+where
+	TP: TreeProperties,
+	TP::Value: Clone,
+{
+	// clone key and value; inner nodes become leafs with a default leaf value
+	fn clone_shallow(&self) -> Self {
 		Self {
 			key: self.key.clone(),
 			value: self.value.clone(),
-			state: self.state.clone(),
+			state: NodeState::Leaf {
+				value: self.get_leaf_value().cloned().unwrap_or_default(),
+			},
 		}
 	}
 }
@@ -240,6 +371,15 @@ impl<TP: TreeProperties> Node<TP> {
 		}
 	}
 
+	// key and mutable leaf value at the same time
+	pub(crate) fn get_key_leaf_value_mut(&mut self) -> (&TP::Key, Option<&mut TP::LeafValue>) {
+		let value = match self.state {
+			NodeState::Leaf { ref mut value } => Some(value),
+			_ => None,
+		};
+		(&self.key, value)
+	}
+
 	/// Make node a leaf node (i.e. drop potential child nodes) and set leaf value
 	#[inline]
 	pub fn set_leaf_value(&mut self, value: TP::LeafValue) -> &mut TP::LeafValue {
@@ -298,6 +438,28 @@ impl<TP: TreeProperties> Node<TP> {
 		}
 	}
 
+	// whether both subtrees have the same keys, shape and leaf values (using
+	// `TP::LeafValueComparer`); values of inner nodes are ignored.
+	//
+	// recursion depth is bounded by the key length; doesn't allocate.
+	pub(crate) fn subtree_leaf_eq(&self, other: &Self) -> bool {
+		if core::ptr::eq(self, other) {
+			return true;
+		}
+		if self.key != other.key {
+			return false;
+		}
+		match (&self.state, &other.state) {
+			(NodeState::Leaf { value: a }, NodeState::Leaf { value: b }) => {
+				TP::LeafValueComparer::eq(a, b)
+			},
+			(NodeState::InnerNode { children: a }, NodeState::InnerNode { children: b }) => {
+				a.left.subtree_leaf_eq(&b.left) && a.right.subtree_leaf_eq(&b.right)
+			},
+			_ => false,
+		}
+	}
+
 	/// Return mutable reference to left child node unless node is a leaf
 	#[inline]
 	pub fn get_left_mut(&mut self) -> Option<&mut Self> {
@@ -359,7 +521,25 @@ impl<TP: TreeProperties> Node<TP> {
 		}
 	}
 
-	fn new_leaf(key: TP::Key, inner: TP::Value, value: TP::LeafValue) -> Self {
+	// split borrow: key, mutable value and mutable child node (unless node is a leaf)
+	pub(crate) fn get_key_value_child_mut(
+		&mut self,
+		side_bit: bool,
+	) -> (&TP::Key, &mut TP::Value, Option<&mut Node<TP>>) {
+		let child = match self.state {
+			NodeState::InnerNode { ref mut children } => Some({
+				if side_bit {
+					&mut children.right
+				} else {
+					&mut children.left
+				}
+			}),
+			_ => None,
+		};
+		(&self.key, &mut self.value, child)
+	}
+
+	pub(crate) fn new_leaf(key: TP::Key, inner: TP::Value, value: TP::LeafValue) -> Self {
 		Self {
 			key,
 			value: inner,
@@ -367,12 +547,105 @@ impl<TP: TreeProperties> Node<TP> {
 		}
 	}
 
-	// test whether two nodes can be combined because their leaf values are equal
+	// create inner node with the shared prefix of both nodes as key (and a default value)
+	//
+	// neither key must be a prefix of the other. compresses to a leaf if possible.
+	pub(crate) fn new_parent(a: Self, b: Self) -> Self {
+		let shared_prefix_len = a.key.shared_prefix_len(&b.key);
+		debug_assert!(shared_prefix_len < a.key.len());
+		debug_assert!(shared_prefix_len < b.key.len());
+		let mut key = a.key.clone();
+		key.clip(shared_prefix_len);
+		let mut node = Self {
+			key,
+			value: Default::default(),
+			state: NodeState::new_inner_unknown_order(shared_prefix_len, a, b),
+		};
+		if TP::EMPTY {
+			node.compress();
+		}
+		node
+	}
+
+	// number of leaf nodes in subtree
+	pub(crate) fn leaf_count(&self) -> usize {
+		match self.get_children() {
+			None => 1,
+			Some((left, right)) => left.leaf_count() + right.leaf_count(),
+		}
+	}
+
+	// take key out of node, dropping everything else
+	pub(crate) fn into_key(self) -> TP::Key {
+		self.key
+	}
+
+	// take key and leaf value (if a leaf) out of node, dropping everything else
+	pub(crate) fn into_key_leaf_value(self) -> (TP::Key, Option<TP::LeafValue>) {
+		match self.state {
+			NodeState::InnerNode { .. } => (self.key, None),
+			NodeState::Leaf { value } => (self.key, Some(value)),
+		}
+	}
+
+	// take node apart into key, value and (left, right) child nodes (if not a leaf),
+	// dropping the leaf value.
+	pub(crate) fn into_parts(self) -> (TP::Key, TP::Value, Option<(Self, Self)>) {
+		match self.state {
+			NodeState::InnerNode { children } => {
+				let Children { left, right } = *children;
+				(self.key, self.value, Some((left, right)))
+			},
+			NodeState::Leaf { .. } => (self.key, self.value, None),
+		}
+	}
+
+	// build node from parts (see `into_parts`); leafs get a default leaf value.
+	//
+	// children must extend key and be passed in the correct order (left, right).
+	pub(crate) fn from_parts(key: TP::Key, value: TP::Value, children: Option<(Self, Self)>) -> Self
+	where
+		TP::LeafValue: Default,
+	{
+		let state = match children {
+			Some((left, right)) => {
+				debug_assert!(key.len() < left.key.len() && !left.key.get(key.len()));
+				debug_assert!(key.len() < right.key.len() && right.key.get(key.len()));
+				NodeState::InnerNode {
+					children: Box::new(Children { left, right }),
+				}
+			},
+			None => NodeState::Leaf {
+				value: Default::default(),
+			},
+		};
+		Self { key, value, state }
+	}
+
+	// split inner node into (left, right) child nodes, dropping key and value.
+	// returns leaf nodes unchanged as error.
+	pub(crate) fn into_children(self) -> Result<(Self, Self), Self> {
+		match self.state {
+			NodeState::InnerNode { children } => {
+				let Children { left, right } = *children;
+				Ok((left, right))
+			},
+			NodeState::Leaf { .. } => Err(self),
+		}
+	}
+
+	// combine leaf values of two neighbor nodes (left, right) if they can be merged
 	//
 	// with real values (TP::EMPTY = false) we should never combines leaf nodes.
 	// if leaf values are empty too we don't need to actually compare data.
-	fn leaf_value_eq(a: &TP::LeafValue, b: &TP::LeafValue) -> bool {
-		TP::EMPTY && (TP::LEAF_EMPTY || TP::LeafValueComparer::eq(a, b))
+	fn leaf_value_merge(a: &TP::LeafValue, b: &TP::LeafValue) -> Option<TP::LeafValue> {
+		if !TP::EMPTY {
+			None
+		} else if TP::LEAF_EMPTY {
+			Some(a.clone())
+		} else {
+			TP::LeafValueComparer::merge(a, b)
+		}
 	}
 
 	// panic-safe modification
@@ -498,8 +771,90 @@ impl<TP: TreeProperties> Node<TP> {
 		drop(old_inner);
 	}
 
+	// panic-safe modification
+	//
+	// `key` must be a prefix of `self.key`; returns the replaced subtree (with a default value)
+	fn replace_with_leaf(&mut self, key: TP::Key, value: TP::LeafValue) -> Self {
+		let new_inner = if key.len() != self.key.len() {
+			Some(Default::default())
+		} else {
+			None
+		};
+		let new_state = NodeState::Leaf { value };
+
+		// start modification; make it panic safe
+		// * everything shouldn't panic
+		let old_key = replace(&mut self.key, key);
+		let old_state = replace(&mut self.state, new_state);
+		let old_inner = match new_inner {
+			Some(new_inner) => replace(&mut self.value, new_inner),
+			None => Default::default(),
+		};
+		// modification done, allow panics again
+		Self {
+			key: old_key,
+			value: old_inner,
+			state: old_state,
+		}
+	}
+
+	// set leaf values in subtree: existing leafs get `merge(key, old)`, the gaps between
+	// inner nodes and their children get `value`
+	fn merge_leaf_values<F>(&mut self, value: &TP::LeafValue, merge: &mut F)
+	where
+		F: FnMut(&TP::Key, &TP::LeafValue) -> TP::LeafValue,
+	{
+		let self_key_len = self.key.len();
+		match self.state {
+			NodeState::Leaf { value: ref mut old } => {
+				*old = merge(&self.key, old);
+			},
+			NodeState::InnerNode { ref mut children } => {
+				for child in [&mut children.left, &mut children.right] {
+					child.merge_leaf_values(value, merge);
+					child.extend_up(self_key_len + 1, value);
+				}
+			},
+		}
+		if TP::EMPTY {
+			self.compress();
+		}
+	}
+
+	// replace self with chain of inner nodes from a prefix of length `key_len` of
+	// `self.key` down to the previous self; every shorter path gets a side leaf {side_value}
+	fn extend_up(&mut self, key_len: usize, side_value: &TP::LeafValue) {
+		let mut key = self.key.clone();
+		while key.len() > key_len {
+			let l_minus1 = key.len() - 1;
+			let mut other_key = key.clone();
+			other_key.flip(l_minus1);
+			key.clip(l_minus1);
+			let side = Self::new_leaf(other_key, Default::default(), side_value.clone());
+			let tmp_node = Self::new_leaf(key.clone(), Default::default(), Default::default());
+
+			// start modification; make it panic safe
+			// * moving nodes shouldn't panic
+			let old_node = replace(self, tmp_node);
+			// TODO: new_inner_unknown_order calls BitString::get which might panic (but shouldn't)
+			self.state = NodeState::new_inner_unknown_order(l_minus1, old_node, side);
+			// modification done, allow panics again
+			if TP::EMPTY {
+				self.compress();
+			}
+		}
+	}
+
 	/// pre condition: self is the node to insert `key` at
-	fn insert_leaf_value(&mut self, key: TP::Key, value: TP::LeafValue) {
+	///
+	/// if `replaced` is given the replaced leafs with a value different from
+	/// the new value get appended to it (a covering leaf with the new key)
+	fn insert_leaf_value(
+		&mut self,
+		key: TP::Key,
+		value: TP::LeafValue,
+		replaced: Option<&mut Vec<(TP::Key, TP::LeafValue)>>,
+	) -> LeafChanges {
 		let key_len = key.len();
 		let self_key_len = self.key.len();
 		let shared_prefix_len = self.key.shared_prefix_len(&key);
@@ -507,9 +862,26 @@ impl<TP: TreeProperties> Node<TP> {
 		if shared_prefix_len == key_len {
 			// either key == self.key, or key is a prefix of self.key
 			// => replace subtree
+			// replacing a leaf with the same key and an equal value doesn't change anything
+			let changed = self_key_len != key_len
+				|| !self
+					.get_leaf_value()
+					.is_some_and(|old_value| TP::LeafValueComparer::eq(old_value, &value));
+			let removed = self.leaf_count();
 			// panic-safe modification:
-			self.clip_to_value(shared_prefix_len, value);
-			return;
+			let old = self.replace_with_leaf(key, value);
+			if let Some(replaced) = replaced {
+				let new_value = self.get_leaf_value().expect("should be at leaf node");
+				replaced.extend(
+					IntoIterLeaf::new(Some(old), removed)
+						.filter(|(_, old_value)| !TP::LeafValueComparer::eq(old_value, new_value)),
+				);
+			}
+			return LeafChanges {
+				changed,
+				added: 1,
+				removed,
+			};
 		}
 
 		if shared_prefix_len < self_key_len {
@@ -523,18 +895,31 @@ impl<TP: TreeProperties> Node<TP> {
 				if self_key_len == key_len && self_key_len == shared_prefix_len + 1 {
 					// we'd create direct neighbor nodes below
 					if let Some(old_value) = self.get_leaf_value() {
-						if Self::leaf_value_eq(&value, old_value) {
-							// both nodes would be leaf nodes, and their values match
+						let merged = if key.get(shared_prefix_len) {
+							Self::leaf_value_merge(old_value, &value)
+						} else {
+							Self::leaf_value_merge(&value, old_value)
+						};
+						if let Some(merged) = merged {
+							// both nodes would be leaf nodes, and their values can be merged
 							// panic-safe modification:
-							self.clip_to_value(shared_prefix_len, value.clone());
-							return;
+							self.clip_to_value(shared_prefix_len, merged);
+							return LeafChanges {
+								changed: true,
+								added: 1,
+								removed: 1,
+							};
 						}
 					}
 				}
 			}
 
 			self.insert_leaf_sibling(shared_prefix_len, key, value);
-			return;
+			return LeafChanges {
+				changed: true,
+				added: 1,
+				removed: 0,
+			};
 		}
 
 		// otherwise: self.key is a (real) prefix of key
@@ -548,46 +933,63 @@ impl<TP: TreeProperties> Node<TP> {
 		// borrow check is unhappy with putting this into the match below.
 		if TP::LEAF_EMPTY {
 			// we don't care about leaf values, and the key is already covered by a leaf.
-			return;
+			return LeafChanges::default();
 		}
 		if TP::LeafValueComparer::eq(old_value, &value) {
 			// leaf values match, no need to create lots of nodes
-			return;
+			return LeafChanges::default();
+		}
+		if let Some(replaced) = replaced {
+			// only the part covered by the new prefix gets replaced
+			replaced.push((key.clone(), old_value.clone()));
 		}
+		// old leaf gets replaced by new leaf and siblings along the path
+		let added = if TP::IGNORE_LEAFS {
+			2
+		} else {
+			key_len - shared_prefix_len + 1
+		};
 		self.insert_sub_leaf(key, value);
+		LeafChanges {
+			changed: true,
+			added,
+			removed: 1,
+		}
 	}
 
-	// return true when self is a leaf afterwards
-	fn compress(&mut self) -> bool {
+	// leaf value to replace both children with: both child nodes are leafs
+	// exactly one bit longer than the key of this node and their values
+	// can be merged
+	fn merged_children_value(&self) -> Option<TP::LeafValue> {
 		let self_key_len = self.key.len();
+		let children = match self.state {
+			NodeState::InnerNode { ref children } => children,
+			NodeState::Leaf { .. } => return None,
+		};
+		if children.left.key.len() != self_key_len + 1 {
+			return None;
+		}
+		if children.right.key.len() != self_key_len + 1 {
+			return None;
+		}
+		// both need to be leafs
+		let left_value = children.left.get_leaf_value()?;
+		let right_value = children.right.get_leaf_value()?;
+		Self::leaf_value_merge(left_value, right_value)
+	}
 
+	// return true when self is a leaf afterwards
+	pub(crate) fn compress(&mut self) -> bool {
 		// compress: if node has two children, and both sub keys are
 		// exactly one bit longer than the key of the parent node, and
-		// both child nodes are leafs and share the same value, make the
-		// current node a leaf
-		let value = match self.state {
-			NodeState::InnerNode { ref mut children } => {
-				if children.left.key.len() != self_key_len + 1 {
-					return false;
-				}
-				if children.right.key.len() != self_key_len + 1 {
-					return false;
-				}
-				let left_value = match children.left.get_leaf_value() {
-					Some(value) => value,
-					None => return false, // not a leaf
-				};
-				let right_value = match children.right.get_leaf_value() {
-					Some(value) => value,
-					None => return false, // not a leaf
-				};
-				if !Self::leaf_value_eq(left_value, right_value) {
-					return false; // values not equal
-				}
-				// clone value from left side
-				left_value.clone()
-			},
-			NodeState::Leaf { .. } => return true, // already compressed
+		// both child nodes are leafs and their values can be merged, make
+		// the current node a leaf
+		if self.is_leaf() {
+			return true; // already compressed
+		}
+		let value = match self.merged_children_value() {
+			Some(value) => value,
+			None => return false,
 		};
 		// now start modification; make it panic safe
 		// (single assignment should be safe anyway, but make it explicit)
@@ -598,7 +1000,7 @@ impl<TP: TreeProperties> Node<TP> {
 	}
 
 	// delete either left or right side
-	fn delete_side(&mut self, delete_right: bool) {
+	pub(crate) fn delete_side(&mut self, delete_right: bool) {
 		// start modification; make it panic safe
 		// * take might panic when creation of default state fails - nothing else was modified
 		let mut old_state = take(&mut self.state);
@@ -623,6 +1025,40 @@ impl<TP: TreeProperties> Node<TP> {
 	}
 }
 
+// drop nodes and their subtrees without recursion
+fn drop_nodes<TP: TreeProperties>(mut stack: Vec<Node<TP>>) {
+	while let Some(node) = stack.pop() {
+		if let Ok((left, right)) = node.into_children() {
+			stack.push(left);
+			stack.push(right);
+		}
+	}
+}
+
+// what deleting a key removed
+#[derive(Clone, Copy)]
+pub(crate) enum Deleted {
+	// nothing was covered by the key
+	Nothing,
+	// leaf covering the key was replaced by `added` new (sibling) leafs
+	Split { added: usize },
+	// node with the key and its `removed` leafs were deleted
+	Exact { removed: usize },
+	// node with a longer key and its `removed` leafs were deleted
+	Contained { removed: usize },
+}
+
+// what inserting a leaf value changed
+#[derive(Clone, Copy, Default)]
+pub(crate) struct LeafChanges {
+	// whether the tree changed at all
+	pub(crate) changed: bool,
+	// number of new leaf nodes
+	pub(crate) added: usize,
+	// number of removed (or merged) leaf nodes
+	pub(crate) removed: usize,
+}
+
 /// Nodes of a [`Tree`] can be either an InnerNode (with two children)
 /// or a leaf node.
 enum NodeState<TP: TreeProperties> {
@@ -640,19 +1076,18 @@ impl<TP: TreeProperties> Default for NodeState<TP> {
 	}
 }
 
-impl<TP> Clone for NodeState<TP>
+impl<TP> NodeState<TP>
 where
 	TP: TreeProperties,
 	TP::Value: Clone,
 {
-	fn clone(&self) -> Self {
-		match self {
-			Self::InnerNode { children } => Self::InnerNode {
-				children: children.clone(),
-			},
-			Self::Leaf { value } => Self::Leaf {
-				value: value.clone(),
-			},
+	// inner node with shallow clones of the children (see `Node::clone_shallow`)
+	fn new_inner_shallow(children: &Children<TP>) -> Self {
+		Self::InnerNode {
+			children: Box::new(Children {
+				left: children.left.clone_shallow(),
+				right: children.right.clone_shallow(),
+			}),
 		}
 	}
 }
@@ -678,15 +1113,158 @@ struct Children<TP: TreeProperties> {
 	right: Node<TP>,
 }
 
-impl<TP> Clone for Children<TP>
+/// Structural statistics of a [`Tree`]
+///
+/// See [`Tree::stats`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct TreeStats {
+	/// Number of inner nodes
+	pub inner_nodes: usize,
+	/// Number of leaf nodes
+	pub leaf_nodes: usize,
+	/// Maximum number of nodes on a path from the root node to a leaf
+	pub max_depth: usize,
+	/// Maximum length of a key
+	pub max_key_len: usize,
+	/// Bytes allocated for nodes on the heap (estimated by type size)
+	///
+	/// Doesn't include heap memory owned by keys or values.
+	pub heap_bytes: usize,
+}
+
+/// Violated structural invariant of a [`Tree`]
+///
+/// See [`Tree::check_invariants`].
+pub enum InvariantError<TP: TreeProperties> {
+	/// Key of child node isn't longer than the key of its parent node
+	ChildKeyTooShort {
+		/// Key of the child node
+		key: TP::Key,
+	},
+	/// Key of child node doesn't start with the key of its parent node
+	ChildKeyNotPrefixed {
+		/// Key of the child node
+		key: TP::Key,
+	},
+	/// Bit of child key after the parent key doesn't match the side of the child node
+	ChildWrongSide {
+		/// Key of the child node
+		key: TP::Key,
+	},
+	/// Both child nodes are leafs exactly one bit longer than their parent
+	/// node and their leaf values should have been merged
+	UnmergedLeafs {
+		/// Key of the parent node
+		key: TP::Key,
+	},
+	/// Stored leaf count doesn't match the number of leaf nodes
+	LeafCountMismatch {
+		/// Stored leaf count
+		stored: usize,
+		/// Number of leaf nodes in the tree
+		counted: usize,
+	},
+}
+
+impl<TP: TreeProperties> InvariantError<TP> {
+	/// Key of the node violating the invariant
+	///
+	/// `None` if the violation isn't about a single node.
+	pub fn key(&self) -> Option<&TP::Key> {
+		match self {
+			Self::ChildKeyTooShort { key }
+			| Self::ChildKeyNotPrefixed { key }
+			| Self::ChildWrongSide { key }
+			| Self::UnmergedLeafs { key } => Some(key),
+			Self::LeafCountMismatch { .. } => None,
+		}
+	}
+}
+
+impl<TP: TreeProperties> Clone for InvariantError<TP> {
+	fn clone(&self) -> Self {
+		match self {
+			Self::ChildKeyTooShort { key } => Self::ChildKeyTooShort { key: key.clone() },
+			Self::ChildKeyNotPrefixed { key } => Self::ChildKeyNotPrefixed { key: key.clone() },
+			Self::ChildWrongSide { key } => Self::ChildWrongSide { key: key.clone() },
+			Self::UnmergedLeafs { key } => Self::UnmergedLeafs { key: key.clone() },
+			Self::LeafCountMismatch { stored, counted } => Self::LeafCountMismatch {
+				stored: *stored,
+				counted: *counted,
+			},
+		}
+	}
+}
+
+impl<TP: TreeProperties> PartialEq for InvariantError<TP> {
+	fn eq(&self, other: &Self) -> bool {
+		match (self, other) {
+			(
+				Self::LeafCountMismatch { stored, counted },
+				Self::LeafCountMismatch {
+					stored: other_stored,
+					counted: other_counted,
+				},
+			) => stored == other_stored && counted == other_counted,
+			_ => {
+				core::mem::discriminant(self) == core::mem::discriminant(other)
+					&& self.key() == other.key()
+			},
+		}
+	}
+}
+
+impl<TP: TreeProperties> Eq for InvariantError<TP> {}
+
+impl<TP> fmt::Debug for InvariantError<TP>
 where
 	TP: TreeProperties,
-	TP::Value: Clone,
+	TP::Key: fmt::Debug,
 {
-	fn clone(&self) -> Self {
-		Self {
-			left: self.left.clone(),
-			right: self.right.clone(),
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let (name, key) = match self {
+			Self::ChildKeyTooShort { key } => ("ChildKeyTooShort", key),
+			Self::ChildKeyNotPrefixed { key } => ("ChildKeyNotPrefixed", key),
+			Self::ChildWrongSide { key } => ("ChildWrongSide", key),
+			Self::UnmergedLeafs { key } => ("UnmergedLeafs", key),
+			Self::LeafCountMismatch { stored, counted } => {
+				return f
+					.debug_struct("LeafCountMismatch")
+					.field("stored", stored)
+					.field("counted", counted)
+					.finish();
+			},
+		};
+		f.debug_struct(name).field("key", key).finish()
+	}
+}
+
+impl<TP> fmt::Display for InvariantError<TP>
+where
+	TP: TreeProperties,
+	TP::Key: fmt::Debug,
+{
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::ChildKeyTooShort { key } => {
+				write!(f, "child key {:?} isn't longer than its parent key", key)
+			},
+			Self::ChildKeyNotPrefixed { key } => {
+				write!(f, "child key {:?} doesn't start with its parent key", key)
+			},
+			Self::ChildWrongSide { key } => {
+				write!(f, "child key {:?} is on the wrong side of its parent", key)
+			},
+			Self::UnmergedLeafs { key } => {
+				write!(f, "child leafs of {:?} should have been merged", key)
+			},
+			Self::LeafCountMismatch { stored, counted } => {
+				write!(
+					f,
+					"stored leaf count {} doesn't match {} leaf nodes",
+					stored, counted
+				)
+			},
 		}
 	}
 }
@@ -697,6 +1275,9 @@ where
 /// Both node types carry keys and values, leaf nodes an additional leaf value (of different type).
 pub struct Tree<TP: TreeProperties> {
 	node: Option<Node<TP>>,
+	// number of leaf nodes; unknown after handing out mutable nodes (their
+	// structure might change), recounted by the next counted modification
+	leaf_count: Option<usize>,
 }
 
 impl<TP: TreeProperties> Default for Tree<TP> {
@@ -713,8 +1294,20 @@ where
 	fn clone(&self) -> Self {
 		Self {
 			node: self.node.clone(),
+			leaf_count: self.leaf_count,
 		}
 	}
+
+	fn clone_from(&mut self, source: &Self) {
+		match (&mut self.node, &source.node) {
+			// reuse nodes (see `Node::clone_from`)
+			(Some(node), Some(source_node)) => node.clone_from(source_node),
+			// drop without recursion
+			(_, None) => self.clear(),
+			(None, Some(source_node)) => self.node = Some(source_node.clone()),
+		}
+		self.leaf_count = source.leaf_count;
+	}
 }
 
 impl<TP> fmt::Debug for Tree<TP>
@@ -740,7 +1333,221 @@ impl<TP: TreeProperties> Tree<TP> {
 	/// New (empty) tree.
 	pub const fn new() -> Self {
 		assert!(tp_valid::<TP>()); // TODO: make it a static assert somehow?
-		Self { node: None }
+		Self {
+			node: None,
+			leaf_count: Some(0),
+		}
+	}
+
+	// tree with given root node; the caller is responsible for a valid structure
+	pub(crate) fn from_root(node: Option<Node<TP>>) -> Self {
+		let leaf_count = node.as_ref().map_or(0, Node::leaf_count);
+		Self::from_root_counted(node, leaf_count)
+	}
+
+	// like `from_root`, but the caller also knows the number of leafs
+	pub(crate) fn from_root_counted(node: Option<Node<TP>>, leaf_count: usize) -> Self {
+		assert!(tp_valid::<TP>());
+		Self {
+			node,
+			leaf_count: Some(leaf_count),
+		}
+	}
+
+	// mutable access to nodes: structure might change in unknown ways
+	pub(in crate::tree) fn node_mut(&mut self) -> &mut Option<Node<TP>> {
+		self.leaf_count = None;
+		&mut self.node
+	}
+
+	// mutable root and (re)counted leaf count; the caller has to keep the
+	// count in sync with structural changes
+	pub(crate) fn root_mut_counted(&mut self) -> (Option<&mut Node<TP>>, &mut usize) {
+		let Self { node, leaf_count } = self;
+		let leaf_count =
+			leaf_count.get_or_insert_with(|| node.as_ref().map_or(0, Node::leaf_count));
+		(node.as_mut(), leaf_count)
+	}
+
+	/// Number of leaf nodes
+	///
+	/// Runs in constant time unless the tree was modified through mutable
+	/// references to its nodes (e.g. through [`walk_mut`](Self::walk_mut));
+	/// the leafs are counted then, until the next modification through
+	/// the tree (e.g. [`set_leaf_value`](Self::set_leaf_value)) stores the
+	/// count again.
+	pub fn leaf_count(&self) -> usize {
+		match self.leaf_count {
+			Some(leaf_count) => leaf_count,
+			None => self.node.as_ref().map_or(0, Node::leaf_count),
+		}
+	}
+
+	/// Number of (inner and leaf) nodes
+	///
+	/// See [`leaf_count`](Self::leaf_count); inner nodes always have two
+	/// children, so there is one inner node less than leaf nodes.
+	pub fn node_count(&self) -> usize {
+		match self.leaf_count() {
+			0 => 0,
+			leaf_count => 2 * leaf_count - 1,
+		}
+	}
+
+	// extract root node
+	pub(crate) fn into_root(self) -> Option<Node<TP>> {
+		self.node
+	}
+
+	/// Collect structural statistics
+	///
+	/// Traverses the complete tree.
+	pub fn stats(&self) -> TreeStats {
+		let mut stats = TreeStats::default();
+		let mut stack: Vec<(&Node<TP>, usize)> = self.node.iter().map(|root| (root, 1)).collect();
+		while let Some((node, depth)) = stack.pop() {
+			stats.max_depth = stats.max_depth.max(depth);
+			stats.max_key_len = stats.max_key_len.max(node.key.len());
+			match node.get_children() {
+				Some((left, right)) => {
+					stats.inner_nodes += 1;
+					// both children are allocated together
+					stats.heap_bytes += core::mem::size_of::<Children<TP>>();
+					stack.push((right, depth + 1));
+					stack.push((left, depth + 1));
+				},
+				None => stats.leaf_nodes += 1,
+			}
+		}
+		stats
+	}
+
+	/// Check structural invariants
+	///
+	/// Mostly useful to debug containers built on [`Tree`] that modify
+	/// nodes directly. Reports the first violation found in pre-order:
+	/// child keys must extend the key of their parent by at least one bit,
+	/// and the first additional bit must match the side of the child; with
+	/// [`TreeProperties::EMPTY`] sibling leafs that can be merged (see
+	/// [`LeafValueComparer::merge`]) must have been merged. A stored
+	/// [`leaf_count`](Self::leaf_count) must match the number of leaf
+	/// nodes.
+	///
+	/// Traverses the complete tree.
+	pub fn check_invariants(&self) -> Result<(), InvariantError<TP>> {
+		let mut counted = 0;
+		let mut stack: Vec<&Node<TP>> = self.node.iter().collect();
+		while let Some(node) = stack.pop() {
+			let (left, right) = match node.get_children() {
+				Some(children) => children,
+				None => {
+					counted += 1;
+					continue;
+				},
+			};
+			let key_len = node.key.len();
+			for (child, right_side) in [(left, false), (right, true)] {
+				if child.key.len() <= key_len {
+					return Err(InvariantError::ChildKeyTooShort {
+						key: child.key.clone(),
+					});
+				}
+				if child.key.shared_prefix_len(&node.key) < key_len {
+					return Err(InvariantError::ChildKeyNotPrefixed {
+						key: child.key.clone(),
+					});
+				}
+				if child.key.get(key_len) != right_side {
+					return Err(InvariantError::ChildWrongSide {
+						key: child.key.clone(),
+					});
+				}
+			}
+			// `merged_children_value` only merges with `TP::EMPTY`
+			if node.merged_children_value().is_some() {
+				return Err(InvariantError::UnmergedLeafs {
+					key: node.key.clone(),
+				});
+			}
+			stack.push(right);
+			stack.push(left);
+		}
+		match self.leaf_count {
+			Some(stored) if stored != counted => {
+				Err(InvariantError::LeafCountMismatch { stored, counted })
+			},
+			_ => Ok(()),
+		}
+	}
+
+	/// Whether both trees have the same leafs
+	///
+	/// Compares keys and leaf values (using [`TreeProperties::LeafValueComparer`])
+	/// of all leafs in-order; inner nodes and their values are ignored.
+	pub fn leaf_eq(&self, other: &Self) -> bool {
+		self.leaf_eq_by(other, TP::LeafValueComparer::eq)
+	}
+
+	/// Whether both trees have the same leafs, comparing leaf values with `eq`
+	///
+	/// See [`leaf_eq`](Self::leaf_eq).
+	pub fn leaf_eq_by<F>(&self, other: &Self, mut eq: F) -> bool
+	where
+		F: FnMut(&TP::LeafValue, &TP::LeafValue) -> bool,
+	{
+		let mut a = self.iter_leaf();
+		let mut b = other.iter_leaf();
+		loop {
+			match (a.next(), b.next()) {
+				(None, None) => return true,
+				(Some((a_node, a_value)), Some((b_node, b_value))) => {
+					if a_node.key != b_node.key || !eq(a_value, b_value) {
+						return false;
+					}
+				},
+				_ => return false,
+			}
+		}
+	}
+
+	/// Whether both trees have the same structure
+	///
+	/// Compares keys and values of all nodes, and leaf values (using
+	/// [`TreeProperties::LeafValueComparer`]) of all leafs.
+	pub fn structural_eq(&self, other: &Self) -> bool
+	where
+		TP::Value: PartialEq,
+	{
+		let mut stack: Vec<(&Node<TP>, &Node<TP>)> = match (&self.node, &other.node) {
+			(None, None) => return true,
+			(Some(a), Some(b)) => Vec::from([(a, b)]),
+			_ => return false,
+		};
+		while let Some((a, b)) = stack.pop() {
+			if a.key != b.key || a.value != b.value {
+				return false;
+			}
+			match (&a.state, &b.state) {
+				(NodeState::Leaf { value: a_value }, NodeState::Leaf { value: b_value }) => {
+					if !TP::LeafValueComparer::eq(a_value, b_value) {
+						return false;
+					}
+				},
+				(
+					NodeState::InnerNode {
+						children: a_children,
+					},
+					NodeState::InnerNode {
+						children: b_children,
+					},
+				) => {
+					stack.push((&a_children.right, &b_children.right));
+					stack.push((&a_children.left, &b_children.left));
+				},
+				_ => return false,
+			}
+		}
+		true
 	}
 
 	/// Set a new prefix => value mapping.
@@ -750,18 +1557,118 @@ impl<TP: TreeProperties> Tree<TP> {
 	///
 	/// Sibling prefixes that share the same leaf value are merged.
 	pub fn set_leaf_value(&mut self, key: TP::Key, value: TP::LeafValue) {
+		self.set_leaf_value_counted(key, value);
+	}
+
+	/// Set a new prefix => value mapping, merging with existing leaf values
+	///
+	/// Where the prefix overlaps existing leafs their new leaf value is
+	/// `merge(key, old)`; a leaf with a shorter key covering the prefix is
+	/// passed with the prefix as key. All other bit strings with the prefix
+	/// get `value`.
+	///
+	/// Sibling prefixes that share the same leaf value are merged.
+	pub fn set_leaf_value_with<F>(&mut self, key: TP::Key, value: TP::LeafValue, mut merge: F)
+	where
+		F: FnMut(&TP::Key, &TP::LeafValue) -> TP::LeafValue,
+	{
+		let leaf_count = *self.root_mut_counted().1;
+		let mut walk = self.walk_mut::<(), ()>();
+		match walk.goto_insert(&key) {
+			None => (),
+			Some(InsertPosition::BelowLeaf) => {
+				let node = walk.current().node().expect("should be at leaf");
+				let old_value = node.get_leaf_value().expect("should be at leaf");
+				let value = merge(&key, old_value);
+				self.leaf_count = Some(leaf_count);
+				self.set_leaf_value(key, value);
+				return;
+			},
+			Some(InsertPosition::AlreadyExists) | Some(InsertPosition::ReplaceNode) => {
+				let node = walk.current_mut().node().expect("should be at node");
+				if node.key.shared_prefix_len(&key) == key.len() {
+					// node in subtree of key
+					let mut changes = LeafChanges {
+						changed: true,
+						added: 0,
+						removed: node.leaf_count(),
+					};
+					node.merge_leaf_values(&value, &mut merge);
+					node.extend_up(key.len(), &value);
+					changes.added = node.leaf_count();
+					// compress while walking up the tree until compress fails
+					if TP::EMPTY {
+						while walk.up().is_some() {
+							match walk.current_mut() {
+								NodeOrTree::Tree(_) => break,
+								NodeOrTree::Node(node) => {
+									if !node.compress() {
+										break;
+									}
+									// two leafs merged into one
+									changes.removed += 1;
+								},
+							}
+						}
+					}
+					self.leaf_count = Some(leaf_count + changes.added - changes.removed);
+					return;
+				}
+			},
+		}
+		// nothing overlapping
+		self.leaf_count = Some(leaf_count);
+		self.set_leaf_value(key, value);
+	}
+
+	/// Set a new prefix => value mapping and return the replaced leafs
+	///
+	/// See [`set_leaf_value`](Self::set_leaf_value). A leaf with a shorter key
+	/// covering the new prefix is reported with the new prefix as key. Leafs
+	/// with a value equal to the new value (by `TP::LeafValueComparer`) are
+	/// not reported.
+	pub fn set_leaf_value_replacing(
+		&mut self,
+		key: TP::Key,
+		value: TP::LeafValue,
+	) -> ReplacedLeaves<TP> {
+		let mut replaced = Vec::new();
+		self.set_leaf_value_inner(key, value, Some(&mut replaced));
+		ReplacedLeaves::new(replaced)
+	}
+
+	// like `set_leaf_value`, but reports what changed
+	pub(crate) fn set_leaf_value_counted(
+		&mut self,
+		key: TP::Key,
+		value: TP::LeafValue,
+	) -> LeafChanges {
+		self.set_leaf_value_inner(key, value, None)
+	}
+
+	// optionally collects the replaced leafs (see `Node::insert_leaf_value`)
+	fn set_leaf_value_inner(
+		&mut self,
+		key: TP::Key,
+		value: TP::LeafValue,
+		replaced: Option<&mut Vec<(TP::Key, TP::LeafValue)>>,
+	) -> LeafChanges {
+		let leaf_count = *self.root_mut_counted().1;
 		let mut walk = self.walk_mut::<(), ()>();
 		walk.goto_insert(&key);
 
-		match walk.inner.walk.current_mut() {
+		let mut changes = match walk.inner.walk.current_mut() {
 			NodeOrTree::Tree(root) => {
 				assert!(root.is_none());
 				*root = Some(Node::new_leaf(key, Default::default(), value));
+				LeafChanges {
+					changed: true,
+					added: 1,
+					removed: 0,
+				}
 			},
-			NodeOrTree::Node(node) => {
-				node.insert_leaf_value(key, value);
-			},
-		}
+			NodeOrTree::Node(node) => node.insert_leaf_value(key, value, replaced),
+		};
 
 		// compress while walking up the tree until compress fails
 		if TP::EMPTY {
@@ -772,10 +1679,152 @@ impl<TP: TreeProperties> Tree<TP> {
 						if !node.compress() {
 							break;
 						}
+						// two leafs merged into one
+						changes.removed += 1;
+					},
+				}
+			}
+		}
+		self.leaf_count = Some(leaf_count + changes.added - changes.removed);
+		changes
+	}
+
+	/// Delete everything covered by the key
+	///
+	/// If a leaf covers the key it is split up along the path to the key first
+	/// (unless `TP::IGNORE_LEAFS`: shorter prefixes are kept then).
+	///
+	/// Unless `TP::EMPTY` inner node values are significant: a deleted subtree
+	/// is replaced by a leaf with default values instead of replacing the
+	/// parent node by the sibling, as the parent might have a value.
+	pub fn delete(&mut self, key: &TP::Key) {
+		self.delete_counted(key);
+	}
+
+	// like `delete`, but reports what got deleted
+	pub(crate) fn delete_counted(&mut self, key: &TP::Key) -> Deleted {
+		self.delete_compacting(key, |_| false)
+	}
+
+	// like `delete`, but doesn't leave an empty leaf (as considered by
+	// `is_empty`) behind if it can be removed (see `WalkMutOwned::compact_if_empty`)
+	pub(crate) fn delete_compacting<F>(&mut self, key: &TP::Key, is_empty: F) -> Deleted
+	where
+		F: Fn(&TP::Value) -> bool,
+	{
+		let leaf_count = *self.root_mut_counted().1;
+		let (deleted, changes) = self.delete_inner(key, is_empty);
+		self.leaf_count = Some(leaf_count + changes.added - changes.removed);
+		deleted
+	}
+
+	// also returns how the number of leafs changed
+	fn delete_inner<F>(&mut self, key: &TP::Key, is_empty: F) -> (Deleted, LeafChanges)
+	where
+		F: Fn(&TP::Value) -> bool,
+	{
+		let mut walk = self.walk_mut::<WalkedDirection, ()>();
+		let deleted = match walk.goto_insert(key) {
+			None => return (Deleted::Nothing, LeafChanges::default()), // empty tree
+			Some(InsertPosition::BelowLeaf) => {
+				if TP::IGNORE_LEAFS {
+					// leaf has a shorter key
+					return (Deleted::Nothing, LeafChanges::default());
+				}
+				// node is a leaf and covers key; need to split and remove key
+				let leaf_len = walk
+					.current()
+					.node()
+					.expect("should be at leaf")
+					.get_key()
+					.len();
+				// create explicit node with key we want to remove
+				walk.insert(key.clone());
+				// leaf gets replaced by the siblings along the path to key
+				Deleted::Split {
+					added: key.len() - leaf_len,
+				}
+			},
+			Some(InsertPosition::AlreadyExists) => {
+				let node = walk.current().node().expect("should be at node");
+				Deleted::Exact {
+					removed: node.leaf_count(),
+				}
+			},
+			Some(InsertPosition::ReplaceNode) => {
+				let node = walk.current().node().expect("should be at node");
+				if node.get_key().shared_prefix_len(key) < key.len() {
+					// node not in subtree of key
+					return (Deleted::Nothing, LeafChanges::default());
+				}
+				Deleted::Contained {
+					removed: node.leaf_count(),
+				}
+			},
+		};
+		let mut changes = match deleted {
+			Deleted::Nothing => LeafChanges::default(),
+			Deleted::Split { added } => LeafChanges {
+				changed: true,
+				added,
+				removed: 1,
+			},
+			Deleted::Exact { removed } | Deleted::Contained { removed } => LeafChanges {
+				changed: true,
+				added: 0,
+				removed,
+			},
+		};
+
+		if TP::EMPTY {
+			// remove subtree; parent gets replaced by sibling
+			walk.delete_current();
+			// compress while walking up the tree until compress fails
+			while walk.up().is_some() {
+				match walk.current_mut() {
+					NodeOrTree::Tree(_) => break,
+					NodeOrTree::Node(node) => {
+						if !node.compress() {
+							break;
+						}
+						// two leafs merged into one
+						changes.removed += 1;
 					},
 				}
 			}
+		} else {
+			let node_key = walk
+				.current()
+				.node()
+				.expect("should be at node")
+				.get_key()
+				.clone();
+			let delete_right = walk.up() == Some(WalkedDirection::Right);
+			match walk.into_current_mut() {
+				NodeOrTree::Tree(_) => {
+					// deleting root node
+					self.clear();
+				},
+				NodeOrTree::Node(node) => {
+					let sibling = node.get_child(!delete_right).expect("should have children");
+					if is_empty(&node.value) {
+						// parent gets replaced by sibling
+						node.delete_side(delete_right);
+					} else if sibling.is_leaf() && is_empty(&sibling.value) {
+						// both children would be empty leafs: parent becomes a leaf
+						// instead of the sibling
+						node.state = Default::default();
+					} else {
+						let child = node
+							.get_child_mut(delete_right)
+							.expect("should have children");
+						*child = Node::new_leaf(node_key, Default::default(), Default::default());
+						changes.added += 1;
+					}
+				},
+			}
 		}
+		(deleted, changes)
 	}
 
 	/// Get reference to root node
@@ -785,7 +1834,38 @@ impl<TP: TreeProperties> Tree<TP> {
 
 	/// Get mutable reference to root node
 	pub fn root_mut(&mut self) -> Option<&mut Node<TP>> {
-		self.node.as_mut()
+		self.node_mut().as_mut()
+	}
+
+	/// Get leaf node with the smallest key (leftmost leaf)
+	pub fn first_leaf(&self) -> Option<&Node<TP>> {
+		let mut node = self.node.as_ref()?;
+		while let Some(left) = node.get_left() {
+			node = left;
+		}
+		Some(node)
+	}
+
+	/// Get leaf node with the largest key (rightmost leaf)
+	pub fn last_leaf(&self) -> Option<&Node<TP>> {
+		let mut node = self.node.as_ref()?;
+		while let Some(right) = node.get_right() {
+			node = right;
+		}
+		Some(node)
+	}
+
+	/// Remove all nodes from tree
+	///
+	/// Nodes are dropped with an explicit stack instead of recursion.
+	pub fn clear(&mut self) {
+		self.leaf_count = Some(0);
+		drop_nodes(self.node.take().into_iter().collect());
+	}
+
+	/// Take all nodes out of the tree, leaving an empty tree
+	pub fn take(&mut self) -> Self {
+		take(self)
 	}
 
 	/// Get reference to node with exact key
@@ -814,7 +1894,7 @@ impl<TP: TreeProperties> Tree<TP> {
 		&'r mut self,
 		key: &TP::Key,
 	) -> Option<InsertPositionWith<&'r mut Node<TP>>> {
-		Some(self.node.as_mut()?.goto_insert(key))
+		Some(self.node_mut().as_mut()?.goto_insert(key))
 	}
 
 	/// Get a reference to the node with the longest prefix satisfying callback of the target key
@@ -851,6 +1931,7 @@ impl<TP: TreeProperties> Tree<TP> {
 	}
 
 	/// Get a reference to the node with the longest prefix satisfying callback of the target key
+	#[allow(clippy::needless_return)]
 	pub fn get_longest_prefix_mut_with<'r, F>(
 		&'r mut self,
 		key: &TP::Key,
@@ -860,7 +1941,7 @@ impl<TP: TreeProperties> Tree<TP> {
 		F: FnMut(&mut Node<TP>) -> bool,
 	{
 		let key_len = key.len();
-		let mut step = self.node.as_mut()?.lookup_initial_step(key, key_len);
+		let mut step = self.node_mut().as_mut()?.lookup_initial_step(key, key_len);
 		let mut result = None;
 		loop {
 			step = match step {
@@ -905,7 +1986,7 @@ impl<TP: TreeProperties> Tree<TP> {
 	/// Get a mutable reference to the node with the longest prefix of the target key
 	pub fn get_most_specific_mut<'r>(&'r mut self, key: &TP::Key) -> Option<&'r mut Node<TP>> {
 		let key_len = key.len();
-		let mut current = match self.node.as_mut()?.lookup_initial_step(key, key_len) {
+		let mut current = match self.node_mut().as_mut()?.lookup_initial_step(key, key_len) {
 			LookupStepWith::Path(node, _) => node,
 			LookupStepWith::Found(node, _) => return Some(node),
 			LookupStepWith::Miss => return None,
@@ -952,45 +2033,79 @@ impl<TP: TreeProperties> Tree<TP> {
 		IterLeaf::new(self)
 	}
 
+	/// Iterate over nodes and leaf values of tree in-order, restricted to nodes with the given key as prefix
+	///
+	/// If the key is covered by a leaf with a shorter key, that leaf is the only item.
+	pub fn iter_leaf_in(&self, key: &TP::Key) -> IterLeafIn<'_, TP> {
+		IterLeafIn::new(self, key)
+	}
+
 	/// Iterate over nodes and leaf values and uncovered keys of tree in-order
 	pub fn iter_leaf_full(&self) -> IterLeafFull<'_, TP> {
 		IterLeafFull::new(self)
 	}
 
+	/// Iterate over keys and leaf values of tree in-order, consuming the tree
+	pub fn into_iter_leaf(self) -> IntoIterLeaf<TP> {
+		let leaf_count = self.leaf_count();
+		IntoIterLeaf::new(self.node, leaf_count)
+	}
+
+	/// Iterate over keys and values of all nodes of tree depth-first in-order, consuming the tree
+	pub fn into_iter_in_order(self) -> IntoIterInOrder<TP> {
+		IntoIterInOrder::new(self.node)
+	}
+
 	/// Walk mutable tree
+	///
+	/// The structure might get modified through the nodes it hands out, so the
+	/// [`leaf_count`](Self::leaf_count) is recounted afterwards.
 	pub fn walk_mut<D, A>(&mut self) -> WalkMutOwned<'_, TP, D, A> {
 		WalkMutOwned {
 			inner: mut_gen::WalkMut::new(self),
 		}
 	}
 
+	// walk keeping the leaf count up to date: the caller must change the
+	// structure only through the walk (or report it with `leafs_changed`),
+	// not through the nodes it hands out
+	pub(crate) fn walk_mut_counted<D, A>(&mut self) -> WalkMutOwned<'_, TP, D, A> {
+		WalkMutOwned {
+			inner: mut_gen::WalkMut::new_counted(self),
+		}
+	}
+
 	/// Iterate over keys and mutable values of tree that are a prefix of target key
 	pub fn iter_mut_path(&mut self, key: TP::Key) -> MutPath<'_, TP> {
+		// only values can be modified
 		MutPath::new(self.node.as_mut(), key)
 	}
 
 	/// Iterate over keys and mutable values of tree depth-first pre-order
 	pub fn iter_mut_pre_order(&mut self) -> IterMutOwnedPreOrder<'_, TP> {
-		self.walk_mut().into_iter_pre_order()
+		self.walk_mut_counted().into_iter_pre_order()
 	}
 
 	/// Iterate over keys and mutable values of tree depth-first in-order
 	pub fn iter_mut_in_order(&mut self) -> IterMutOwnedInOrder<'_, TP> {
-		self.walk_mut().into_iter_in_order()
+		self.walk_mut_counted().into_iter_in_order()
 	}
 
 	/// Iterate over keys and mutable values of tree depth-first post-order
 	pub fn iter_mut_post_order(&mut self) -> IterMutOwnedPostOrder<'_, TP> {
-		self.walk_mut().into_iter_post_order()
+		self.walk_mut_counted().into_iter_post_order()
 	}
 
 	/// Iterate over keys and mutable leaf values of tree in-order
 	pub fn iter_mut_leaf(&mut self) -> IterMutOwnedLeaf<'_, TP> {
-		self.walk_mut().into_iter_leafs()
+		let leaf_count = *self.root_mut_counted().1;
+		self.walk_mut_counted()
+			.into_iter_leafs()
+			.with_remaining(leaf_count)
 	}
 
 	/// Iterate over keys and mutable leaf values and uncovered keys of tree in-order
 	pub fn iter_mut_leaf_full(&mut self) -> IterMutOwnedLeafFull<'_, TP> {
-		self.walk_mut().into_iter_full_leafs()
+		self.walk_mut_counted().into_iter_full_leafs()
 	}
 }
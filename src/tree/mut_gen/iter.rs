@@ -1,3 +1,5 @@
+#![allow(clippy::mem_replace_option_with_some)]
+
 use crate::{
 	iter::{
 		iter_between,
@@ -1,3 +1,5 @@
+#![allow(clippy::needless_return)]
+
 use bitstring::BitString;
 
 use crate::{
@@ -139,12 +141,26 @@ pub(in crate::tree) struct WalkMut<
 	A = (),
 > {
 	pub(in crate::tree) walk: O::WalkMut,
+	// leaf count of the tree; kept in sync by structural changes through the walk
+	leaf_count: Option<&'r mut usize>,
 }
 
 impl<'r, TP: TreeProperties + 'r, D, A> WalkMut<'r, TP, Owned, D, A> {
 	pub(in crate::tree) fn new(tree: &'r mut Tree<TP>) -> Self {
 		Self {
-			walk: crate::walk_mut::WalkMut::new(&mut tree.node),
+			walk: crate::walk_mut::WalkMut::new(tree.node_mut()),
+			leaf_count: None,
+		}
+	}
+
+	pub(in crate::tree) fn new_counted(tree: &'r mut Tree<TP>) -> Self {
+		let Tree { node, leaf_count } = tree;
+		// recount after mutable node access
+		let leaf_count =
+			leaf_count.get_or_insert_with(|| node.as_ref().map_or(0, Node::leaf_count));
+		Self {
+			walk: crate::walk_mut::WalkMut::new(node),
+			leaf_count: Some(leaf_count),
 		}
 	}
 }
@@ -186,6 +202,19 @@ where
 	pub fn into_current_mut(self) -> NodeOrTree<Option<&'r mut Node<TP>>, &'r mut Node<TP>> {
 		O::into_current_mut(self.walk)
 	}
+
+	/// Update leaf count after changing the structure below the current node
+	pub fn leafs_changed(&mut self, added: usize, removed: usize) {
+		if let Some(leaf_count) = &mut self.leaf_count {
+			**leaf_count = **leaf_count + added - removed;
+		}
+	}
+
+	fn leafs_cleared(&mut self) {
+		if let Some(leaf_count) = &mut self.leaf_count {
+			**leaf_count = 0;
+		}
+	}
 }
 
 impl<'r, TP> WalkMut<'r, TP, Owned, WalkedDirection, ()>
@@ -225,13 +254,16 @@ where
 					let delete_right = walked.0 == WalkedDirection::Right;
 					match self.walk.current_mut() {
 						NodeOrTree::Node(node) => {
+							let removed = node.get_child(delete_right).map_or(0, Node::leaf_count);
 							node.delete_side(delete_right);
+							self.leafs_changed(0, removed);
 							return Some(walked);
 						},
 						// shouldn't have been able to walk left/right from tree;
 						// anyway: delete previous node == full tree.
 						NodeOrTree::Tree(tree) => {
 							*tree = None;
+							self.leafs_cleared();
 							return Some(walked);
 						},
 					}
@@ -240,9 +272,41 @@ where
 		}
 		// either up() already was at tree, or explicit fallthrough above
 		*self.walk.pop_all() = None;
+		self.leafs_cleared();
 		None
 	}
 
+	/// Delete either left or right side of the current node, replacing it with the other side
+	///
+	/// Does nothing if the current node is a leaf (or at tree).
+	pub fn delete_side(&mut self, delete_right: bool) {
+		if let NodeOrTree::Node(node) = self.walk.current_mut() {
+			let removed = node.get_child(delete_right).map_or(0, Node::leaf_count);
+			node.delete_side(delete_right);
+			self.leafs_changed(0, removed);
+		}
+	}
+
+	/// Merge the child leafs of the current node if their values can be merged
+	///
+	/// Returns true if the current node is a leaf afterwards.
+	pub fn compress(&mut self) -> bool {
+		match self.walk.current_mut() {
+			NodeOrTree::Tree(_) => false,
+			NodeOrTree::Node(node) => {
+				if node.is_leaf() {
+					return true;
+				}
+				if !node.compress() {
+					return false;
+				}
+				// two leafs merged into one
+				self.leafs_changed(0, 1);
+				true
+			},
+		}
+	}
+
 	/// Remove empty leaf nodes if possible
 	///
 	/// A node is considered "empty" if the passed function considers its value empty.
@@ -273,6 +337,7 @@ where
 				if let Some(root) = tree {
 					if is_empty(&root.value) {
 						*tree = None;
+						self.leafs_cleared();
 					}
 				}
 			},
@@ -283,8 +348,10 @@ where
 				if let Some((left, right)) = node.get_children() {
 					if left.is_leaf() && is_empty(&left.value) {
 						node.delete_side(false); // delete left empty leaf
+						self.leafs_changed(0, 1);
 					} else if right.is_leaf() && is_empty(&right.value) {
 						node.delete_side(true); // delete right empty leaf
+						self.leafs_changed(0, 1);
 					}
 					// even if we deleted an empty child node, assume that at least one wasn't an empty leaf,
 					// otherwise we shouldn't have needed an inner node at the previous `node`
@@ -295,6 +362,7 @@ where
 						WalkedDirection::Down => {
 							// node was last node in tree, drop it
 							*self.walk.pop_all() = None;
+							self.leafs_cleared();
 							return;
 						},
 						WalkedDirection::Left => false,
@@ -304,16 +372,19 @@ where
 						NodeOrTree::Tree(tree) => {
 							// should have gotten `WalkedDirection::Down` above, but clear tree anyway
 							*tree = None;
+							self.leafs_cleared();
 						},
 						NodeOrTree::Node(node) => {
 							if is_empty(&node.value) {
 								node.delete_side(delete_side);
+								self.leafs_changed(0, 1);
 							} else {
 								let sibling =
 									node.get_child(!delete_side).expect("sibling should exist");
 								if sibling.is_leaf() && is_empty(&sibling.value) {
 									// both child nodes are empty leafs: make a leaf node
 									node.state = Default::default();
+									self.leafs_changed(0, 1);
 								}
 							}
 						},
@@ -446,6 +517,23 @@ where
 		}
 	}
 
+	// continue a path walked before (by `path` or `resume_path`) with the same key from the
+	// current position; the current node isn't returned again.
+	pub(crate) fn resume_path(&mut self, key: TP::Key) -> WalkMutPath<'r, '_, TP, O, D> {
+		let target_len = key.len();
+		let (start, done) = match self.current() {
+			NodeOrTree::Tree(_) => (true, false),
+			NodeOrTree::Node(node) => (false, node.get_key().len() >= target_len),
+		};
+		WalkMutPath {
+			start,
+			done,
+			walk: self,
+			target_len,
+			target: key,
+		}
+	}
+
 	// first need go up until current_node.key is a prefix of key (or we are at the root)
 	fn goto_clean(&mut self, key: &TP::Key) {
 		let key_len = key.len();
@@ -506,7 +594,14 @@ where
 		match self.goto_insert(&key) {
 			Some(InsertPosition::BelowLeaf) => {
 				let node = self.walk.current_mut().node().expect("should be at leaf");
+				// leaf gets replaced by the new leaf and its siblings along the path
+				let added = if TP::IGNORE_LEAFS {
+					1
+				} else {
+					key.len() - node.key.len()
+				};
 				node.insert_sub_leaf(key.clone(), Default::default());
+				self.leafs_changed(added, 0);
 				self.goto_insert_down(&key);
 			},
 			Some(InsertPosition::AlreadyExists) => (),
@@ -519,9 +614,11 @@ where
 					sibling_key.clip(shared_prefix_len + 1);
 					sibling_key.flip(shared_prefix_len);
 					node.insert_leaf_sibling(shared_prefix_len, sibling_key, Default::default());
+					self.leafs_changed(1, 0);
 					// already at correct node (that was replaced by the shared prefix == key)
 				} else {
 					node.insert_leaf_sibling(shared_prefix_len, key.clone(), Default::default());
+					self.leafs_changed(1, 0);
 					// move down to sibling we just inserted
 					let side = key.get(shared_prefix_len);
 					self.down(side);
@@ -534,6 +631,7 @@ where
 					"goto musn't end at tree with non-empty tree"
 				);
 				*root = Some(Node::new_leaf(key, Default::default(), Default::default()));
+				self.leafs_changed(1, 0);
 				self.down_root();
 			},
 		}
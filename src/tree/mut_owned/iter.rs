@@ -14,15 +14,27 @@ where
 	inner: mut_gen::IterWalkMutPath<'r, 'w, TP, mut_gen::Owned, D>,
 }
 
-impl<'r, TP, D> Iterator for IterWalkMutOwnedPath<'r, '_, TP, D>
+impl<'r, 'w, TP, D> IterWalkMutOwnedPath<'r, 'w, TP, D>
+where
+	TP: TreeProperties + 'r,
+	D: From<WalkedDirection>,
+{
+	pub(in crate::tree) fn new(path: mut_gen::WalkMutPath<'r, 'w, TP, mut_gen::Owned, D>) -> Self {
+		Self {
+			inner: path.into_iter(),
+		}
+	}
+}
+
+impl<'r, 'w, TP, D> Iterator for IterWalkMutOwnedPath<'r, 'w, TP, D>
 where
 	TP: TreeProperties + 'r,
 	D: From<WalkedDirection>,
 {
 	type Item = (
-		&'r TP::Key,
-		&'r mut TP::Value,
-		Option<&'r mut TP::LeafValue>,
+		&'w TP::Key,
+		&'w mut TP::Value,
+		Option<&'w mut TP::LeafValue>,
 	);
 
 	fn next(&mut self) -> Option<Self::Item> {
@@ -133,6 +145,20 @@ where
 	TP: TreeProperties + 'r,
 {
 	inner: mut_gen::IterMutLeaf<'r, TP, mut_gen::Owned>,
+	// leafs not returned yet (only known when iterating a whole tree)
+	remaining: Option<usize>,
+}
+
+impl<'r, TP> IterMutOwnedLeaf<'r, TP>
+where
+	TP: TreeProperties + 'r,
+{
+	pub(in crate::tree) fn with_remaining(self, remaining: usize) -> Self {
+		Self {
+			remaining: Some(remaining),
+			..self
+		}
+	}
 }
 
 impl<'r, TP> From<mut_gen::IterMutLeaf<'r, TP, mut_gen::Owned>> for IterMutOwnedLeaf<'r, TP>
@@ -140,7 +166,10 @@ where
 	TP: TreeProperties + 'r,
 {
 	fn from(inner: mut_gen::IterMutLeaf<'r, TP, mut_gen::Owned>) -> Self {
-		Self { inner }
+		Self {
+			inner,
+			remaining: None,
+		}
 	}
 }
 
@@ -151,7 +180,18 @@ where
 	type Item = (&'r TP::Key, &'r mut TP::LeafValue);
 
 	fn next(&mut self) -> Option<Self::Item> {
-		self.inner.next()
+		let item = self.inner.next()?;
+		if let Some(remaining) = &mut self.remaining {
+			*remaining -= 1;
+		}
+		Some(item)
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		match self.remaining {
+			Some(remaining) => (remaining, Some(remaining)),
+			None => (0, None),
+		}
 	}
 }
 
@@ -19,6 +19,7 @@ use super::{
 	IterMutOwnedLeafFull,
 	IterMutOwnedPostOrder,
 	IterMutOwnedPreOrder,
+	IterWalkMutOwnedPath,
 };
 
 /// Walk owned mutable tree up and down
@@ -66,6 +67,11 @@ where
 	pub fn into_current_mut(self) -> NodeOrTree<Option<&'r mut Node<TP>>, &'r mut Node<TP>> {
 		self.inner.into_current_mut()
 	}
+
+	// update leaf count after changing the structure through `current_mut`
+	pub(crate) fn leafs_changed(&mut self, added: usize, removed: usize) {
+		self.inner.leafs_changed(added, removed)
+	}
 }
 
 impl<'r, TP> WalkMutOwned<'r, TP, WalkedDirection, ()>
@@ -101,6 +107,20 @@ where
 		self.inner.delete_current_with()
 	}
 
+	/// Delete either left or right side of the current node, replacing it with the other side
+	///
+	/// Does nothing if the current node is a leaf (or at tree).
+	pub fn delete_side(&mut self, delete_right: bool) {
+		self.inner.delete_side(delete_right)
+	}
+
+	/// Merge the child leafs of the current node if their values can be merged
+	///
+	/// Returns true if the current node is a leaf afterwards.
+	pub fn compress(&mut self) -> bool {
+		self.inner.compress()
+	}
+
 	/// Remove empty leaf nodes if possible
 	///
 	/// A node is considered "empty" if the passed function considers its value empty.
@@ -203,6 +223,13 @@ where
 		}
 	}
 
+	// continue a path walked before with the same key from the current position
+	pub(crate) fn resume_path(&mut self, key: TP::Key) -> WalkMutOwnedPath<'r, '_, TP, D> {
+		WalkMutOwnedPath {
+			inner: self.inner.resume_path(key),
+		}
+	}
+
 	/// Walk to node where we'd have to insert key at
 	///
 	/// Returns `None` if tree is empty.
@@ -292,7 +319,8 @@ where
 	}
 }
 
-/*
+// items can only borrow from the walk (`'w`), not the tree (`'r`): the walk
+// can be used to modify the nodes again once the iterator is gone.
 impl<'r, 'w, TP, D> IntoIterator for WalkMutOwnedPath<'r, 'w, TP, D>
 where
 	TP: TreeProperties + 'r,
@@ -300,13 +328,12 @@ where
 {
 	type IntoIter = IterWalkMutOwnedPath<'r, 'w, TP, D>;
 	type Item = (
-		&'r TP::Key,
-		&'r mut TP::Value,
-		Option<&'r mut TP::LeafValue>,
+		&'w TP::Key,
+		&'w mut TP::Value,
+		Option<&'w mut TP::LeafValue>,
 	);
 
 	fn into_iter(self) -> Self::IntoIter {
-		IterWalkMutOwnedPath::new(self)
+		IterWalkMutOwnedPath::new(self.inner)
 	}
 }
-*/
@@ -1,3 +1,5 @@
+#![allow(clippy::needless_return)]
+
 use alloc::vec::Vec;
 use bitstring::BitString;
 
@@ -264,6 +266,34 @@ impl<'r, TP: TreeProperties> Walk<'r, TP, WalkedDirection> {
 		return self.current().node();
 	}
 
+	/// Tree traversal: depth-first reverse in-order leaf nodes only
+	///
+	/// Like [`next_leaf`](Self::next_leaf), but starting with the rightmost leaf.
+	pub fn next_leaf_back(&mut self) -> Option<&'r Node<TP>> {
+		match self.current() {
+			NodeOrTree::Tree(_) => {
+				self.down_root();
+				while self.down_right() {}
+			},
+			NodeOrTree::Node(_) => {
+				loop {
+					match self.up()? {
+						WalkedDirection::Down => {
+							return None; // back up at tree
+						},
+						WalkedDirection::Right => {
+							self.down_left();
+							while self.down_right() {}
+							break;
+						},
+						WalkedDirection::Left => (), // continue further up
+					}
+				}
+			},
+		}
+		return self.current().node();
+	}
+
 	/// Tree traversal: depth-first post-order
 	pub fn next_post_order(&mut self) -> Option<&'r Node<TP>> {
 		match self.current() {
@@ -0,0 +1,816 @@
+mod common;
+
+use cidr::AnyIpCidr;
+use common::{
+	c,
+	Rng,
+};
+
+#[test]
+fn full_map_contains() {
+	let mut map = bitstring_trees::full_map::FullMap::<AnyIpCidr, u32>::new();
+	assert!(!map.contains_key(&c("0.0.0.0/0")));
+	assert!(!map.contains_prefix_of(&c("10.0.0.1")));
+	map.insert(c("10.0.0.0/8"), 1);
+	map.insert(c("10.1.0.0/16"), 2);
+	map.insert(c("10.2.0.0/16"), 3);
+
+	assert!(map.contains_key(&c("10.0.0.0/8")));
+	assert!(map.contains_key(&c("10.1.0.0/16")));
+	// inner node without value
+	assert!(!map.contains_key(&c("10.0.0.0/14")));
+	assert!(!map.contains_key(&c("10.1.2.0/24")));
+
+	assert!(map.contains_prefix_of(&c("10.1.2.0/24")));
+	assert!(map.contains_prefix_of(&c("10.3.0.1")));
+	assert!(map.contains_prefix_of(&c("10.0.0.0/8")));
+	assert!(!map.contains_prefix_of(&c("10.0.0.0/7")));
+	assert!(!map.contains_prefix_of(&c("11.0.0.1")));
+
+	map.remove(&c("10.0.0.0/8"));
+	assert!(map.contains_prefix_of(&c("10.1.2.0/24")));
+	assert!(!map.contains_prefix_of(&c("10.3.0.1")));
+}
+
+#[test]
+fn full_map_iter_prefix() {
+	let mut map = bitstring_trees::full_map::FullMap::<AnyIpCidr, u32>::new();
+	let under = |map: &bitstring_trees::full_map::FullMap<AnyIpCidr, u32>, prefix: &str| {
+		map.iter_prefix(&c(prefix))
+			.map(|(k, v)| (k.to_string(), *v))
+			.collect::<Vec<_>>()
+	};
+	assert_eq!(under(&map, "10.0.0.0/8"), vec![]);
+
+	map.insert(c("10.0.0.0/8"), 1);
+	map.insert(c("10.1.0.0/16"), 2);
+	map.insert(c("10.1.2.0/24"), 3);
+	map.insert(c("10.1.3.0/24"), 4);
+	map.insert(c("192.168.0.0/16"), 5);
+
+	// exact node
+	assert_eq!(
+		under(&map, "10.0.0.0/8"),
+		vec![
+			("10.1.2.0/24".to_string(), 3),
+			("10.1.3.0/24".to_string(), 4),
+			("10.1.0.0/16".to_string(), 2),
+			("10.0.0.0/8".to_string(), 1),
+		]
+	);
+	// node with longer key below prefix
+	assert_eq!(
+		under(&map, "10.0.0.0/15"),
+		vec![
+			("10.1.2.0/24".to_string(), 3),
+			("10.1.3.0/24".to_string(), 4),
+			("10.1.0.0/16".to_string(), 2),
+		]
+	);
+	// inner node without value
+	assert_eq!(
+		under(&map, "10.1.2.0/23"),
+		vec![
+			("10.1.2.0/24".to_string(), 3),
+			("10.1.3.0/24".to_string(), 4)
+		]
+	);
+	// node with longer key diverging from prefix
+	assert_eq!(under(&map, "10.1.4.0/23"), vec![]);
+	// below leaf: covering entry is shorter than prefix
+	assert_eq!(under(&map, "192.168.1.0/24"), vec![]);
+	assert_eq!(under(&map, "10.1.2.128/25"), vec![]);
+
+	let all: Vec<_> = map.iter().map(|(k, v)| (k.to_string(), *v)).collect();
+	assert_eq!(under(&map, "0.0.0.0/0"), all);
+}
+
+#[test]
+fn full_map_clear() {
+	let mut map = bitstring_trees::full_map::FullMap::<AnyIpCidr, u32>::new();
+	// chain of nested prefixes
+	let host: AnyIpCidr = "2001:db8::1".parse().unwrap();
+	for len in 1..=129 {
+		let mut key = host;
+		bitstring::BitString::clip(&mut key, len);
+		map.insert(key, len as u32);
+	}
+	assert_eq!(map.iter().count(), 129);
+	map.clear();
+	assert_eq!(map.iter().next(), None);
+	map.clear();
+	map.insert("10.0.0.0/8".parse().unwrap(), 1);
+	assert_eq!(map.iter().count(), 1);
+}
+
+#[test]
+fn full_map_into_iter() {
+	let mut map = bitstring_trees::full_map::FullMap::<AnyIpCidr, String>::new();
+	map.insert(c("10.0.0.0/8"), "a".to_string());
+	map.insert(c("10.0.0.0/24"), "b".to_string());
+	map.insert(c("10.0.1.0/24"), "c".to_string());
+	map.insert(c("192.168.0.0/16"), "d".to_string());
+	let expected: Vec<(AnyIpCidr, String)> = map.iter().map(|(k, v)| (*k, v.clone())).collect();
+	assert_eq!(expected.len(), 4);
+	assert_eq!(map.clone().into_iter().collect::<Vec<_>>(), expected);
+	let mut count = 0;
+	for (k, v) in &map {
+		assert_eq!(map.get(k), Some(v));
+		count += 1;
+	}
+	assert_eq!(count, 4);
+	// drop early
+	let mut iter = map.into_iter();
+	assert_eq!(iter.next(), Some(expected[0].clone()));
+	drop(iter);
+}
+
+#[test]
+fn full_map_path_mut() {
+	use bitstring_trees::full_map::Entry;
+
+	let mut map = bitstring_trees::full_map::FullMap::<AnyIpCidr, u32>::new();
+	map.insert(c("0.0.0.0/0"), 1);
+	map.insert(c("10.0.0.0/8"), 2);
+	map.insert(c("10.1.0.0/16"), 3);
+	map.insert(c("10.2.0.0/16"), 4);
+	map.insert(c("192.168.0.0/16"), 5);
+
+	for (_, value) in &mut map.path_mut(c("10.1.2.3")) {
+		*value *= 10;
+	}
+	assert_eq!(
+		map.path(c("10.1.2.3"))
+			.map(|(k, v)| (k.to_string(), *v))
+			.collect::<Vec<_>>(),
+		vec![
+			("0.0.0.0/0".to_string(), 10),
+			("10.0.0.0/8".to_string(), 20),
+			("10.1.0.0/16".to_string(), 30),
+		]
+	);
+
+	// step by step, then insert more specific entry where the walk ended
+	let mut path = map.path_mut(c("10.1.2.0/24"));
+	assert_eq!(path.next().map(|(k, _)| *k), Some(c("0.0.0.0/0")));
+	let (key, value) = path.next().unwrap();
+	assert_eq!(*key, c("10.0.0.0/8"));
+	*value += 1;
+	assert_eq!(
+		path.next().map(|(k, v)| (*k, *v)),
+		Some((c("10.1.0.0/16"), 30))
+	);
+	assert_eq!(path.next(), None);
+	assert_eq!(path.next(), None);
+	match path.into_entry() {
+		Entry::Vacant(entry) => *entry.insert(6) += 1,
+		Entry::Occupied(_) => panic!("expected vacant entry"),
+	}
+	assert_eq!(map.get(&c("10.1.2.0/24")), Some(&7));
+	assert_eq!(map.get(&c("10.0.0.0/8")), Some(&21));
+
+	// continue iterating after stepping, then stepping after iterating
+	let mut path = map.path_mut(c("10.1.2.0/24"));
+	path.next();
+	let rest: Vec<AnyIpCidr> = (&mut path).into_iter().take(2).map(|(k, _)| *k).collect();
+	assert_eq!(rest, vec![c("10.0.0.0/8"), c("10.1.0.0/16")]);
+	assert_eq!(path.next().map(|(k, _)| *k), Some(c("10.1.2.0/24")));
+	assert_eq!(path.next(), None);
+	assert!(matches!(path.into_entry(), Entry::Occupied(_)));
+
+	// existing entry
+	let mut path = map.path_mut(c("10.2.0.0/16"));
+	while path.next().is_some() {}
+	match path.into_entry() {
+		Entry::Occupied(entry) => assert_eq!(*entry.get(), 4),
+		Entry::Vacant(_) => panic!("expected occupied entry"),
+	}
+	// diverging below an inner node
+	let mut path = map.path_mut(c("10.3.0.0/16"));
+	assert_eq!((&mut path).into_iter().count(), 2);
+	assert!(matches!(path.into_entry(), Entry::Vacant(_)));
+	let mut empty = bitstring_trees::full_map::FullMap::<AnyIpCidr, u32>::new();
+	assert_eq!(empty.path_mut(c("10.0.0.0/8")).next(), None);
+	assert_eq!(
+		(&mut empty.path_mut(c("10.0.0.0/8"))).into_iter().next(),
+		None
+	);
+	*empty.path_mut(c("10.0.0.0/8")).into_entry().or_default() = 1;
+	assert_eq!(empty.get(&c("10.0.0.0/8")), Some(&1));
+}
+
+#[test]
+fn full_map_most_specific_with() {
+	let mut map = bitstring_trees::full_map::FullMap::<AnyIpCidr, u32>::new();
+	map.insert(c("0.0.0.0/0"), 1);
+	map.insert(c("10.0.0.0/8"), 2);
+	map.insert(c("10.1.0.0/16"), 3);
+	map.insert(c("10.1.2.0/24"), 4);
+	map.insert(c("10.1.3.0/24"), 5);
+
+	let host = c("10.1.2.3");
+	assert_eq!(
+		map.most_specific_with(&host, |_, _| true),
+		map.most_specific(&host)
+	);
+	// longest accepted match, not the first one
+	assert_eq!(
+		map.most_specific_with(&host, |_, v| v & 1 == 1),
+		Some((&c("10.1.0.0/16"), &3))
+	);
+	assert_eq!(
+		map.most_specific_with(&host, |k, _| k.network_length() < Some(16)),
+		Some((&c("10.0.0.0/8"), &2))
+	);
+	assert_eq!(map.most_specific_with(&host, |_, v| *v > 10), None);
+	// inner node 10.1.2.0/23 without value is never passed to the predicate
+	assert_eq!(
+		map.most_specific_with(&c("10.1.2.0/23"), |_, _| true),
+		Some((&c("10.1.0.0/16"), &3))
+	);
+
+	let (key, value) = map.most_specific_mut_with(&host, |_, v| *v != 4).unwrap();
+	assert_eq!(*key, c("10.1.0.0/16"));
+	*value = 30;
+	assert_eq!(map.get(&c("10.1.0.0/16")), Some(&30));
+	assert_eq!(map.most_specific_mut_with(&host, |_, _| false), None);
+}
+
+#[test]
+fn full_map_most_specific() {
+	let mut map = bitstring_trees::full_map::FullMap::<AnyIpCidr, u32>::new();
+	assert_eq!(map.most_specific(&c("10.0.0.1")), None);
+
+	map.insert(c("10.0.0.0/8"), 1);
+	map.insert(c("10.1.2.0/24"), 2);
+	map.insert(c("10.1.3.0/24"), 3);
+	// no entry matches
+	assert_eq!(map.most_specific(&c("11.0.0.1")), None);
+	assert_eq!(map.most_specific(&c("0.0.0.0/0")), None);
+	// exact, below and inner node without value
+	assert_eq!(
+		map.most_specific(&c("10.1.2.0/24")),
+		Some((&c("10.1.2.0/24"), &2))
+	);
+	assert_eq!(
+		map.most_specific(&c("10.1.3.1")),
+		Some((&c("10.1.3.0/24"), &3))
+	);
+	assert_eq!(
+		map.most_specific(&c("10.1.2.0/23")),
+		Some((&c("10.0.0.0/8"), &1))
+	);
+	assert_eq!(
+		map.most_specific(&c("10.1.4.1")),
+		Some((&c("10.0.0.0/8"), &1))
+	);
+
+	// only the root /0 matches
+	map.insert(c("0.0.0.0/0"), 0);
+	assert_eq!(
+		map.most_specific(&c("11.0.0.1")),
+		Some((&c("0.0.0.0/0"), &0))
+	);
+	assert_eq!(
+		map.most_specific(&c("0.0.0.0/0")),
+		Some((&c("0.0.0.0/0"), &0))
+	);
+
+	let mut rng = Rng(0x5bec);
+	for _ in 0..20 {
+		let mut map = bitstring_trees::full_map::FullMap::<AnyIpCidr, u32>::new();
+		for i in 0..32 {
+			map.insert(rng.cidr(), i);
+		}
+		for _ in 0..64 {
+			let key = if rng.next() & 1 == 0 {
+				rng.host()
+			} else {
+				rng.cidr()
+			};
+			assert_eq!(map.most_specific(&key), map.path(key).last());
+		}
+	}
+}
+
+#[test]
+fn full_map_entry_ref() {
+	use bitstring_trees::full_map::EntryRef;
+
+	let mut map = bitstring_trees::full_map::FullMap::<AnyIpCidr, u32>::new();
+	let key = c("10.0.0.0/24");
+	*map.entry_ref(&key).or_insert(1) += 1;
+	*map.entry_ref(&key).or_insert(10) += 1;
+	assert_eq!(map.get(&key), Some(&3));
+	match map.entry_ref(&c("10.0.1.0/24")) {
+		EntryRef::Vacant(entry) => {
+			assert_eq!(entry.key(), &c("10.0.1.0/24"));
+			assert_eq!(*entry.insert(4), 4);
+		},
+		EntryRef::Occupied(_) => panic!("expected vacant entry"),
+	}
+	match map.entry_ref(&key) {
+		EntryRef::Occupied(entry) => assert_eq!(entry.remove(), 3),
+		EntryRef::Vacant(_) => panic!("expected occupied entry"),
+	}
+	map.entry_ref(&c("10.0.0.0/8"))
+		.and_modify(|v| *v += 1)
+		.or_insert_with_key(|k| k.network_length().unwrap() as u32);
+	map.entry_ref(&c("10.0.0.0/8"))
+		.and_modify(|v| *v += 1)
+		.or_default();
+	assert_eq!(
+		map.iter()
+			.map(|(k, v)| (k.to_string(), *v))
+			.collect::<Vec<_>>(),
+		vec![
+			("10.0.1.0/24".to_string(), 4),
+			("10.0.0.0/8".to_string(), 9)
+		]
+	);
+}
+
+#[test]
+fn full_map_merge() {
+	use bitstring_trees::full_map::FullMap;
+
+	let mut a = FullMap::<AnyIpCidr, u32>::new();
+	a.insert(c("10.0.0.0/8"), 1);
+	a.insert(c("10.1.0.0/16"), 2);
+	let mut b = FullMap::<AnyIpCidr, u32>::new();
+	b.insert(c("10.0.0.0/8"), 10);
+	b.insert(c("10.1.2.0/24"), 20);
+	b.insert(c("0.0.0.0/0"), 30);
+	let mut calls = Vec::new();
+	a.merge(b, |k, old, new| {
+		calls.push(k.to_string());
+		*old += new;
+	});
+	assert_eq!(calls, vec!["10.0.0.0/8"]);
+	// more/less specific entries stay independent
+	assert_eq!(
+		a.iter()
+			.map(|(k, v)| (k.to_string(), *v))
+			.collect::<Vec<_>>(),
+		vec![
+			("10.1.2.0/24".to_string(), 20),
+			("10.1.0.0/16".to_string(), 2),
+			("10.0.0.0/8".to_string(), 11),
+			("0.0.0.0/0".to_string(), 30),
+		]
+	);
+	a.merge(FullMap::new(), |_, _, _| unreachable!());
+	assert_eq!(a.iter().count(), 4);
+
+	let mut rng = Rng(0x3e6e);
+	for _ in 0..50 {
+		let mut a = FullMap::<AnyIpCidr, u32>::new();
+		let mut b = FullMap::<AnyIpCidr, u32>::new();
+		let mut expected = FullMap::<AnyIpCidr, u32>::new();
+		for i in 0..(rng.next() % 24) as u32 {
+			let key = rng.cidr();
+			a.insert(key, i);
+			expected.insert(key, i);
+		}
+		for i in 0..(rng.next() % 24) as u32 {
+			let key = if rng.next() & 3 == 0 {
+				// reuse key of a to get conflicts
+				match a.keys().nth((rng.next() % 8) as usize) {
+					Some(key) => *key,
+					None => rng.cidr(),
+				}
+			} else {
+				rng.cidr()
+			};
+			let value = 100 + i;
+			b.insert(key, value);
+		}
+		for (key, value) in b.iter() {
+			*expected.entry(*key).or_insert(0) += *value;
+		}
+		a.merge(b, |_, old, new| *old += new);
+		assert_eq!(
+			a.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>(),
+			expected.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>()
+		);
+		let host = rng.host();
+		assert_eq!(a.most_specific(&host), expected.most_specific(&host));
+	}
+}
+
+#[test]
+fn full_map_children() {
+	let mut map = bitstring_trees::full_map::FullMap::<AnyIpCidr, u32>::new();
+	let keys = |iter: bitstring_trees::full_map::IterChildren<'_, AnyIpCidr, u32>| {
+		iter.map(|(k, _)| k.to_string()).collect::<Vec<_>>()
+	};
+	assert_eq!(keys(map.roots()), Vec::<String>::new());
+	map.insert(c("10.0.0.0/8"), 1);
+	map.insert(c("10.1.0.0/16"), 2);
+	map.insert(c("10.1.2.0/24"), 3);
+	map.insert(c("10.2.0.0/16"), 4);
+	map.insert(c("10.3.4.0/24"), 5);
+	map.insert(c("192.168.0.0/24"), 6);
+	map.insert(c("192.168.1.0/24"), 7);
+
+	assert_eq!(
+		keys(map.roots()),
+		vec!["10.0.0.0/8", "192.168.0.0/24", "192.168.1.0/24"]
+	);
+	assert_eq!(
+		keys(map.children(&c("10.0.0.0/8"))),
+		vec!["10.1.0.0/16", "10.2.0.0/16", "10.3.4.0/24"]
+	);
+	assert_eq!(keys(map.children(&c("10.1.0.0/16"))), vec!["10.1.2.0/24"]);
+	assert_eq!(keys(map.children(&c("10.1.2.0/24"))), Vec::<String>::new());
+	// key not stored: inner node, above a node, below a leaf, diverging
+	assert_eq!(
+		keys(map.children(&c("192.168.0.0/23"))),
+		vec!["192.168.0.0/24", "192.168.1.0/24"]
+	);
+	assert_eq!(
+		keys(map.children(&c("192.0.0.0/8"))),
+		vec!["192.168.0.0/24", "192.168.1.0/24"]
+	);
+	assert_eq!(keys(map.children(&c("10.1.2.0/25"))), Vec::<String>::new());
+	assert_eq!(keys(map.children(&c("10.4.0.0/16"))), Vec::<String>::new());
+}
+
+#[test]
+fn full_map_remove_entry() {
+	use bitstring_trees::full_map::Entry;
+
+	let mut map = bitstring_trees::full_map::FullMap::<AnyIpCidr, u32>::new();
+	map.insert(c("10.0.0.0/8"), 1);
+	map.insert(c("10.1.0.0/16"), 2);
+	map.insert(c("10.2.0.0/16"), 3);
+	assert_eq!(map.remove_entry(&c("10.0.0.0/16")), None);
+	assert_eq!(
+		map.remove_entry(&c("10.1.0.0/16")),
+		Some((c("10.1.0.0/16"), 2))
+	);
+	assert_eq!(map.remove_entry(&c("10.1.0.0/16")), None);
+	match map.entry(c("10.0.0.0/8")) {
+		Entry::Occupied(entry) => assert_eq!(entry.remove_entry(), (c("10.0.0.0/8"), 1)),
+		Entry::Vacant(_) => panic!("expected occupied entry"),
+	}
+	assert_eq!(
+		map.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>(),
+		vec![(c("10.2.0.0/16"), 3)]
+	);
+	assert_eq!(
+		map.remove_entry(&c("10.2.0.0/16")),
+		Some((c("10.2.0.0/16"), 3))
+	);
+	assert_eq!(map.iter().next(), None);
+}
+
+#[test]
+fn full_map_aggregate() {
+	let mut map = bitstring_trees::full_map::FullMap::<AnyIpCidr, u32>::new();
+	map.insert(c("10.0.0.0/8"), 1);
+	map.insert(c("10.1.0.0/16"), 1);
+	map.insert(c("10.1.2.0/24"), 2);
+	map.insert(c("10.1.2.128/25"), 2);
+	map.insert(c("10.1.2.192/26"), 1);
+	map.insert(c("10.1.3.0/24"), 1);
+	map.insert(c("192.168.0.0/16"), 1);
+	assert_eq!(map.count_redundant(), 3);
+	map.aggregate();
+	assert_eq!(map.count_redundant(), 0);
+	assert_eq!(
+		map.iter()
+			.map(|(k, v)| (k.to_string(), *v))
+			.collect::<Vec<_>>(),
+		vec![
+			("10.1.2.0/24".to_string(), 2),
+			("10.1.2.192/26".to_string(), 1),
+			("10.0.0.0/8".to_string(), 1),
+			("192.168.0.0/16".to_string(), 1),
+		]
+	);
+
+	let mut rng = Rng(0xa66);
+	for _ in 0..50 {
+		let mut map = bitstring_trees::full_map::FullMap::<AnyIpCidr, u32>::new();
+		for _ in 0..32 {
+			map.insert(rng.cidr(), (rng.next() % 3) as u32);
+		}
+		let before = map.clone();
+		let count = map.iter().count();
+		let redundant = map.count_redundant();
+		map.aggregate();
+		assert_eq!(map.iter().count(), count - redundant);
+		assert_eq!(map.count_redundant(), 0);
+		for _ in 0..64 {
+			let key = if rng.next() & 1 == 0 {
+				rng.host()
+			} else {
+				rng.cidr()
+			};
+			assert_eq!(
+				map.most_specific(&key).map(|(_, v)| v),
+				before.most_specific(&key).map(|(_, v)| v)
+			);
+		}
+	}
+}
+
+#[test]
+fn full_map_get_key_value() {
+	let mut map = bitstring_trees::full_map::FullMap::<AnyIpCidr, u32>::new();
+	map.insert(c("10.0.0.0/24"), 1);
+	map.insert(c("10.0.1.0/24"), 2);
+	assert_eq!(
+		map.get_key_value(&c("10.0.1.0/24")),
+		Some((&c("10.0.1.0/24"), &2))
+	);
+	// inner node without value, covered and missing keys
+	assert_eq!(map.get_key_value(&c("10.0.0.0/23")), None);
+	assert_eq!(map.get_key_value(&c("10.0.0.1")), None);
+	assert_eq!(map.get_key_value(&c("10.0.2.0/24")), None);
+
+	let (key, value) = map.get_key_value_mut(&c("10.0.0.0/24")).unwrap();
+	assert_eq!(*key, c("10.0.0.0/24"));
+	*value = 3;
+	assert_eq!(map.get(&c("10.0.0.0/24")), Some(&3));
+	assert_eq!(map.get_key_value_mut(&c("10.0.0.0/23")), None);
+}
+
+#[test]
+fn full_map_ancestors() {
+	let mut map = bitstring_trees::full_map::FullMap::<AnyIpCidr, u32>::new();
+	map.insert(c("0.0.0.0/0"), 1);
+	map.insert(c("10.0.0.0/8"), 2);
+	map.insert(c("10.1.0.0/16"), 3);
+	map.insert(c("10.1.2.0/24"), 4);
+	map.insert(c("10.2.0.0/16"), 5);
+
+	let key = c("10.1.2.0/24");
+	let forward: Vec<_> = map.ancestors(&key).collect();
+	assert_eq!(forward, map.path(key).collect::<Vec<_>>());
+	assert_eq!(forward.len(), 4);
+	assert_eq!(map.ancestors(&key).len(), 4);
+	let mut backward: Vec<_> = map.ancestors(&key).rev().collect();
+	backward.reverse();
+	assert_eq!(forward, backward);
+	assert_eq!(
+		map.ancestors(&c("10.1.0.0/17")).next_back(),
+		Some((&c("10.1.0.0/16"), &3))
+	);
+	assert_eq!(map.ancestors(&c("11.0.0.1")).count(), 1);
+
+	for (_, value) in map.ancestors_mut(&c("10.1.2.3")).rev().take(2) {
+		*value += 10;
+	}
+	assert_eq!(
+		map.ancestors(&key).map(|(_, v)| *v).collect::<Vec<_>>(),
+		vec![1, 2, 13, 14]
+	);
+	assert_eq!(map.ancestors_mut(&c("::/0")).count(), 0);
+}
+
+#[test]
+fn full_map_first_last() {
+	let mut map = bitstring_trees::full_map::FullMap::<AnyIpCidr, u32>::new();
+	assert_eq!(map.first_key_value(), None);
+	assert_eq!(map.last_key_value(), None);
+	assert_eq!(map.pop_first(), None);
+	map.insert(c("10.1.0.0/16"), 2);
+	map.insert(c("10.0.0.0/8"), 1);
+	map.insert(c("10.0.0.0/24"), 3);
+	map.insert(c("10.128.0.0/9"), 4);
+	map.insert(c("10.128.1.0/24"), 5);
+	assert_eq!(map.first_key_value(), Some((&c("10.0.0.0/8"), &1)));
+	assert_eq!(map.last_key_value(), Some((&c("10.128.1.0/24"), &5)));
+
+	// drain in prefix order
+	let mut drained = Vec::new();
+	while let Some((key, _)) = map.pop_first() {
+		drained.push(key.to_string());
+	}
+	assert_eq!(
+		drained,
+		vec![
+			"10.0.0.0/8",
+			"10.0.0.0/24",
+			"10.1.0.0/16",
+			"10.128.0.0/9",
+			"10.128.1.0/24"
+		]
+	);
+	assert_eq!(map.iter().next(), None);
+
+	let mut rng = Rng(0xf1a5);
+	for _ in 0..20 {
+		let mut map = bitstring_trees::full_map::FullMap::<AnyIpCidr, u32>::new();
+		for i in 0..24 {
+			map.insert(rng.cidr(), i);
+		}
+		// prefix order: sort by first address, then shorter prefix first
+		let mut expected: Vec<AnyIpCidr> = map.keys().copied().collect();
+		expected.sort_by_key(|k| (k.first_address(), k.network_length()));
+		let (mut front, mut back) = (Vec::new(), Vec::new());
+		loop {
+			let len = map.iter().count();
+			let popped = if rng.next() & 1 == 0 {
+				map.pop_first().map(|(key, _)| front.push(key))
+			} else {
+				map.pop_last().map(|(key, _)| back.push(key))
+			};
+			if popped.is_none() {
+				break;
+			}
+			assert_eq!(map.iter().count(), len - 1);
+		}
+		assert_eq!(map.iter().next(), None);
+		back.reverse();
+		front.extend(back);
+		assert_eq!(front, expected);
+	}
+}
+
+#[test]
+fn full_map_get_or_insert_with() {
+	let mut map = bitstring_trees::full_map::FullMap::<AnyIpCidr, u32>::new();
+	*map.get_or_insert_with(c("10.0.0.0/24"), || 1) += 1;
+	*map.get_or_insert_with(c("10.0.0.0/24"), || panic!("already present")) += 1;
+	// sibling and placeholder nodes must not count as present
+	assert_eq!(*map.get_or_insert_with(c("10.0.1.0/24"), || 5), 5);
+	assert_eq!(*map.get_or_insert_with(c("10.0.0.0/16"), || 7), 7);
+	assert_eq!(*map.get_or_insert_with(c("10.0.0.0/25"), || 9), 9);
+	assert_eq!(*map.get_or_insert_with(c("10.0.0.128/25"), || 11), 11);
+	assert_eq!(
+		map.iter()
+			.map(|(k, v)| (k.to_string(), *v))
+			.collect::<Vec<_>>(),
+		vec![
+			("10.0.0.0/25".to_string(), 9),
+			("10.0.0.0/24".to_string(), 3),
+			("10.0.0.128/25".to_string(), 11),
+			("10.0.1.0/24".to_string(), 5),
+			("10.0.0.0/16".to_string(), 7),
+		]
+	);
+}
+
+#[test]
+fn full_map_view() {
+	let mut map = bitstring_trees::full_map::FullMap::<AnyIpCidr, u32>::new();
+	map.insert(c("10.0.0.0/8"), 1);
+	map.insert(c("10.1.0.0/16"), 2);
+	map.insert(c("10.1.2.0/24"), 3);
+	map.insert(c("10.2.0.0/16"), 4);
+
+	let view = map.view(c("10.1.0.0/16"));
+	assert_eq!(view.prefix(), &c("10.1.0.0/16"));
+	assert_eq!(view.get(&c("10.1.2.0/24")), Some(&3));
+	assert_eq!(view.get(&c("10.1.3.0/24")), None);
+	assert_eq!(view.get(&c("10.2.0.0/16")), None);
+	assert_eq!(
+		view.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>(),
+		vec![(c("10.1.2.0/24"), 3), (c("10.1.0.0/16"), 2)]
+	);
+	assert_eq!(
+		view.most_specific(&c("10.1.2.128/25")),
+		Some((&c("10.1.2.0/24"), &3))
+	);
+	assert_eq!(view.most_specific(&c("10.2.0.0/24")), None);
+
+	// view without entry for the prefix itself
+	let view = map.view(c("10.1.128.0/17"));
+	assert_eq!(view.iter().count(), 0);
+	assert_eq!(view.most_specific(&c("10.1.128.0/24")), None);
+	let view = view.with_ancestors(true);
+	assert_eq!(
+		view.most_specific(&c("10.1.128.0/24")),
+		Some((&c("10.1.0.0/16"), &2))
+	);
+	// keys outside the view don't use ancestors
+	assert_eq!(view.most_specific(&c("10.1.0.0/24")), None);
+
+	let view = map.view(c("10.0.0.0/8")).with_ancestors(true);
+	assert_eq!(view.iter().count(), 4);
+	assert_eq!(
+		view.most_specific(&c("10.3.0.0/16")),
+		Some((&c("10.0.0.0/8"), &1))
+	);
+	assert_eq!(
+		map.view(c("11.0.0.0/8"))
+			.with_ancestors(true)
+			.most_specific(&c("11.0.0.0/8")),
+		None
+	);
+}
+
+#[test]
+fn full_map_remove_tree() {
+	let mut map = bitstring_trees::full_map::FullMap::<AnyIpCidr, u32>::new();
+	map.insert(c("10.0.0.0/8"), 1);
+	map.insert(c("10.1.0.0/16"), 2);
+	map.insert(c("10.1.2.0/24"), 3);
+	map.insert(c("10.2.0.0/16"), 4);
+	// no nodes without value left that a fresh map wouldn't have
+	let check_pruned = |map: &bitstring_trees::full_map::FullMap<AnyIpCidr, u32>| {
+		let mut fresh = bitstring_trees::full_map::FullMap::new();
+		for (key, value) in map.iter() {
+			fresh.insert(*key, *value);
+		}
+		assert_eq!(map.stats(), fresh.stats());
+	};
+	// shorter prefixes are kept
+	map.remove_tree(c("10.1.2.0/25"));
+	assert_eq!(map.iter().count(), 4);
+	map.remove_tree(c("10.1.0.0/16"));
+	assert_eq!(
+		map.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>(),
+		vec![(c("10.2.0.0/16"), 4), (c("10.0.0.0/8"), 1)]
+	);
+	check_pruned(&map);
+	map.remove_tree(c("10.2.0.0/15"));
+	assert_eq!(
+		map.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>(),
+		vec![(c("10.0.0.0/8"), 1)]
+	);
+	check_pruned(&map);
+	map.remove_tree(c("0.0.0.0/0"));
+	assert_eq!(map.iter().next(), None);
+}
+
+#[test]
+fn full_map_display() {
+	let mut full_map = bitstring_trees::full_map::FullMap::<AnyIpCidr, u32>::new();
+	full_map.insert("10.0.0.0/8".parse().unwrap(), 1);
+	full_map.insert("10.1.0.0/16".parse().unwrap(), 2);
+	// same order as `iter`: 10.1.0.0/16 is in the left half of 10.0.0.0/8
+	assert_eq!(full_map.to_string(), "{10.1.0.0/16 => 2, 10.0.0.0/8 => 1}");
+	assert_eq!(
+		format!("{:#}", full_map),
+		"10.1.0.0/16 => 2\n10.0.0.0/8 => 1\n"
+	);
+	full_map.clear();
+	assert_eq!(full_map.to_string(), "{}");
+}
+
+#[test]
+fn full_map_longest_common_prefix() {
+	let mut full_map = bitstring_trees::full_map::FullMap::<AnyIpCidr, u32>::new();
+	assert_eq!(full_map.longest_common_prefix(), None);
+	full_map.insert(c("10.0.0.0/8"), 1);
+	full_map.insert(c("10.1.0.0/16"), 2);
+	assert_eq!(full_map.longest_common_prefix(), Some(c("10.0.0.0/8")));
+	// removing the covering entry leaves an empty placeholder sibling behind
+	full_map.remove(&c("10.0.0.0/8"));
+	assert_eq!(full_map.longest_common_prefix(), Some(c("10.1.0.0/16")));
+	full_map.insert(c("10.1.2.0/24"), 3);
+	full_map.insert(c("10.1.3.0/24"), 4);
+	full_map.remove(&c("10.1.0.0/16"));
+	assert_eq!(full_map.longest_common_prefix(), Some(c("10.1.2.0/23")));
+}
+
+#[test]
+fn full_map_keys_values() {
+	let mut map = bitstring_trees::full_map::FullMap::<AnyIpCidr, u32>::new();
+	map.insert(c("10.0.0.0/8"), 1);
+	map.insert(c("10.0.0.0/24"), 2);
+	for v in map.values_mut() {
+		*v += 1;
+	}
+	assert_eq!(
+		map.keys().map(|k| k.to_string()).collect::<Vec<_>>(),
+		vec!["10.0.0.0/24", "10.0.0.0/8"]
+	);
+	assert_eq!(map.values().copied().collect::<Vec<_>>(), vec![3, 2]);
+
+	// inner nodes without value are skipped
+	map.insert(c("10.0.1.0/24"), 4);
+	for v in map.values_mut() {
+		*v *= 2;
+	}
+	assert_eq!(
+		map.keys().map(|k| k.to_string()).collect::<Vec<_>>(),
+		vec!["10.0.0.0/24", "10.0.1.0/24", "10.0.0.0/8"]
+	);
+	assert_eq!(map.values().copied().collect::<Vec<_>>(), vec![6, 8, 4]);
+}
+
+#[test]
+fn full_map_entry_sibling() {
+	use bitstring_trees::full_map::Entry;
+
+	// sibling with same length as an existing entry is a different key
+	let mut map = bitstring_trees::full_map::FullMap::<AnyIpCidr, u32>::new();
+	map.insert(c("10.0.0.0/24"), 1);
+	assert!(matches!(map.entry(c("10.0.1.0/24")), Entry::Vacant(_)));
+	assert_eq!(map.remove(&c("10.0.1.0/24")), None);
+	assert_eq!(map.get(&c("10.0.0.0/24")), Some(&1));
+	map.entry(c("10.0.1.0/24")).or_insert(2);
+	assert_eq!(map.get(&c("10.0.0.0/24")), Some(&1));
+	assert_eq!(map.get(&c("10.0.1.0/24")), Some(&2));
+
+	map.insert(c("10.0.0.0/8"), 3);
+	assert_eq!(map.remove(&c("10.0.2.0/24")), None);
+	assert_eq!(map.remove(&c("10.0.1.0/24")), Some(2));
+	assert_eq!(map.remove(&c("10.0.1.0/24")), None);
+	assert_eq!(map.get(&c("10.0.0.0/24")), Some(&1));
+}
@@ -0,0 +1,1066 @@
+mod common;
+
+use cidr::{
+	AnyIpCidr,
+	Ipv4Cidr,
+};
+use common::{
+	c,
+	Rng,
+};
+
+#[test]
+fn map_get_key_value() {
+	let mut map = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	map.insert("10.0.0.0/25".parse().unwrap(), 1);
+	map.insert("10.0.0.128/25".parse().unwrap(), 1);
+	map.insert("192.168.0.0/24".parse().unwrap(), 2);
+	map.insert("192.168.1.0/24".parse().unwrap(), 3);
+	let get = |key: &str| {
+		map.get_key_value(&key.parse().unwrap())
+			.map(|(k, v)| (k.to_string(), *v))
+	};
+	// merged into 10.0.0.0/24
+	assert_eq!(get("10.0.0.0/24"), Some(("10.0.0.0/24".to_string(), 1)));
+	assert_eq!(get("10.0.0.0/25"), None);
+	assert_eq!(
+		get("192.168.1.0/24"),
+		Some(("192.168.1.0/24".to_string(), 3))
+	);
+	// inner node
+	assert_eq!(get("192.168.0.0/23"), None);
+	assert_eq!(get("172.16.0.0/12"), None);
+	assert_eq!(map.get(&"10.0.0.1".parse().unwrap()), Some(&1));
+}
+
+#[test]
+fn map_lookup() {
+	let mut map = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	map.insert("10.0.0.0/17".parse().unwrap(), 1);
+	map.insert("10.0.128.0/17".parse().unwrap(), 1);
+	map.insert("10.1.0.0/16".parse().unwrap(), 2);
+	map.insert("10.1.2.0/24".parse().unwrap(), 3);
+	let lookup = |key: &str| {
+		map.lookup(&key.parse().unwrap())
+			.map(|(k, v)| (k.to_string(), *v))
+	};
+	assert_eq!(lookup("10.0.200.1"), Some(("10.0.0.0/16".to_string(), 1)));
+	// 10.1.0.0/16 got split by 10.1.2.0/24
+	assert_eq!(lookup("10.1.3.4"), Some(("10.1.3.0/24".to_string(), 2)));
+	assert_eq!(lookup("10.1.200.1"), Some(("10.1.128.0/17".to_string(), 2)));
+	assert_eq!(lookup("10.1.2.4"), Some(("10.1.2.0/24".to_string(), 3)));
+	assert_eq!(lookup("10.2.0.1"), None);
+	// not fully contained in a single prefix
+	assert_eq!(lookup("10.0.0.0/15"), None);
+	assert_eq!(lookup("10.0.0.0/8"), None);
+}
+
+#[test]
+fn map_entry() {
+	use bitstring_trees::map::Entry;
+
+	let mut map = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	map.insert(c("10.0.0.0/16"), 1);
+
+	match map.entry(c("10.0.1.0/24")) {
+		Entry::Covered(entry) => {
+			assert_eq!(entry.covering_key(), &c("10.0.0.0/16"));
+			assert_eq!(*entry.get(), 1);
+		},
+		_ => panic!("expected covered entry"),
+	}
+	// modify covering aggregate
+	map.entry(c("10.0.1.0/24"))
+		.and_modify(|v| *v += 1)
+		.or_insert(10);
+	assert_eq!(map.get(&c("10.0.200.1")), Some(&2));
+
+	assert_eq!(*map.entry(c("10.0.0.0/16")).or_insert(10), 2);
+	assert!(matches!(map.entry(c("10.1.0.0/16")), Entry::Vacant(_)));
+	// inserting merges siblings
+	assert_eq!(*map.entry(c("10.1.0.0/16")).or_insert(2), 2);
+	assert_eq!(
+		map.iter()
+			.map(|(k, v)| (k.to_string(), *v))
+			.collect::<Vec<_>>(),
+		vec![("10.0.0.0/15".to_string(), 2)]
+	);
+
+	// split aggregate
+	match map.entry(c("10.0.1.0/24")) {
+		Entry::Covered(entry) => assert_eq!(*entry.insert(5), 5),
+		_ => panic!("expected covered entry"),
+	}
+	assert_eq!(map.get(&c("10.0.1.1")), Some(&5));
+	assert_eq!(map.get(&c("10.0.2.1")), Some(&2));
+	match map.entry(c("10.0.1.0/24")) {
+		Entry::Occupied(entry) => assert_eq!(entry.remove(), 5),
+		_ => panic!("expected occupied entry"),
+	}
+	assert_eq!(map.get(&c("10.0.1.1")), None);
+	// partially mapped
+	assert!(matches!(map.entry(c("10.0.0.0/16")), Entry::Vacant(_)));
+	*map.entry(c("10.0.0.0/16")).or_default() += 3;
+	assert_eq!(map.get(&c("10.0.1.1")), Some(&3));
+}
+
+#[test]
+fn map_insert_with() {
+	let mut map = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	let entries = |map: &bitstring_trees::map::Map<AnyIpCidr, u32>| {
+		#[cfg(debug_assertions)]
+		map.raw_tree().check_invariants().unwrap();
+		map.iter()
+			.map(|(k, v)| (k.to_string(), *v))
+			.collect::<Vec<_>>()
+	};
+	map.insert(c("10.0.0.0/25"), 1);
+	map.insert(c("10.0.1.0/24"), 2);
+
+	// straddles two leaves with different values, rest unmapped
+	let mut seen = Vec::new();
+	map.insert_with(c("10.0.0.0/23"), 4, |k, new, old| {
+		seen.push(k.to_string());
+		new | old
+	});
+	assert_eq!(seen, vec!["10.0.0.0/25", "10.0.1.0/24"]);
+	assert_eq!(
+		entries(&map),
+		vec![
+			("10.0.0.0/25".to_string(), 5),
+			("10.0.0.128/25".to_string(), 4),
+			("10.0.1.0/24".to_string(), 6),
+		]
+	);
+
+	// covered by a shorter leaf
+	map.insert_with(c("10.0.1.128/25"), 1, |k, new, old| {
+		assert_eq!(k, &c("10.0.1.128/25"));
+		new | old
+	});
+	assert_eq!(map.get(&c("10.0.1.129")), Some(&7));
+	assert_eq!(map.get(&c("10.0.1.1")), Some(&6));
+
+	// merging results in equal values gets aggregated
+	map.insert_with(c("10.0.0.0/23"), 0, |_, _, _| 9);
+	assert_eq!(entries(&map), vec![("10.0.0.0/23".to_string(), 9)]);
+}
+
+#[test]
+fn map_retain() {
+	let mut map = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	map.insert(c("10.0.0.0/25"), 1);
+	map.insert(c("10.0.0.128/26"), 2);
+	map.insert(c("10.0.0.192/26"), 3);
+	map.insert(c("10.0.1.0/24"), 4);
+	map.retain(|k, v| {
+		if *k == c("10.0.1.0/24") {
+			return false;
+		}
+		// siblings end up with equal values and get merged
+		if *v == 3 {
+			*v = 2;
+		}
+		true
+	});
+	#[cfg(debug_assertions)]
+	map.raw_tree().check_invariants().unwrap();
+	assert_eq!(
+		map.iter()
+			.map(|(k, v)| (k.to_string(), *v))
+			.collect::<Vec<_>>(),
+		vec![
+			("10.0.0.0/25".to_string(), 1),
+			("10.0.0.128/25".to_string(), 2),
+		]
+	);
+	map.retain(|_, v| {
+		*v = 5;
+		true
+	});
+	assert_eq!(
+		map.get_key_value(&c("10.0.0.0/24")),
+		Some((&c("10.0.0.0/24"), &5))
+	);
+	map.retain(|_, _| false);
+	assert_eq!(map.iter().next(), None);
+}
+
+#[test]
+fn map_len() {
+	let mut map = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	assert!(map.is_empty());
+	assert_eq!(map.len(), 0);
+	map.insert(c("10.0.0.0/25"), 1);
+	map.insert(c("10.0.0.128/25"), 1);
+	assert_eq!(map.len(), 1);
+	map.insert(c("10.0.1.0/24"), 2);
+	map.insert(c("10.0.0.64/26"), 3);
+	#[cfg(debug_assertions)]
+	map.raw_tree().check_invariants().unwrap();
+	assert_eq!(map.len(), map.iter().count());
+	assert_eq!(map.len(), 4);
+	map.remove(c("10.0.0.0/23"));
+	assert!(map.is_empty());
+}
+
+#[test]
+fn map_len_random() {
+	fn check(map: &bitstring_trees::map::Map<AnyIpCidr, u32>) {
+		let count = map.iter().count();
+		assert_eq!(map.len(), count);
+		assert_eq!(map.iter().len(), count);
+		assert_eq!(map.keys().len(), count);
+		assert_eq!(map.clone().into_iter().len(), count);
+	}
+
+	let mut rng = Rng(0x1e5);
+	for _ in 0..50 {
+		let mut map = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+		for _ in 0..32 {
+			match rng.next() % 6 {
+				0 => map.remove(rng.cidr()),
+				1 => {
+					let mut other = map.split_off(&rng.cidr());
+					check(&other);
+					other.insert(rng.cidr(), 0);
+					map.merge(&other, |_, a, b| *a.max(b));
+				},
+				2 => map.retain(|_, v| *v != 1),
+				3 => {
+					map.insert_with(rng.cidr(), 2, |_, a, b| a + b);
+				},
+				_ => {
+					map.insert(rng.cidr(), (rng.next() % 3) as u32);
+				},
+			}
+			check(&map);
+			let len = map.len();
+			assert_eq!(map.iter_mut().size_hint(), (len, Some(len)));
+		}
+	}
+}
+
+#[test]
+fn map_keys_values() {
+	let mut map = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	map.insert(c("10.0.0.0/24"), 1);
+	map.insert(c("192.168.0.0/16"), 2);
+	for v in map.values_mut() {
+		*v *= 10;
+	}
+	assert_eq!(
+		map.keys().map(|k| k.to_string()).collect::<Vec<_>>(),
+		vec!["10.0.0.0/24", "192.168.0.0/16"]
+	);
+	assert_eq!(map.values().copied().collect::<Vec<_>>(), vec![10, 20]);
+}
+
+#[test]
+fn map_into_iter() {
+	let mut map = bitstring_trees::map::Map::<AnyIpCidr, String>::new();
+	map.insert(c("10.0.0.0/24"), "a".to_string());
+	map.insert(c("10.0.1.0/24"), "b".to_string());
+	map.insert(c("192.168.0.0/16"), "c".to_string());
+	let expected: Vec<(AnyIpCidr, String)> = map.iter().map(|(k, v)| (*k, v.clone())).collect();
+	assert_eq!(map.clone().into_iter().collect::<Vec<_>>(), expected);
+	let mut count = 0;
+	for (k, v) in &map {
+		assert_eq!(map.get(k), Some(v));
+		count += 1;
+	}
+	assert_eq!(count, 3);
+	// drop early
+	let mut iter = map.into_iter();
+	assert_eq!(iter.next(), Some(expected[0].clone()));
+	drop(iter);
+}
+
+#[test]
+fn map_eq_insert_order() {
+	let mut a = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	let mut b = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	for value in 0..8 {
+		a.insert(c(&format!("10.0.{value}.128/25")), value);
+		a.insert(c(&format!("10.0.{value}.0/25")), value);
+	}
+	for value in (0..8).rev() {
+		b.insert(c(&format!("10.0.{value}.0/24")), value);
+	}
+	assert_eq!(a, b);
+	b.insert(c("10.0.3.0/25"), 0);
+	assert_ne!(a, b);
+}
+
+#[test]
+fn map_iter_in() {
+	let mut map = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	map.insert(c("10.0.0.0/8"), 1);
+	map.insert(c("10.1.0.0/16"), 2);
+	map.insert(c("172.16.0.0/16"), 3);
+	map.insert(c("172.17.0.0/16"), 4);
+	map.insert(c("192.168.0.0/16"), 5);
+	let iter_in = |key: &str| {
+		map.iter_in(&c(key))
+			.map(|(k, v)| (k.to_string(), *v))
+			.collect::<Vec<_>>()
+	};
+	assert_eq!(
+		iter_in("172.16.0.0/12"),
+		vec![
+			("172.16.0.0/16".to_string(), 3),
+			("172.17.0.0/16".to_string(), 4)
+		]
+	);
+	assert_eq!(iter_in("10.1.0.0/16"), vec![("10.1.0.0/16".to_string(), 2)]);
+	// covered by shorter prefix
+	assert_eq!(iter_in("10.2.0.0/24"), vec![("10.2.0.0/15".to_string(), 1)]);
+	assert_eq!(iter_in("172.18.0.0/16"), vec![]);
+	assert_eq!(iter_in("0.0.0.0/0").len(), map.len());
+}
+
+#[test]
+fn map_merge() {
+	let entries = |map: &bitstring_trees::map::Map<AnyIpCidr, u32>| {
+		map.iter()
+			.map(|(k, v)| (k.to_string(), *v))
+			.collect::<Vec<_>>()
+	};
+	let mut base = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	base.insert(c("10.0.0.0/8"), 1);
+	base.insert(c("192.168.0.0/24"), 2);
+	let mut over = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	// nested in base
+	over.insert(c("10.1.0.0/16"), 10);
+	// partially overlapping: 192.168.0.0/24 in base
+	over.insert(c("192.168.0.128/25"), 20);
+	over.insert(c("192.168.1.0/24"), 30);
+	// disjoint
+	over.insert(c("172.16.0.0/12"), 40);
+
+	let mut merged = base.clone();
+	merged.merge(&over, |_, a, b| a + b);
+	let mut expected = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	expected.insert(c("10.0.0.0/8"), 1);
+	expected.insert(c("10.1.0.0/16"), 11);
+	expected.insert(c("192.168.0.0/25"), 2);
+	expected.insert(c("192.168.0.128/25"), 22);
+	expected.insert(c("192.168.1.0/24"), 30);
+	expected.insert(c("172.16.0.0/12"), 40);
+	assert_eq!(entries(&merged), entries(&expected));
+
+	// results equal to neighbours get merged
+	let mut merged = base.clone();
+	merged.merge(&over, |_, a, _| *a);
+	let mut expected = base.clone();
+	expected.insert(c("192.168.1.0/24"), 30);
+	expected.insert(c("172.16.0.0/12"), 40);
+	assert_eq!(entries(&merged), entries(&expected));
+	assert_eq!(merged.len(), 4);
+
+	// covering prefix in other map
+	let mut merged = over.clone();
+	merged.merge(&base, |k, _, b| {
+		assert!(k.to_string().starts_with("10.1.") || k.to_string().starts_with("192.168.0.128"));
+		*b
+	});
+	assert_eq!(
+		merged.get_key_value(&c("10.0.0.0/8")),
+		Some((&c("10.0.0.0/8"), &1))
+	);
+	assert_eq!(merged.get(&c("192.168.0.129")), Some(&2));
+
+	// random: every host maps to the combined values of both maps
+	let mut rng = Rng(0x3e7);
+	for _ in 0..50 {
+		let mut a = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+		let mut b = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+		for _ in 0..16 {
+			a.insert(rng.cidr(), (rng.next() % 3) as u32);
+			b.insert(rng.cidr(), (rng.next() % 3) as u32);
+		}
+		let mut merged = a.clone();
+		merged.merge(&b, |_, a, b| (a + b) % 3);
+		#[cfg(debug_assertions)]
+		merged.raw_tree().check_invariants().unwrap();
+		assert_eq!(merged.len(), merged.iter().count());
+		for _ in 0..64 {
+			let host = rng.host();
+			let expected = match (a.get(&host), b.get(&host)) {
+				(Some(a), Some(b)) => Some((a + b) % 3),
+				(a, b) => a.or(b).copied(),
+			};
+			assert_eq!(merged.get(&host).copied(), expected);
+		}
+	}
+}
+
+#[test]
+fn map_map_values() {
+	let mut map = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	map.insert(c("10.0.0.0/25"), 1);
+	map.insert(c("10.0.0.128/26"), 2);
+	map.insert(c("10.0.0.192/26"), 3);
+	map.insert(c("192.168.0.0/16"), 4);
+
+	let doubled = map.map_values(|_, v| v * 2);
+	assert_eq!(
+		doubled.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>(),
+		map.iter().map(|(k, v)| (*k, v * 2)).collect::<Vec<_>>()
+	);
+
+	// distinct neighbours mapped to the same value get merged (recursively)
+	let small = map.map_values(|_, v| *v < 4);
+	assert_eq!(
+		small
+			.iter()
+			.map(|(k, v)| (k.to_string(), *v))
+			.collect::<Vec<_>>(),
+		vec![
+			("10.0.0.0/24".to_string(), true),
+			("192.168.0.0/16".to_string(), false)
+		]
+	);
+	let odd = map.into_map_values(|_, v| (v & 1).to_string());
+	assert_eq!(
+		odd.iter()
+			.map(|(k, v)| (k.to_string(), v.as_str()))
+			.collect::<Vec<_>>(),
+		vec![
+			("10.0.0.0/25".to_string(), "1"),
+			("10.0.0.128/26".to_string(), "0"),
+			("10.0.0.192/26".to_string(), "1"),
+			("192.168.0.0/16".to_string(), "0"),
+		]
+	);
+}
+
+#[test]
+fn map_split_off() {
+	use bitstring::BitString as _;
+
+	let entries = |map: &bitstring_trees::map::Map<AnyIpCidr, u32>| {
+		map.iter()
+			.map(|(k, v)| (k.to_string(), *v))
+			.collect::<Vec<_>>()
+	};
+	let mut map = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	map.insert(c("10.0.0.0/8"), 1);
+	map.insert(c("192.168.0.0/24"), 2);
+	map.insert(c("192.168.1.0/24"), 3);
+
+	// straddling leaf
+	let part = map.split_off(&c("10.1.0.0/16"));
+	assert_eq!(entries(&part), vec![("10.1.0.0/16".to_string(), 1)]);
+	assert_eq!(map.get(&c("10.1.2.3")), None);
+	assert_eq!(map.get(&c("10.2.2.3")), Some(&1));
+
+	let part = map.split_off(&c("192.168.0.0/16"));
+	assert_eq!(
+		entries(&part),
+		vec![
+			("192.168.0.0/24".to_string(), 2),
+			("192.168.1.0/24".to_string(), 3)
+		]
+	);
+	assert_eq!(map.iter_in(&c("192.168.0.0/16")).next(), None);
+	assert!(map.split_off(&c("172.16.0.0/12")).is_empty());
+
+	// random: split result and rest answer exactly like before
+	let mut rng = Rng(0x5b1);
+	for _ in 0..50 {
+		let mut map = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+		for _ in 0..32 {
+			let key = rng.cidr();
+			let value = (rng.next() % 3) as u32;
+			map.insert(key, value);
+		}
+		let orig = map.clone();
+		let prefix = rng.cidr();
+		let part = map.split_off(&prefix);
+		for _ in 0..64 {
+			let host = rng.host();
+			if host.shared_prefix_len(&prefix) == prefix.len() {
+				assert_eq!(part.get(&host), orig.get(&host));
+				assert_eq!(map.get(&host), None);
+			} else {
+				assert_eq!(part.get(&host), None);
+				assert_eq!(map.get(&host), orig.get(&host));
+			}
+		}
+		let mut rejoined = map.clone();
+		rejoined.merge(&part, |_, _, _| unreachable!());
+		assert_eq!(rejoined, orig);
+	}
+}
+
+#[test]
+fn map_iter_full_mut() {
+	let mut map = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	map.insert(c("10.0.0.0/25"), 1);
+	map.insert(c("10.0.0.128/25"), 2);
+	let mut gaps = 0;
+	for (_, value) in map.iter_full_mut() {
+		match value {
+			Some(value) => *value = 5,
+			None => gaps += 1,
+		}
+	}
+	assert!(gaps > 0);
+	assert_eq!(map.len(), 2);
+	map.normalize();
+	assert_eq!(map.len(), 1);
+	assert_eq!(
+		map.get_key_value(&c("10.0.0.0/24")),
+		Some((&c("10.0.0.0/24"), &5))
+	);
+}
+
+#[test]
+fn map_insert_replaced() {
+	let mut map = bitstring_trees::map::Map::<AnyIpCidr, char>::new();
+	assert_eq!(map.insert(c("10.1.0.0/16"), 'B'), vec![]);
+	assert_eq!(map.insert(c("10.2.0.0/16"), 'C'), vec![]);
+	assert_eq!(map.insert(c("10.3.0.0/16"), 'A'), vec![]);
+	assert_eq!(
+		map.insert(c("10.0.0.0/8"), 'A'),
+		vec![(c("10.1.0.0/16"), 'B'), (c("10.2.0.0/16"), 'C')]
+	);
+	assert_eq!(map.insert(c("10.5.0.0/16"), 'A'), vec![]);
+	// covering leaf gets split
+	assert_eq!(
+		map.insert(c("10.5.0.0/16"), 'D'),
+		vec![(c("10.5.0.0/16"), 'A')]
+	);
+	assert_eq!(
+		map.insert(c("10.5.0.0/16"), 'A'),
+		vec![(c("10.5.0.0/16"), 'D')]
+	);
+	assert_eq!(map.len(), 1);
+}
+
+#[test]
+fn map_ranges() {
+	let entries = |map: &bitstring_trees::map::Map<AnyIpCidr, u32>| {
+		map.iter()
+			.map(|(k, v)| (k.to_string(), *v))
+			.collect::<Vec<_>>()
+	};
+	let mut map = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	map.insert(c("10.0.0.0/24"), 1);
+	// range starts mid-leaf
+	map.remove_range(c("10.0.0.64"), c("10.0.1.9"));
+	assert_eq!(entries(&map), vec![("10.0.0.0/26".to_string(), 1)]);
+
+	map.insert_range(c("10.0.0.64"), c("10.0.0.255"), 1);
+	assert_eq!(entries(&map), vec![("10.0.0.0/24".to_string(), 1)]);
+	map.insert_range(c("10.0.0.10"), c("10.0.0.11"), 2);
+	assert_eq!(map.get(&c("10.0.0.9")), Some(&1));
+	assert_eq!(map.get(&c("10.0.0.10")), Some(&2));
+	assert_eq!(map.get(&c("10.0.0.11")), Some(&2));
+	assert_eq!(map.get(&c("10.0.0.12")), Some(&1));
+	map.remove_range(c("10.0.0.11"), c("10.0.0.10"));
+	assert_eq!(map.get(&c("10.0.0.10")), Some(&2));
+	map.remove_range(c("10.0.0.0"), c("10.0.0.255"));
+	assert!(map.is_empty());
+}
+
+#[test]
+fn map_contains_key_covers() {
+	let mut map = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	map.insert(c("10.0.0.0/16"), 1);
+	map.insert(c("10.1.0.0/16"), 2);
+	assert!(map.contains_key(&c("10.0.0.0/16")));
+	assert!(!map.contains_key(&c("10.0.1.0/24")));
+	assert!(!map.contains_key(&c("10.0.0.0/15")));
+	assert!(map.covers(&c("10.0.1.0/24")));
+	assert!(map.covers(&c("10.1.0.0/16")));
+	// mapped, but not to a single value
+	assert!(!map.covers(&c("10.0.0.0/15")));
+	assert!(!map.covers(&c("10.2.0.0/16")));
+}
+
+#[test]
+fn map_get_or_insert() {
+	let entries = |map: &bitstring_trees::map::Map<AnyIpCidr, u32>| {
+		map.iter()
+			.map(|(k, v)| (k.to_string(), *v))
+			.collect::<Vec<_>>()
+	};
+	let mut map = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	*map.get_or_insert_with(c("10.0.0.0/24"), || 1) += 1;
+	assert_eq!(entries(&map), vec![("10.0.0.0/24".to_string(), 2)]);
+	*map.get_or_insert_with(c("10.0.0.0/24"), || 7) += 1;
+	assert_eq!(entries(&map), vec![("10.0.0.0/24".to_string(), 3)]);
+
+	// split covering leaf
+	assert_eq!(*map.get_or_insert_with(c("10.0.0.128/26"), || 5), 5);
+	assert_eq!(
+		entries(&map),
+		vec![
+			("10.0.0.0/25".to_string(), 3),
+			("10.0.0.128/26".to_string(), 5),
+			("10.0.0.192/26".to_string(), 3),
+		]
+	);
+	assert_eq!(map.get_or_clone_covering(c("10.1.0.0/24")), None);
+	*map.get_or_clone_covering(c("10.0.0.0/26")).unwrap() += 10;
+	assert_eq!(map.get(&c("10.0.0.1")), Some(&13));
+	assert_eq!(map.get(&c("10.0.0.65")), Some(&3));
+
+	// replaces partial coverage
+	assert_eq!(*map.get_or_insert_with(c("10.0.0.0/23"), || 1), 1);
+	assert_eq!(entries(&map), vec![("10.0.0.0/23".to_string(), 1)]);
+
+	// explicit entry isn't merged until normalized
+	map.get_or_clone_covering(c("10.0.1.0/24"));
+	assert_eq!(map.len(), 2);
+	map.normalize();
+	assert_eq!(entries(&map), vec![("10.0.0.0/23".to_string(), 1)]);
+}
+
+#[test]
+fn map_pop_first_last() {
+	let mut map = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	map.insert(c("10.0.0.0/25"), 1);
+	map.insert(c("10.0.0.128/26"), 2);
+	map.insert(c("10.0.0.192/26"), 1);
+	map.insert(c("2001:db8::/32"), 3);
+	let expected: Vec<(AnyIpCidr, u32)> = map.iter().map(|(k, v)| (*k, *v)).collect();
+	assert_eq!(map.pop_last(), Some((c("2001:db8::/32"), 3)));
+	assert_eq!(map.pop_first(), Some((c("10.0.0.0/25"), 1)));
+	assert_eq!(map.first_key_value(), Some((&c("10.0.0.128/26"), &2)));
+	let mut rest = Vec::new();
+	while let Some(entry) = map.pop_first() {
+		rest.push(entry);
+	}
+	assert_eq!(rest, expected[1..3]);
+	assert_eq!(map.pop_last(), None);
+
+	// remaining map must stay canonical (nothing left to merge)
+	let mut rng = Rng(0x909);
+	for _ in 0..20 {
+		let mut map = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+		for _ in 0..32 {
+			map.insert(rng.cidr(), (rng.next() & 1) as u32);
+		}
+		while !map.is_empty() {
+			let popped = if rng.next() & 1 == 0 {
+				map.pop_first()
+			} else {
+				map.pop_last()
+			};
+			assert!(popped.is_some());
+			let mut rebuilt = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+			for (key, value) in map.iter() {
+				rebuilt.insert(*key, *value);
+			}
+			assert_eq!(map, rebuilt);
+		}
+	}
+}
+
+#[test]
+fn map_iter_rev() {
+	let mut map = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	let prefixes = [
+		"10.0.0.0/8",
+		"10.1.0.0/16",
+		"10.1.2.0/24",
+		"172.16.0.0/12",
+		"192.168.0.0/24",
+		"192.168.1.0/24",
+		"0.0.0.0/1",
+		"2001:db8::/32",
+		"2001:db8:1::/48",
+		"fe80::/10",
+		"::/8",
+		"ff00::/8",
+	];
+	for (value, prefix) in prefixes.iter().enumerate() {
+		map.insert(prefix.parse().unwrap(), value as u32);
+	}
+	let forward: Vec<_> = map.iter().collect();
+	let mut backward: Vec<_> = map.iter().rev().collect();
+	backward.reverse();
+	assert_eq!(forward, backward);
+	assert!(forward.len() > 12);
+
+	// mixing both ends
+	let mut iter = map.iter();
+	assert_eq!(iter.next(), forward.first().copied());
+	assert_eq!(iter.next_back(), forward.last().copied());
+	assert_eq!(iter.count(), forward.len() - 2);
+}
+
+#[test]
+fn map_update_range() {
+	let entries = |map: &bitstring_trees::map::Map<AnyIpCidr, u32>| {
+		map.iter()
+			.map(|(k, v)| (k.to_string(), *v))
+			.collect::<Vec<_>>()
+	};
+	let mut map = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	map.insert(c("10.0.0.0/16"), 1);
+	map.insert(c("10.1.0.0/16"), 11);
+	map.insert(c("192.168.0.0/16"), 1);
+	map.update_range(&c("10.0.0.0/8"), |_, v| *v += 10);
+	assert_eq!(
+		entries(&map),
+		vec![
+			("10.0.0.0/16".to_string(), 11),
+			("10.1.0.0/16".to_string(), 21),
+			("192.168.0.0/16".to_string(), 1)
+		]
+	);
+
+	// split covering leaf; only the covered part changes
+	map.update_range(&c("192.168.1.0/24"), |k, v| {
+		assert_eq!(k, &c("192.168.1.0/24"));
+		*v = 2;
+	});
+	assert_eq!(map.get(&c("192.168.1.1")), Some(&2));
+	assert_eq!(map.get(&c("192.168.2.1")), Some(&1));
+
+	// re-equalized siblings are merged, also with parent's sibling
+	map.update_range(&c("192.168.1.0/24"), |_, v| *v = 1);
+	map.update_range(&c("10.1.0.0/16"), |_, v| *v = 11);
+	assert_eq!(
+		entries(&map),
+		vec![
+			("10.0.0.0/15".to_string(), 11),
+			("192.168.0.0/16".to_string(), 1)
+		]
+	);
+	// nothing below
+	map.update_range(&c("172.16.0.0/12"), |_, _| unreachable!());
+}
+
+#[test]
+fn merging_map() {
+	use bitstring_trees::{
+		map::MergingMap,
+		tree::LeafValueMerger,
+	};
+
+	// sum counters as long as they stay small
+	struct SumSmall;
+	impl LeafValueMerger<u32> for SumSmall {
+		fn merge(a: &u32, b: &u32) -> Option<u32> {
+			let sum = a + b;
+			(sum < 10).then_some(sum)
+		}
+	}
+
+	let mut map = MergingMap::<AnyIpCidr, u32, SumSmall>::default();
+	let entries = |map: &MergingMap<AnyIpCidr, u32, SumSmall>| {
+		map.iter()
+			.map(|(k, v)| (k.to_string(), *v))
+			.collect::<Vec<_>>()
+	};
+	map.insert(c("10.0.0.0/25"), 1);
+	map.insert(c("10.0.0.128/25"), 2);
+	assert_eq!(entries(&map), vec![("10.0.0.0/24".to_string(), 3)]);
+	map.insert(c("10.0.1.128/25"), 4);
+	map.insert(c("10.0.1.0/25"), 1);
+	assert_eq!(entries(&map), vec![("10.0.0.0/23".to_string(), 8)]);
+	map.insert(c("10.0.2.0/23"), 5);
+	assert_eq!(
+		entries(&map),
+		vec![
+			("10.0.0.0/23".to_string(), 8),
+			("10.0.2.0/23".to_string(), 5)
+		]
+	);
+
+	// default comparer only merges equal values
+	let mut map = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	map.insert(c("10.0.0.0/25"), 1);
+	map.insert(c("10.0.0.128/25"), 2);
+	assert_eq!(map.len(), 2);
+}
+
+#[test]
+fn map_diff() {
+	use bitstring::BitString as _;
+	use bitstring_trees::map::Change;
+
+	let mut a = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	a.insert(c("10.0.0.0/8"), 1);
+	a.insert(c("192.168.0.0/24"), 2);
+	let mut b = a.clone();
+	assert_eq!(a.diff(&b).next(), None);
+	assert_eq!(a.diff(&a).next(), None);
+	b.insert(c("10.1.0.0/16"), 3);
+	b.remove(c("192.168.0.128/25"));
+	b.insert(c("2001:db8::/32"), 4);
+	let diff: Vec<(String, Change<'_, u32>)> = a
+		.diff(&b)
+		.map(|(k, change)| (k.to_string(), change))
+		.collect();
+	assert_eq!(
+		diff,
+		vec![
+			("10.1.0.0/16".to_string(), Change::Modified(&1, &3)),
+			("192.168.0.128/25".to_string(), Change::Removed(&2)),
+			("2001:db8::/32".to_string(), Change::Added(&4)),
+		]
+	);
+
+	let mut rng = Rng(0xd1ff);
+	for _ in 0..50 {
+		let mut a = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+		let mut b = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+		for _ in 0..16 {
+			let (key, value) = (rng.cidr(), (rng.next() % 3) as u32);
+			a.insert(key, value);
+			if rng.next() & 3 != 0 {
+				b.insert(key, value);
+			}
+		}
+		for _ in 0..4 {
+			let (key, value) = (rng.cidr(), (rng.next() % 3) as u32);
+			b.insert(key, value);
+		}
+		let diff: Vec<(AnyIpCidr, Change<'_, u32>)> = a.diff(&b).collect();
+		for _ in 0..64 {
+			let host = rng.host();
+			let expected = match (a.get(&host), b.get(&host)) {
+				(x, y) if x == y => None,
+				(Some(x), None) => Some(Change::Removed(x)),
+				(None, Some(y)) => Some(Change::Added(y)),
+				(Some(x), Some(y)) => Some(Change::Modified(x, y)),
+				(None, None) => unreachable!(),
+			};
+			let found = diff
+				.iter()
+				.find(|(k, _)| host.shared_prefix_len(k) == k.len())
+				.map(|(_, change)| *change);
+			assert_eq!(found, expected);
+		}
+	}
+}
+
+#[test]
+fn map_view() {
+	let mut map = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	map.insert(c("10.0.0.0/8"), 1);
+	map.insert(c("192.168.0.0/25"), 2);
+	map.insert(c("192.168.1.0/24"), 3);
+
+	let view = map.view(c("192.168.0.0/23"));
+	assert_eq!(view.prefix(), &c("192.168.0.0/23"));
+	assert_eq!(view.get(&c("192.168.0.1")), Some(&2));
+	assert_eq!(view.get(&c("192.168.0.128")), None);
+	assert_eq!(view.get(&c("10.0.0.1")), None);
+	assert_eq!(
+		view.iter().collect::<Vec<_>>(),
+		vec![(&c("192.168.0.0/25"), &2), (&c("192.168.1.0/24"), &3)]
+	);
+	assert_eq!(
+		view.iter_full().collect::<Vec<_>>(),
+		vec![
+			(c("192.168.0.0/25"), Some(&2)),
+			(c("192.168.0.128/25"), None),
+			(c("192.168.1.0/24"), Some(&3)),
+		]
+	);
+
+	let view = map.view(c("10.1.0.0/16"));
+	assert_eq!(view.get(&c("10.1.2.3")), Some(&1));
+	assert_eq!(
+		view.iter().collect::<Vec<_>>(),
+		vec![(&c("10.0.0.0/8"), &1)]
+	);
+	assert_eq!(
+		view.iter_full().collect::<Vec<_>>(),
+		vec![(c("10.1.0.0/16"), Some(&1))]
+	);
+
+	let view = map.view(c("172.16.0.0/12"));
+	assert_eq!(view.iter().next(), None);
+	assert_eq!(
+		view.iter_full().collect::<Vec<_>>(),
+		vec![(c("172.16.0.0/12"), None)]
+	);
+}
+
+#[test]
+fn map_get_covering() {
+	let mut map = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	map.insert(c("10.0.0.0/12"), 1);
+	map.insert(c("192.168.0.0/24"), 2);
+	map.insert(c("192.168.1.0/24"), 3);
+
+	// covered by shorter leaf
+	assert_eq!(
+		map.get_covering(&c("10.1.2.3")),
+		Some((&c("10.0.0.0/12"), &1))
+	);
+	// exact leaf
+	assert_eq!(
+		map.get_covering(&c("10.0.0.0/12")),
+		Some((&c("10.0.0.0/12"), &1))
+	);
+	assert_eq!(
+		map.get_covering(&c("192.168.1.0/24")),
+		Some((&c("192.168.1.0/24"), &3))
+	);
+	// key shorter than coverage
+	assert_eq!(map.get_covering(&c("10.0.0.0/8")), None);
+	assert_eq!(map.get_covering(&c("192.168.0.0/23")), None);
+	// not mapped at all
+	assert_eq!(map.get_covering(&c("10.16.0.1")), None);
+
+	for key in ["10.1.2.3", "10.0.0.0/8", "192.168.0.0/23", "192.168.1.1"] {
+		assert_eq!(map.get_covering(&c(key)), map.lookup(&c(key)));
+		assert_eq!(map.get_covering(&c(key)).map(|(_, v)| v), map.get(&c(key)));
+	}
+}
+
+#[test]
+fn map_insert_if_absent() {
+	let mut map = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	map.insert(c("10.0.0.0/9"), 1);
+	map.insert(c("10.192.0.0/10"), 2);
+
+	assert!(map.insert_if_absent(c("10.0.0.0/8"), 3));
+	assert_eq!(
+		map.iter()
+			.map(|(k, v)| (k.to_string(), *v))
+			.collect::<Vec<_>>(),
+		vec![
+			("10.0.0.0/9".to_string(), 1),
+			("10.128.0.0/10".to_string(), 3),
+			("10.192.0.0/10".to_string(), 2),
+		]
+	);
+	// fully covered already
+	assert!(!map.insert_if_absent(c("10.0.0.0/8"), 4));
+	assert!(!map.insert_if_absent(c("10.1.0.0/16"), 4));
+	assert_eq!(map.get(&c("10.1.0.0/16")), Some(&1));
+
+	// completely unmapped
+	assert!(map.insert_if_absent(c("11.0.0.0/8"), 3));
+	assert_eq!(
+		map.get_covering(&c("11.0.0.1")),
+		Some((&c("11.0.0.0/8"), &3))
+	);
+	assert_eq!(map.len(), 4);
+}
+
+#[test]
+fn map_iter_ranges() {
+	let c = |s: &str| -> Ipv4Cidr { s.parse().unwrap() };
+	let mut map = bitstring_trees::map::Map::<Ipv4Cidr, u32>::new();
+	let ranges = |map: &bitstring_trees::map::Map<Ipv4Cidr, u32>| -> Vec<String> {
+		map.iter_ranges(32)
+			.map(|(first, last, value)| format!("{}-{}={}", first, last, value))
+			.collect()
+	};
+	assert_eq!(ranges(&map), Vec::<String>::new());
+
+	// adjacent across parent boundary: not siblings in the tree
+	map.insert(c("10.0.1.0/24"), 1);
+	map.insert(c("10.0.2.0/24"), 1);
+	assert_eq!(map.len(), 2);
+	assert_eq!(ranges(&map), vec!["10.0.1.0-10.0.2.255=1"]);
+
+	// different value breaks the range
+	map.insert(c("10.0.3.0/24"), 2);
+	// gap breaks the range
+	map.insert(c("10.0.5.0/24"), 2);
+	map.insert(c("10.0.6.0/32"), 2);
+	assert_eq!(
+		ranges(&map),
+		vec![
+			"10.0.1.0-10.0.2.255=1",
+			"10.0.3.0-10.0.3.255=2",
+			"10.0.5.0-10.0.6.0=2",
+		]
+	);
+}
+
+#[test]
+fn map_eq() {
+	let mut a = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	a.insert("192.168.0.0/25".parse().unwrap(), 1);
+	a.insert("192.168.0.128/25".parse().unwrap(), 1);
+	let mut b = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	b.insert("192.168.0.0/24".parse().unwrap(), 2);
+	assert_ne!(a, b);
+	b.insert("192.168.0.0/24".parse().unwrap(), 1);
+	assert_eq!(a, b);
+
+	// values are compared with `Eq`, not with the comparer
+	let mut map =
+		bitstring_trees::map::Map::<AnyIpCidr, u32, bitstring_trees::tree::NoEqual>::default();
+	map.insert(c("10.0.0.0/8"), 1);
+	assert_eq!(map, map.clone());
+	let mut other = map.clone();
+	other.insert(c("10.0.0.0/8"), 2);
+	assert_ne!(map, other);
+}
+
+#[test]
+fn map_first_last() {
+	let mut map = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	assert_eq!(map.first_key_value(), None);
+	map.insert("10.0.0.0/8".parse().unwrap(), 1);
+	map.insert("192.168.0.0/24".parse().unwrap(), 2);
+	let (k, v) = map.first_key_value().unwrap();
+	assert_eq!((k.to_string(), *v), ("10.0.0.0/8".to_string(), 1));
+	let (k, v) = map.last_key_value().unwrap();
+	assert_eq!((k.to_string(), *v), ("192.168.0.0/24".to_string(), 2));
+}
+
+#[test]
+fn map_covering_prefix() {
+	let mut map = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	map.insert("10.0.0.0/25".parse().unwrap(), 1);
+	map.insert("10.0.0.128/26".parse().unwrap(), 2);
+	let get = |key: &str| {
+		map.get_covering(&key.parse().unwrap())
+			.map(|(k, v)| (k.to_string(), *v))
+	};
+	assert_eq!(get("10.0.0.1"), Some(("10.0.0.0/25".to_string(), 1)));
+	assert_eq!(get("10.0.0.128/26"), Some(("10.0.0.128/26".to_string(), 2)));
+	assert_eq!(get("10.0.0.0/24"), None);
+	assert_eq!(get("10.0.1.0/24"), None);
+	assert_eq!(map.get(&"10.0.1.0/24".parse().unwrap()), None);
+	assert_eq!(map.get(&"10.0.0.129".parse().unwrap()), Some(&2));
+}
+
+#[test]
+fn map_display() {
+	let mut map = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	map.insert("10.0.0.0/8".parse().unwrap(), 1);
+	map.insert("192.168.0.0/16".parse().unwrap(), 2);
+	assert_eq!(map.to_string(), "{10.0.0.0/8 => 1, 192.168.0.0/16 => 2}");
+	assert_eq!(
+		format!("{:#}", map),
+		"10.0.0.0/8 => 1\n192.168.0.0/16 => 2\n"
+	);
+	let empty = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	assert_eq!(empty.to_string(), "{}");
+	assert_eq!(format!("{:#}", empty), "");
+}
+
+#[test]
+fn map_raw_tree_mut() {
+	let mut map = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	map.insert("10.0.0.0/8".parse().unwrap(), 1);
+	assert_eq!(map.raw_tree().iter_leaf().count(), 1);
+	map.raw_tree_mut()
+		.set_leaf_value("10.0.0.0/9".parse().unwrap(), 2);
+	assert_eq!(map.to_string(), "{10.0.0.0/9 => 2, 10.128.0.0/9 => 1}");
+}
+
+#[test]
+fn map_longest_common_prefix() {
+	let mut map = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	assert_eq!(map.longest_common_prefix(), None);
+	map.insert(c("192.168.0.0/24"), 1);
+	map.insert(c("192.168.1.0/24"), 2);
+	assert_eq!(map.longest_common_prefix(), Some(c("192.168.0.0/23")));
+}
@@ -1,7 +1,17 @@
+mod common;
+
 use bitstring_trees::iter::iter_between;
 use cidr::{
 	AnyIpCidr,
 	Ipv4Cidr,
+	Ipv6Cidr,
+};
+use common::{
+	assert_canonical,
+	c,
+	cidr_list,
+	cidr_set,
+	Rng,
 };
 
 #[test]
@@ -159,3 +169,1137 @@ fn goto() {
 	assert!(!set.contains(&"0.168.10.0/24".parse().unwrap()));
 	assert!(!set.contains(&"128.168.10.0/24".parse().unwrap()));
 }
+
+#[test]
+fn union() {
+	let a = cidr_set(&["192.168.0.0/24", "10.0.0.0/8", "8000::/1"]);
+	let b = cidr_set(&["192.168.1.0/24", "10.1.0.0/16", "2001:db8::/32"]);
+	let expected = vec!["10.0.0.0/8", "192.168.0.0/23", "2001:db8::/32", "8000::/1"];
+	assert_eq!(cidr_list(&a.union(&b)), expected);
+	assert_eq!(cidr_list(&b.union(&a)), expected);
+
+	let empty = cidr_set(&[]);
+	assert_eq!(cidr_list(&a.union(&empty)), cidr_list(&a));
+	assert_eq!(cidr_list(&empty.union(&a)), cidr_list(&a));
+
+	let mut c = cidr_set(&["0.0.0.0/1"]);
+	c.union_with(&cidr_set(&["128.0.0.0/1"]));
+	assert_eq!(cidr_list(&c), vec!["0.0.0.0/0"]);
+}
+
+#[test]
+fn intersection() {
+	let a = cidr_set(&["10.0.0.0/8", "192.168.0.0/23", "8000::/1"]);
+	let b = cidr_set(&["10.1.0.0/16", "192.168.1.0/24", "192.168.2.0/24", "::/0"]);
+	let expected = vec!["10.1.0.0/16", "192.168.1.0/24", "8000::/1"];
+	assert_eq!(cidr_list(&a.intersection(&b)), expected);
+	assert_eq!(cidr_list(&b.intersection(&a)), expected);
+	assert!(a.intersection(&cidr_set(&[])).iter().next().is_none());
+}
+
+#[test]
+fn intersection_random() {
+	let mut rng = Rng(0x1234_5678_9abc_def0);
+	for _ in 0..200 {
+		let a = rng.set(40);
+		let b = rng.set(40);
+		let both = a.intersection(&b);
+		assert_eq!(cidr_list(&both), cidr_list(&b.intersection(&a)));
+		for _ in 0..50 {
+			let host = rng.host();
+			assert_eq!(both.contains(&host), a.contains(&host) && b.contains(&host));
+		}
+		assert_canonical(&both);
+	}
+}
+
+#[test]
+fn difference() {
+	let a = cidr_set(&["10.0.0.0/8"]);
+	let b = cidr_set(&[
+		"10.1.2.0/24",
+		"10.200.0.0/24",
+		"10.200.3.0/24",
+		"192.168.0.0/16",
+	]);
+	assert_eq!(
+		cidr_list(&a.difference(&b)),
+		vec![
+			"10.0.0.0/16",
+			"10.1.0.0/23",
+			"10.1.3.0/24",
+			"10.1.4.0/22",
+			"10.1.8.0/21",
+			"10.1.16.0/20",
+			"10.1.32.0/19",
+			"10.1.64.0/18",
+			"10.1.128.0/17",
+			"10.2.0.0/15",
+			"10.4.0.0/14",
+			"10.8.0.0/13",
+			"10.16.0.0/12",
+			"10.32.0.0/11",
+			"10.64.0.0/10",
+			"10.128.0.0/10",
+			"10.192.0.0/13",
+			"10.200.1.0/24",
+			"10.200.2.0/24",
+			"10.200.4.0/22",
+			"10.200.8.0/21",
+			"10.200.16.0/20",
+			"10.200.32.0/19",
+			"10.200.64.0/18",
+			"10.200.128.0/17",
+			"10.201.0.0/16",
+			"10.202.0.0/15",
+			"10.204.0.0/14",
+			"10.208.0.0/12",
+			"10.224.0.0/11",
+		]
+	);
+	assert!(b.difference(&b).iter().next().is_none());
+	assert_eq!(cidr_list(&b.difference(&a)), vec!["192.168.0.0/16"]);
+
+	let mut c = cidr_set(&["10.1.2.0/24", "10.1.3.0/24"]);
+	c.subtract(&cidr_set(&["10.1.2.128/25"]));
+	assert_eq!(cidr_list(&c), vec!["10.1.2.0/25", "10.1.3.0/24"]);
+}
+
+#[test]
+fn difference_random() {
+	let mut rng = Rng(0x0fed_cba9_8765_4321);
+	for _ in 0..200 {
+		let a = rng.set(40);
+		let b = rng.set(40);
+		let diff = a.difference(&b);
+		for _ in 0..50 {
+			let host = rng.host();
+			assert_eq!(
+				diff.contains(&host),
+				a.contains(&host) && !b.contains(&host)
+			);
+		}
+		assert_canonical(&diff);
+	}
+}
+
+#[test]
+fn symmetric_difference() {
+	let a = cidr_set(&["10.0.0.0/8", "192.168.0.0/24"]);
+	let b = cidr_set(&["10.0.0.0/9", "192.168.1.0/24", "8000::/1"]);
+	assert_eq!(
+		cidr_list(&a.symmetric_difference(&b)),
+		vec!["10.128.0.0/9", "192.168.0.0/23", "8000::/1"]
+	);
+	assert!(a.symmetric_difference(&a).iter().next().is_none());
+
+	let full = cidr_set(&["::/0", "0.0.0.0/0"]);
+	assert_eq!(cidr_list(&full), vec!["any"]);
+	assert_eq!(
+		cidr_list(&cidr_set(&["8000::/1"]).symmetric_difference(&full)),
+		vec!["0.0.0.0/0", "::/1"]
+	);
+}
+
+#[test]
+fn symmetric_difference_random() {
+	let mut rng = Rng(0x5555_aaaa_3333_cccc);
+	for _ in 0..200 {
+		let a = rng.set(40);
+		let b = rng.set(40);
+		let xor = a.symmetric_difference(&b);
+		assert_eq!(cidr_list(&xor), cidr_list(&b.symmetric_difference(&a)));
+		for _ in 0..50 {
+			let host = rng.host();
+			assert_eq!(xor.contains(&host), a.contains(&host) != b.contains(&host));
+		}
+		assert_canonical(&xor);
+	}
+}
+
+#[test]
+fn complement() {
+	let empty = cidr_set(&[]);
+	assert_eq!(cidr_list(&empty.complement()), vec!["any"]);
+	assert!(empty.complement().complement().iter().next().is_none());
+
+	let mut set = cidr_set(&["192.168.10.0/24", "224.0.0.0/4", "8000::/1"]);
+	let inverted = set.complement();
+	assert_eq!(
+		inverted.iter().cloned().collect::<Vec<_>>(),
+		set.iter_full()
+			.filter(|(_, covered)| !covered)
+			.map(|(cidr, _)| cidr)
+			.collect::<Vec<_>>()
+	);
+	assert_canonical(&inverted);
+
+	let before = cidr_list(&set);
+	set.invert();
+	assert_eq!(cidr_list(&set), cidr_list(&inverted));
+	set.invert();
+	assert_eq!(cidr_list(&set), before);
+}
+
+#[test]
+fn subset() {
+	let allocation = cidr_set(&["10.0.0.0/8", "2001:db8::/32"]);
+	let announced = cidr_set(&["10.1.0.0/16", "10.2.3.0/24", "2001:db8:1::/48"]);
+	assert!(announced.is_subset(&allocation));
+	assert!(allocation.is_superset(&announced));
+	assert!(!allocation.is_subset(&announced));
+	assert!(allocation.is_subset(&allocation));
+
+	let empty = cidr_set(&[]);
+	assert!(empty.is_subset(&allocation));
+	assert!(empty.is_subset(&empty));
+	assert!(!allocation.is_subset(&empty));
+
+	let leaking = cidr_set(&["10.1.0.0/16", "11.0.0.0/24"]);
+	assert!(!leaking.is_subset(&allocation));
+	// shorter leaf than allocation
+	assert!(!cidr_set(&["10.0.0.0/7"]).is_subset(&allocation));
+}
+
+#[test]
+fn subset_random() {
+	let mut rng = Rng(0x0123_4567_89ab_cdef);
+	for _ in 0..200 {
+		let a = rng.set(10);
+		let b = rng.set(40);
+		let expected = a.difference(&b).iter().next().is_none();
+		assert_eq!(a.is_subset(&b), expected);
+		assert!(a.intersection(&b).is_subset(&b));
+		assert!(a.union(&b).is_superset(&a));
+	}
+}
+
+#[test]
+fn overlaps() {
+	let a = cidr_set(&["10.0.0.0/8", "2001:db8::/32"]);
+	assert!(a.overlaps(&cidr_set(&["10.1.2.0/24"])));
+	assert!(a.overlaps(&cidr_set(&["0.0.0.0/0"])));
+	assert!(a.overlaps(&a));
+	assert!(!a.overlaps(&cidr_set(&["11.0.0.0/8", "2001:db9::/32"])));
+	assert!(!a.overlaps(&cidr_set(&[])));
+	assert!(!cidr_set(&["0.0.0.0/1"]).overlaps(&cidr_set(&["128.0.0.0/1"])));
+
+	let mut rng = Rng(0x7777_1111_9999_3333);
+	for _ in 0..200 {
+		let a = rng.set(20);
+		let b = rng.set(20);
+		assert_eq!(a.overlaps(&b), a.intersection(&b).iter().next().is_some());
+	}
+}
+
+#[test]
+fn contains_exact() {
+	let mut set = cidr_set(&["192.168.0.0/16", "10.0.0.0/25"]);
+	assert!(set.contains_exact(&"192.168.0.0/16".parse().unwrap()));
+	assert!(set.contains(&"192.168.0.0/24".parse().unwrap()));
+	assert!(!set.contains_exact(&"192.168.0.0/24".parse().unwrap()));
+	// inner node, not a leaf
+	assert!(!set.contains_exact(&"0.0.0.0/0".parse().unwrap()));
+
+	set.insert("10.0.0.128/25".parse().unwrap());
+	assert!(set.contains_exact(&"10.0.0.0/24".parse().unwrap()));
+	assert!(!set.contains_exact(&"10.0.0.0/25".parse().unwrap()));
+	assert!(!set.contains_exact(&"10.0.0.128/25".parse().unwrap()));
+}
+
+#[test]
+fn remove() {
+	use bitstring_trees::set::Removed;
+
+	let mut set = cidr_set(&["10.0.0.0/8", "192.168.0.0/24", "192.168.2.0/24"]);
+	assert_eq!(set.remove("11.0.0.0/8".parse().unwrap()), Removed::Nothing);
+	// disjoint from all nodes, but shorter than them
+	assert_eq!(set.remove("172.0.0.0/6".parse().unwrap()), Removed::Nothing);
+	assert_eq!(set.remove("::/0".parse().unwrap()), Removed::Nothing);
+	assert_eq!(
+		cidr_list(&set),
+		vec!["10.0.0.0/8", "192.168.0.0/24", "192.168.2.0/24"]
+	);
+
+	assert_eq!(set.remove("10.1.0.0/16".parse().unwrap()), Removed::Split);
+	assert!(!set.contains(&"10.1.2.3/32".parse().unwrap()));
+	assert!(set.contains(&"10.2.0.0/16".parse().unwrap()));
+	assert_canonical(&set);
+
+	assert_eq!(
+		set.remove("192.168.2.0/24".parse().unwrap()),
+		Removed::Exact
+	);
+	assert_eq!(
+		set.remove("192.168.0.0/16".parse().unwrap()),
+		Removed::Contained
+	);
+	assert_eq!(
+		set.remove("10.0.0.0/8".parse().unwrap()),
+		Removed::Contained
+	);
+	assert_eq!(set.remove("0.0.0.0/0".parse().unwrap()), Removed::Nothing);
+	assert!(set.iter().next().is_none());
+
+	let mut single = cidr_set(&["10.1.0.0/16"]);
+	assert_eq!(
+		single.remove("192.0.0.0/8".parse().unwrap()),
+		Removed::Nothing
+	);
+	assert_eq!(cidr_list(&single), vec!["10.1.0.0/16"]);
+}
+
+#[test]
+fn insert_range() {
+	let mut set = cidr_set(&[]);
+	set.insert_range(
+		"192.168.0.6".parse().unwrap(),
+		"192.168.0.17".parse().unwrap(),
+	);
+	assert_eq!(
+		cidr_list(&set),
+		vec!["192.168.0.6/31", "192.168.0.8/29", "192.168.0.16/31"]
+	);
+
+	// reversed range
+	let mut set = cidr_set(&[]);
+	set.insert_range("10.0.0.2".parse().unwrap(), "10.0.0.1".parse().unwrap());
+	assert!(set.iter().next().is_none());
+
+	let mut set = cidr_set(&[]);
+	set.insert_range(
+		"0.0.0.0".parse().unwrap(),
+		"255.255.255.255".parse().unwrap(),
+	);
+	assert_eq!(cidr_list(&set), vec!["0.0.0.0/0"]);
+	set.insert_range(
+		"::".parse().unwrap(),
+		"ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff".parse().unwrap(),
+	);
+	assert_eq!(cidr_list(&set), vec!["any"]);
+}
+
+#[test]
+fn remove_range() {
+	let mut set = cidr_set(&["10.0.0.0/24"]);
+	set.remove_range("10.0.0.1".parse().unwrap(), "10.0.0.6".parse().unwrap());
+	assert_eq!(
+		cidr_list(&set),
+		vec![
+			"10.0.0.0",
+			"10.0.0.7",
+			"10.0.0.8/29",
+			"10.0.0.16/28",
+			"10.0.0.32/27",
+			"10.0.0.64/26",
+			"10.0.0.128/25",
+		]
+	);
+
+	let mut set = cidr_set(&["0.0.0.0/0"]);
+	set.remove_range(
+		"0.0.0.1".parse().unwrap(),
+		"255.255.255.254".parse().unwrap(),
+	);
+	assert_eq!(cidr_list(&set), vec!["0.0.0.0", "255.255.255.255"]);
+
+	// reversed range
+	set.remove_range(
+		"255.255.255.255".parse().unwrap(),
+		"0.0.0.0".parse().unwrap(),
+	);
+	assert_eq!(cidr_list(&set), vec!["0.0.0.0", "255.255.255.255"]);
+}
+
+#[test]
+fn retain() {
+	let mut set = cidr_set(&["10.0.0.0/24", "2001:db8::/32"]);
+	set.retain(|c| c.is_ipv4());
+	assert_eq!(cidr_list(&set), vec!["10.0.0.0/24"]);
+
+	// delete alternating leafs
+	let hosts: Vec<String> = (0..16).map(|i| format!("10.0.0.{}", 2 * i)).collect();
+	let mut set = cidr_set(&hosts.iter().map(String::as_str).collect::<Vec<_>>());
+	let mut index = 0;
+	set.retain(|_| {
+		index += 1;
+		index % 2 == 0
+	});
+	let expected: Vec<String> = (0..8).map(|i| format!("10.0.0.{}", 4 * i + 2)).collect();
+	assert_eq!(cidr_list(&set), expected);
+	assert_canonical(&set);
+
+	let mut rng = Rng(0x1234_5678);
+	for _ in 0..50 {
+		let mut set = rng.set(64);
+		let mut keep = Vec::new();
+		let mut flag = false;
+		set.retain(|c| {
+			flag = !flag;
+			if flag {
+				keep.push(c.to_string());
+			}
+			flag
+		});
+		assert_eq!(cidr_list(&set), keep);
+		assert_canonical(&set);
+	}
+
+	set.retain(|_| false);
+	assert_eq!(cidr_list(&set), Vec::<String>::new());
+}
+
+#[test]
+fn len() {
+	let mut set = cidr_set(&[]);
+	assert!(set.is_empty());
+	set.insert("192.168.0.0/25".parse().unwrap());
+	set.insert("192.168.0.128/25".parse().unwrap());
+	assert_eq!(set.len(), 1);
+	set.remove("192.168.0.7/32".parse().unwrap());
+	assert_eq!(set.len(), 8);
+	set.insert("192.168.0.0/16".parse().unwrap());
+	assert_eq!(set.len(), 1);
+	set.remove("192.168.0.0/16".parse().unwrap());
+	assert!(set.is_empty());
+
+	let mut rng = Rng(0xdead_beef);
+	for _ in 0..50 {
+		let mut a = rng.set(64);
+		let b = rng.set(64);
+		for _ in 0..8 {
+			a.remove(rng.cidr());
+		}
+		assert_canonical(&a);
+		assert_canonical(&a.union(&b));
+		assert_canonical(&a.intersection(&b));
+		assert_canonical(&a.difference(&b));
+		assert_canonical(&a.symmetric_difference(&b));
+		assert_canonical(&a.complement());
+	}
+}
+
+#[test]
+fn clear() {
+	let mut rng = Rng(0x5eed);
+	let mut set = rng.set(64);
+	set.insert("10.0.0.1".parse().unwrap());
+	set.clear();
+	assert!(set.is_empty());
+	assert_eq!(cidr_list(&set), Vec::<String>::new());
+	set.insert("10.0.0.0/8".parse().unwrap());
+	assert_eq!(cidr_list(&set), vec!["10.0.0.0/8"]);
+}
+
+#[test]
+fn collect() {
+	let set: bitstring_trees::set::Set<AnyIpCidr> = ["192.168.0.0/25", "192.168.0.128/25"]
+		.iter()
+		.map(|c| c.parse().unwrap())
+		.collect();
+	assert_eq!(cidr_list(&set), vec!["192.168.0.0/24"]);
+
+	let mut set = set;
+	let more: Vec<AnyIpCidr> = vec!["10.0.0.0/8".parse().unwrap()];
+	set.extend(&more);
+	set.extend(more);
+	assert_eq!(cidr_list(&set), vec!["10.0.0.0/8", "192.168.0.0/24"]);
+}
+
+#[test]
+fn into_iter() {
+	let mut rng = Rng(0xfeed);
+	for _ in 0..20 {
+		let set = rng.set(64);
+		let list = cidr_list(&set);
+		let owned: Vec<String> = set.clone().into_iter().map(|c| c.to_string()).collect();
+		assert_eq!(owned, list);
+		let borrowed: Vec<String> = (&set).into_iter().map(|c| c.to_string()).collect();
+		assert_eq!(borrowed, list);
+
+		// drop iterator halfway
+		let mut iter = set.into_iter();
+		iter.next();
+		drop(iter);
+	}
+}
+
+#[test]
+fn eq() {
+	let a = cidr_set(&["192.168.0.0/25", "10.0.0.0/8", "192.168.0.128/25"]);
+	let b = cidr_set(&["192.168.0.128/25", "192.168.0.0/24", "10.0.0.0/8"]);
+	assert_eq!(a, b);
+	assert_ne!(a, cidr_set(&["192.168.0.0/24"]));
+	assert_ne!(a, cidr_set(&["192.168.0.0/24", "10.0.0.0/9"]));
+
+	let mut rng = Rng(0xe9);
+	for _ in 0..20 {
+		let set = rng.set(64);
+		let mut keys: Vec<AnyIpCidr> = set.iter().cloned().collect();
+		keys.reverse();
+		assert_eq!(set, keys.into_iter().collect());
+	}
+}
+
+#[test]
+fn hash() {
+	use std::collections::HashSet;
+
+	let a = cidr_set(&["192.168.0.0/25", "10.0.0.0/8", "192.168.0.128/25"]);
+	let b = cidr_set(&["192.168.0.128/25", "192.168.0.0/24", "10.0.0.0/8"]);
+	let c = cidr_set(&["192.168.0.0/24"]);
+	let mut policies = HashSet::new();
+	assert!(policies.insert(a));
+	assert!(!policies.insert(b));
+	assert!(policies.insert(c));
+	assert_eq!(policies.len(), 2);
+}
+
+#[test]
+fn iter_in() {
+	let set = cidr_set(&[
+		"10.1.0.0/16",
+		"10.2.3.0/24",
+		"10.128.0.0/9",
+		"192.168.0.0/24",
+		"::/0",
+	]);
+	let list = |prefix: &str| -> Vec<String> {
+		set.iter_in(&prefix.parse().unwrap())
+			.map(|c| c.to_string())
+			.collect()
+	};
+	assert_eq!(
+		list("10.0.0.0/8"),
+		vec!["10.1.0.0/16", "10.2.3.0/24", "10.128.0.0/9"]
+	);
+	assert_eq!(list("10.0.0.0/9"), vec!["10.1.0.0/16", "10.2.3.0/24"]);
+	assert_eq!(list("10.2.0.0/16"), vec!["10.2.3.0/24"]);
+	// covered by shorter prefix
+	assert_eq!(list("192.168.0.1"), vec!["192.168.0.0/24"]);
+	assert_eq!(list("2001:db8::/32"), vec!["::/0"]);
+	// misses
+	assert_eq!(list("10.3.0.0/16"), Vec::<String>::new());
+	assert_eq!(list("11.0.0.0/8"), Vec::<String>::new());
+	assert_eq!(list("any"), cidr_list(&set));
+}
+
+#[test]
+fn iter_uncovered() {
+	let list = |set: &bitstring_trees::set::Set<AnyIpCidr>| -> Vec<String> {
+		set.iter_uncovered().map(|c| c.to_string()).collect()
+	};
+	assert_eq!(list(&cidr_set(&[])), vec!["any"]);
+	assert_eq!(list(&cidr_set(&["any"])), Vec::<String>::new());
+	assert_eq!(list(&cidr_set(&["::/0"])), vec!["0.0.0.0/0"]);
+	assert_eq!(
+		list(&cidr_set(&["0.0.0.0/1", "192.0.0.0/2", "::/0"])),
+		vec!["128.0.0.0/2"]
+	);
+
+	let mut rng = Rng(0x0c0);
+	for _ in 0..20 {
+		let set = rng.set(64);
+		let expected: Vec<String> = set
+			.iter_full()
+			.filter(|(_, covered)| !covered)
+			.map(|(c, _)| c.to_string())
+			.collect();
+		assert_eq!(list(&set), expected);
+	}
+}
+
+#[test]
+fn first_last() {
+	let set = cidr_set(&[]);
+	assert_eq!(set.first(), None);
+	assert_eq!(set.last(), None);
+
+	// root is a leaf
+	let set = cidr_set(&["10.0.0.0/8"]);
+	assert_eq!(set.first().unwrap().to_string(), "10.0.0.0/8");
+	assert_eq!(set.last().unwrap().to_string(), "10.0.0.0/8");
+
+	let set = cidr_set(&[
+		"10.0.0.0/8",
+		"192.168.0.0/24",
+		"2001:db8::/32",
+		"172.16.0.0/12",
+	]);
+	assert_eq!(set.first().unwrap().to_string(), "10.0.0.0/8");
+	assert_eq!(set.last().unwrap().to_string(), "2001:db8::/32");
+}
+
+#[test]
+fn drain() {
+	let mut set = cidr_set(&["10.0.0.0/8", "192.168.0.0/24", "2001:db8::/32"]);
+	let drained: Vec<String> = set.drain().map(|c| c.to_string()).collect();
+	assert_eq!(
+		drained,
+		vec!["10.0.0.0/8", "192.168.0.0/24", "2001:db8::/32"]
+	);
+	assert!(set.is_empty());
+
+	// drop halfway
+	let mut set = cidr_set(&["10.0.0.0/8", "192.168.0.0/24", "2001:db8::/32"]);
+	assert_eq!(set.drain().next().unwrap().to_string(), "10.0.0.0/8");
+	assert!(set.is_empty());
+	assert_eq!(cidr_list(&set), Vec::<String>::new());
+	set.insert("10.0.0.0/8".parse().unwrap());
+	assert_eq!(cidr_list(&set), vec!["10.0.0.0/8"]);
+}
+
+#[test]
+fn split_off() {
+	let mut set = cidr_set(&[
+		"10.0.0.0/8",
+		"2001:db8::/48",
+		"2001:db8:2::/48",
+		"2001:db9::/32",
+	]);
+	let v6 = set.split_off(&"2001:db8::/32".parse().unwrap());
+	assert_eq!(cidr_list(&v6), vec!["2001:db8::/48", "2001:db8:2::/48"]);
+	assert_eq!(cidr_list(&set), vec!["10.0.0.0/8", "2001:db9::/32"]);
+	assert_canonical(&set);
+	assert_canonical(&v6);
+
+	// split covering leaf
+	let part = set.split_off(&"10.1.0.0/16".parse().unwrap());
+	assert_eq!(cidr_list(&part), vec!["10.1.0.0/16"]);
+	assert_eq!(set.len(), 9);
+	assert!(!set.contains(&"10.1.2.3".parse().unwrap()));
+	assert!(set.contains(&"10.2.0.0/16".parse().unwrap()));
+	assert_canonical(&set);
+
+	// misses
+	assert!(set.split_off(&"192.168.0.0/16".parse().unwrap()).is_empty());
+	assert_eq!(set.len(), 9);
+
+	// exact leaf
+	let part = set.split_off(&"2001:db9::/32".parse().unwrap());
+	assert_eq!(cidr_list(&part), vec!["2001:db9::/32"]);
+	assert_eq!(set.len(), 8);
+
+	let mut rng = Rng(0x5b1);
+	for _ in 0..50 {
+		let mut set = rng.set(64);
+		let orig = set.clone();
+		let prefix = rng.cidr();
+		let part = set.split_off(&prefix);
+		let mut expected = bitstring_trees::set::Set::new();
+		expected.insert(prefix);
+		assert_eq!(part, orig.intersection(&expected));
+		assert_eq!(set, orig.difference(&expected));
+		assert_canonical(&set);
+		assert_canonical(&part);
+	}
+}
+
+#[test]
+fn covered_count() {
+	use bitstring_trees::set::Set;
+
+	let mut set = Set::<Ipv4Cidr>::new();
+	assert_eq!(set.covered_count(32), 0);
+	set.insert("192.168.0.0/24".parse().unwrap());
+	set.insert("10.0.0.1/32".parse().unwrap());
+	assert_eq!(set.covered_count(32), 257);
+	set.insert("0.0.0.0/0".parse().unwrap());
+	assert_eq!(set.covered_count(32), 1 << 32);
+
+	let mut set = Set::<Ipv6Cidr>::new();
+	set.insert("2001:db8::/32".parse().unwrap());
+	assert_eq!(set.covered_count(128), 1 << 96);
+	set.insert("8000::/1".parse().unwrap());
+	assert_eq!(set.covered_count(128), (1 << 127) + (1 << 96));
+	set.insert("::/1".parse().unwrap());
+	// 2^128 saturates
+	assert_eq!(set.covered_count(128), u128::MAX);
+	assert_eq!(set.covered_count(200), u128::MAX);
+}
+
+#[test]
+fn aggregate_to() {
+	let mut set = cidr_set(&["10.0.0.1", "10.0.1.0/24", "10.0.2.0/23", "192.168.0.0/16"]);
+	set.aggregate_to(21); // IPv4 /20 (plus one bit for the address family)
+	assert_eq!(cidr_list(&set), vec!["10.0.0.0/20", "192.168.0.0/16"]);
+
+	let mut set = cidr_set(&["10.0.0.1", "10.0.16.0/24"]);
+	set.aggregate_to(21); // IPv4 /20 (plus one bit for the address family)
+					   // siblings merged
+	assert_eq!(cidr_list(&set), vec!["10.0.0.0/19"]);
+
+	let mut rng = Rng(0xa99);
+	for _ in 0..50 {
+		let orig = rng.set(64);
+		let max_len = (rng.next() % 40) as usize;
+		let mut set = orig.clone();
+		set.aggregate_to(max_len);
+		assert!(orig.is_subset(&set));
+		assert!(set.iter().all(|c| bitstring::BitString::len(c) <= max_len));
+		assert_canonical(&set);
+	}
+}
+
+#[test]
+fn covering_prefix() {
+	let set = cidr_set(&["1.0.0.0/8", "10.0.0.0/25", "10.0.0.128/26"]);
+	let covering = |key: &str| {
+		set.covering_prefix(&key.parse().unwrap())
+			.map(|c| c.to_string())
+	};
+	assert_eq!(covering("1.2.3.4"), Some("1.0.0.0/8".to_string()));
+	assert_eq!(covering("1.0.0.0/8"), Some("1.0.0.0/8".to_string()));
+	assert_eq!(covering("10.0.0.130"), Some("10.0.0.128/26".to_string()));
+	assert_eq!(covering("10.0.0.200"), None);
+	assert_eq!(covering("0.0.0.0/0"), None);
+	// inner node: only partially covered
+	assert_eq!(covering("10.0.0.0/24"), None);
+	assert!(!set.contains(&"10.0.0.0/24".parse().unwrap()));
+	// diverging from inner node
+	assert_eq!(covering("10.0.1.0/24"), None);
+}
+
+#[test]
+fn iter_rev() {
+	let set = cidr_set(&["10.0.0.0/8", "192.168.0.0/24", "2001:db8::/32"]);
+	let rev: Vec<String> = set.iter().rev().map(|c| c.to_string()).collect();
+	assert_eq!(rev, vec!["2001:db8::/32", "192.168.0.0/24", "10.0.0.0/8"]);
+
+	let mut rng = Rng(0xb4c);
+	for _ in 0..50 {
+		let set = rng.set(64);
+		let list = cidr_list(&set);
+		// alternate between both ends; every leaf must show up exactly once
+		let mut iter = set.iter();
+		let (mut front, mut back) = (Vec::new(), Vec::new());
+		loop {
+			let item = if rng.next() & 1 == 0 {
+				iter.next().map(|c| front.push(c.to_string()))
+			} else {
+				iter.next_back().map(|c| back.push(c.to_string()))
+			};
+			if item.is_none() {
+				break;
+			}
+		}
+		assert_eq!(iter.next(), None);
+		assert_eq!(iter.next_back(), None);
+		back.reverse();
+		front.extend(back);
+		assert_eq!(front, list);
+	}
+}
+
+#[test]
+fn subtract_set() {
+	// small blocklist from large allowlist
+	let mut allow = cidr_set(&["10.0.0.0/8", "172.16.0.0/12", "2001:db8::/32"]);
+	allow.subtract_set(&cidr_set(&["10.1.2.3", "2001:db8::/33"]));
+	assert_eq!(allow.len(), 26);
+	assert!(!allow.contains(&"10.1.2.3".parse().unwrap()));
+	assert!(allow.contains(&"10.1.2.2".parse().unwrap()));
+	assert_eq!(
+		cidr_list(&allow.intersection(&cidr_set(&["2001:db8::/32"]))),
+		vec!["2001:db8:8000::/33"]
+	);
+	assert_canonical(&allow);
+
+	// large blocklist from small allowlist
+	let mut allow = cidr_set(&["10.1.2.0/24", "192.168.0.0/24"]);
+	allow.subtract_set(&cidr_set(&[
+		"10.0.0.0/8",
+		"172.16.0.0/12",
+		"192.168.0.128/25",
+		"2001:db8::/32",
+	]));
+	assert_eq!(cidr_list(&allow), vec!["192.168.0.0/25"]);
+
+	// everything
+	allow.subtract_set(&cidr_set(&["any"]));
+	assert!(allow.is_empty());
+
+	let mut rng = Rng(0x5ab);
+	for _ in 0..50 {
+		let a = rng.set(64);
+		let b = rng.set(8);
+		for (x, y) in [(&a, &b), (&b, &a)] {
+			let mut result = x.clone();
+			result.subtract_set(y);
+			assert!(!result.overlaps(y));
+			assert_eq!(result.union(&x.intersection(y)), *x);
+			assert_canonical(&result);
+		}
+	}
+}
+
+#[test]
+fn insert_changed() {
+	let mut set = cidr_set(&[]);
+	let mut insert = |key: &str| set.insert(key.parse().unwrap());
+	assert!(insert("192.168.0.0/25"));
+	assert!(!insert("192.168.0.0/25"));
+	assert!(!insert("192.168.0.1"));
+	// merges with sibling
+	assert!(insert("192.168.0.128/25"));
+	assert!(!insert("192.168.0.0/24"));
+	// replaces subtree
+	assert!(insert("10.1.0.0/16"));
+	assert!(insert("10.2.0.0/16"));
+	assert!(insert("10.0.0.0/8"));
+	assert!(!insert("10.1.0.0/16"));
+	assert!(insert("any"));
+	assert!(!insert("any"));
+
+	let mut rng = Rng(0x1c5);
+	for _ in 0..50 {
+		let mut set = rng.set(64);
+		let key = rng.cidr();
+		let covered = set.contains(&key);
+		let before = set.clone();
+		assert_eq!(set.insert(key), !covered);
+		assert_eq!(set != before, !covered);
+	}
+}
+
+#[test]
+fn from_sorted() {
+	use bitstring_trees::set::Set;
+
+	let keys: Vec<AnyIpCidr> = [
+		"10.0.0.0/25",
+		"10.0.0.128/25",
+		"10.0.1.0/24",
+		"10.2.0.0/16",
+		"::/1",
+	]
+	.iter()
+	.map(|c| c.parse().unwrap())
+	.collect();
+	let set = Set::try_from_sorted(keys.clone()).unwrap();
+	assert_eq!(cidr_list(&set), vec!["10.0.0.0/23", "10.2.0.0/16", "::/1"]);
+	assert_canonical(&set);
+
+	// unsorted
+	let mut unsorted = keys.clone();
+	unsorted.swap(1, 3);
+	assert_eq!(Set::try_from_sorted(unsorted.clone()).unwrap_err(), keys[2]);
+	assert_eq!(Set::from_sorted(unsorted), set);
+	// overlapping
+	let overlapping: Vec<AnyIpCidr> = vec![
+		"10.0.0.0/8".parse().unwrap(),
+		"10.1.0.0/16".parse().unwrap(),
+	];
+	assert_eq!(
+		Set::try_from_sorted(overlapping.clone()).unwrap_err(),
+		overlapping[1]
+	);
+	assert_eq!(
+		cidr_list(&Set::from_sorted(overlapping)),
+		vec!["10.0.0.0/8"]
+	);
+
+	assert!(Set::<AnyIpCidr>::from_sorted(Vec::new()).is_empty());
+
+	let mut rng = Rng(0x50f);
+	for _ in 0..50 {
+		let set = rng.set(64);
+		// split some leafs to get unaggregated input
+		let mut keys = Vec::new();
+		for c in set.iter() {
+			if rng.next() & 1 == 0 && !c.is_host_address() {
+				for bit in [false, true] {
+					let mut half = *c;
+					bitstring::BitString::append(&mut half, bit);
+					keys.push(half);
+				}
+			} else {
+				keys.push(*c);
+			}
+		}
+		assert_eq!(Set::try_from_sorted(keys).unwrap(), set);
+	}
+}
+
+#[test]
+fn contains_all() {
+	let set = cidr_set(&["10.0.0.0/25", "10.0.0.128/26", "192.168.0.0/16"]);
+	let keys =
+		|list: &[&str]| -> Vec<AnyIpCidr> { list.iter().map(|c| c.parse().unwrap()).collect() };
+	assert!(set.contains_all(keys(&[
+		"10.0.0.1",
+		"10.0.0.2",
+		"10.0.0.130",
+		"192.168.1.0/24"
+	])));
+	assert!(set.contains_all(keys(&["192.168.1.0/24", "10.0.0.130", "10.0.0.1"])));
+	assert!(!set.contains_all(keys(&["10.0.0.1", "10.0.0.200"])));
+	assert!(!set.contains_all(keys(&["10.0.0.0/24"])));
+	assert!(set.contains_all(keys(&[])));
+	assert!(!cidr_set(&[]).contains_all(keys(&["10.0.0.1"])));
+
+	let mut rng = Rng(0xca1);
+	for _ in 0..50 {
+		let set = rng.set(64);
+		let keys: Vec<AnyIpCidr> = (0..8).map(|_| rng.host()).collect();
+		assert_eq!(
+			set.contains_all(keys.iter().cloned()),
+			keys.iter().all(|k| set.contains(k))
+		);
+		let covered: Vec<AnyIpCidr> = keys.into_iter().filter(|k| set.contains(k)).collect();
+		assert!(set.contains_all(covered));
+	}
+}
+
+#[test]
+fn display() {
+	let set = cidr_set(&["192.168.10.0/24", "224.0.0.0/4", "8000::/1"]);
+	assert_eq!(set.to_string(), "{192.168.10.0/24, 224.0.0.0/4, 8000::/1}");
+	assert_eq!(
+		format!("{:#}", set),
+		"192.168.10.0/24\n224.0.0.0/4\n8000::/1\n"
+	);
+	assert_eq!(cidr_set(&[]).to_string(), "{}");
+}
+
+#[test]
+fn tree_mut() {
+	let mut set = cidr_set(&["10.0.0.0/8", "192.168.0.0/24", "2001:db8::/32"]);
+	{
+		// remove all IPv4 prefixes by hand
+		let tree = set.tree_mut();
+		let mut walk = tree.walk_mut::<bitstring_trees::tree::WalkedDirection, ()>();
+		walk.goto_insert(&"0.0.0.0/0".parse().unwrap());
+		walk.delete_current();
+	}
+	assert_eq!(cidr_list(&set), vec!["2001:db8::/32"]);
+	assert_eq!(set.len(), 1);
+}
+
+#[test]
+fn intersects_range() {
+	let set = cidr_set(&["10.0.0.0/24", "10.0.2.0/24", "192.168.0.0/16"]);
+	let intersects = |first: &str, last: &str| {
+		set.intersects_range(&first.parse().unwrap(), &last.parse().unwrap())
+	};
+	// straddles the gap between two leafs without touching either
+	assert!(!intersects("10.0.1.0", "10.0.1.255"));
+	assert!(intersects("10.0.1.0", "10.0.2.0"));
+	assert!(intersects("10.0.0.255", "10.0.1.0"));
+	assert!(intersects("0.0.0.0", "255.255.255.255"));
+	assert!(intersects("192.168.10.0/24", "192.168.10.0/24"));
+	assert!(!intersects("11.0.0.0", "192.167.255.255"));
+	// reversed
+	assert!(!intersects("10.0.2.0", "10.0.0.0"));
+	assert!(!cidr_set(&[]).intersects_range(&"any".parse().unwrap(), &"any".parse().unwrap()));
+
+	let mut rng = Rng(0x1a7);
+	for _ in 0..100 {
+		let set = rng.set(32);
+		let (first, last) = (rng.cidr(), rng.cidr());
+		let mut range = bitstring_trees::set::Set::new();
+		range.insert_range(first, last);
+		assert_eq!(set.intersects_range(&first, &last), set.overlaps(&range));
+	}
+}
+
+#[test]
+fn operators() {
+	let a = cidr_set(&["10.0.0.0/8", "192.168.0.0/24"]);
+	let b = cidr_set(&["10.1.0.0/16", "172.16.0.0/12"]);
+	assert_eq!(&a | &b, a.union(&b));
+	assert_eq!(&a & &b, a.intersection(&b));
+	assert_eq!(&a - &b, a.difference(&b));
+	assert_eq!(&a ^ &b, a.symmetric_difference(&b));
+	assert_eq!(a.clone() | &b, a.union(&b));
+	assert_eq!(a.clone() & &b, a.intersection(&b));
+	assert_eq!(a.clone() - &b, a.difference(&b));
+	assert_eq!(a.clone() ^ &b, a.symmetric_difference(&b));
+
+	let mut c = a.clone();
+	c |= &b;
+	assert_eq!(c, a.union(&b));
+	let mut c = a.clone();
+	c &= &b;
+	assert_eq!(c, a.intersection(&b));
+	let mut c = a.clone();
+	c -= &b;
+	assert_eq!(c, a.difference(&b));
+	let mut c = a.clone();
+	c ^= &b;
+	assert_eq!(c, a.symmetric_difference(&b));
+}
+
+#[test]
+fn toggle() {
+	let mut set = cidr_set(&["10.0.0.0/8"]);
+	// fully covered
+	set.toggle("10.0.0.0/8".parse().unwrap());
+	assert!(set.is_empty());
+	// not covered at all
+	set.toggle("10.0.0.0/8".parse().unwrap());
+	assert_eq!(cidr_list(&set), vec!["10.0.0.0/8"]);
+	// covered by shorter prefix
+	set.toggle("10.0.0.0/9".parse().unwrap());
+	assert_eq!(cidr_list(&set), vec!["10.128.0.0/9"]);
+	// partially covered
+	set.toggle("10.0.0.0/8".parse().unwrap());
+	assert_eq!(cidr_list(&set), vec!["10.0.0.0/9"]);
+	let mut set = cidr_set(&["10.0.0.0/25", "10.0.0.192/26", "10.1.0.0/16"]);
+	set.toggle("10.0.0.0/24".parse().unwrap());
+	assert_eq!(cidr_list(&set), vec!["10.0.0.128/26", "10.1.0.0/16"]);
+	set.toggle("0.0.0.0/0".parse().unwrap());
+	assert_eq!(set.len(), 15 + 10);
+	assert!(!set.contains(&"10.0.0.129".parse().unwrap()));
+	assert!(set.contains(&"10.0.0.1".parse().unwrap()));
+	assert_canonical(&set);
+
+	let mut rng = Rng(0x7091);
+	for _ in 0..100 {
+		let mut set = rng.set(64);
+		let key = rng.cidr();
+		let mut single = bitstring_trees::set::Set::new();
+		single.insert(key);
+		let expected = set.symmetric_difference(&single);
+		set.toggle(key);
+		assert_eq!(set, expected);
+		assert_canonical(&set);
+	}
+}
+
+#[test]
+fn pop_first_last() {
+	let mut set = cidr_set(&["10.0.0.0/8"]);
+	assert_eq!(set.pop_last().unwrap().to_string(), "10.0.0.0/8");
+	assert!(set.is_empty());
+	assert_eq!(set.pop_first(), None);
+	assert_eq!(set.pop_last(), None);
+
+	let mut rng = Rng(0x909);
+	for _ in 0..20 {
+		let mut set = rng.set(64);
+		let list = cidr_list(&set);
+		let mut front = Vec::new();
+		let mut back = Vec::new();
+		loop {
+			let key = if rng.next() & 1 == 0 {
+				set.pop_first().map(|c| front.push(c.to_string()))
+			} else {
+				set.pop_last().map(|c| back.push(c.to_string()))
+			};
+			if key.is_none() {
+				break;
+			}
+			assert_canonical(&set);
+		}
+		back.reverse();
+		front.extend(back);
+		assert_eq!(front, list);
+	}
+}
+
+#[test]
+fn iter_ranges() {
+	use bitstring_trees::set::Set;
+
+	let ranges = |set: &Set<Ipv4Cidr>| -> Vec<String> {
+		set.iter_ranges(32)
+			.map(|(first, last)| format!("{}-{}", first, last))
+			.collect()
+	};
+	let set: Set<Ipv4Cidr> = [
+		"192.168.0.0/24",
+		"192.168.1.0/24",
+		"192.168.2.0/23",
+		"10.0.0.1/32",
+		"10.0.0.3/32",
+	]
+	.iter()
+	.map(|c| c.parse().unwrap())
+	.collect();
+	assert_eq!(
+		ranges(&set),
+		vec![
+			"10.0.0.1-10.0.0.1",
+			"10.0.0.3-10.0.0.3",
+			"192.168.0.0-192.168.3.255"
+		]
+	);
+	assert_eq!(ranges(&Set::new()), Vec::<String>::new());
+
+	let mut set: Set<Ipv4Cidr> = ["0.0.0.0/0".parse().unwrap()].into_iter().collect();
+	assert_eq!(ranges(&set), vec!["0.0.0.0-255.255.255.255"]);
+	// no wraparound
+	set.remove("10.0.0.0/8".parse().unwrap());
+	assert_eq!(
+		ranges(&set),
+		vec!["0.0.0.0-9.255.255.255", "11.0.0.0-255.255.255.255"]
+	);
+
+	let mut rng = Rng(0x4a9);
+	for _ in 0..20 {
+		let mut set = Set::<Ipv4Cidr>::new();
+		for _ in 0..rng.next() % 64 {
+			if let AnyIpCidr::V4(c) = rng.cidr() {
+				set.insert(c);
+			}
+		}
+		let mut rebuilt = Set::new();
+		for (first, last) in set.iter_ranges(32) {
+			rebuilt.insert_range(first, last);
+		}
+		assert_eq!(rebuilt, set);
+	}
+}
+
+#[test]
+fn diff() {
+	use bitstring_trees::set::DiffSide;
+
+	let a = cidr_set(&["10.0.0.0/8", "192.168.0.0/24"]);
+	let b = cidr_set(&["10.0.0.0/9", "192.168.0.0/25", "2001:db8::/32"]);
+	let diff: Vec<(String, DiffSide)> = a.diff(&b).map(|(c, side)| (c.to_string(), side)).collect();
+	assert_eq!(
+		diff,
+		vec![
+			("10.128.0.0/9".to_string(), DiffSide::OnlyLeft),
+			("192.168.0.128/25".to_string(), DiffSide::OnlyLeft),
+			("2001:db8::/32".to_string(), DiffSide::OnlyRight),
+		]
+	);
+	assert_eq!(a.diff(&a.clone()).next(), None);
+	assert_eq!(cidr_set(&[]).diff(&cidr_set(&[])).next(), None);
+
+	let mut rng = Rng(0xd1f);
+	for _ in 0..50 {
+		let a = rng.set(64);
+		let b = rng.set(64);
+		let mut only_left = bitstring_trees::set::Set::new();
+		let mut only_right = bitstring_trees::set::Set::new();
+		let mut count = 0;
+		for (key, side) in a.diff(&b) {
+			count += 1;
+			match side {
+				DiffSide::OnlyLeft => only_left.insert(key),
+				DiffSide::OnlyRight => only_right.insert(key),
+			};
+		}
+		assert_eq!(only_left, a.difference(&b));
+		assert_eq!(only_right, b.difference(&a));
+		// minimal: nothing merged when collecting
+		assert_eq!(count, only_left.len() + only_right.len());
+	}
+}
+
+#[test]
+fn longest_common_prefix() {
+	assert_eq!(cidr_set(&[]).longest_common_prefix(), None);
+	assert_eq!(
+		cidr_set(&["10.0.0.0/24", "10.0.3.0/24"]).longest_common_prefix(),
+		Some(c("10.0.0.0/22"))
+	);
+	assert_eq!(
+		cidr_set(&["10.0.0.0/24"]).longest_common_prefix(),
+		Some(c("10.0.0.0/24"))
+	);
+}
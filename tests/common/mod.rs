@@ -0,0 +1,111 @@
+// helpers shared by the integration tests; not every test uses all of them
+#![allow(dead_code)]
+
+use cidr::{
+	AnyIpCidr,
+	Ipv4Cidr,
+	Ipv6Cidr,
+};
+
+pub fn c(s: &str) -> AnyIpCidr {
+	s.parse().unwrap()
+}
+
+pub fn cidr_set(prefixes: &[&str]) -> bitstring_trees::set::Set<AnyIpCidr> {
+	let mut set = bitstring_trees::set::Set::new();
+	for prefix in prefixes {
+		set.insert(prefix.parse().unwrap());
+	}
+	set
+}
+
+pub fn cidr_list(set: &bitstring_trees::set::Set<AnyIpCidr>) -> Vec<String> {
+	set.iter().map(|c| c.to_string()).collect()
+}
+
+// set must equal the set built by inserting its leafs again
+pub fn assert_canonical(set: &bitstring_trees::set::Set<AnyIpCidr>) {
+	#[cfg(debug_assertions)]
+	set.tree().check_invariants().unwrap();
+	let list = cidr_list(set);
+	assert_eq!(set.len(), list.len());
+	let rebuilt = cidr_set(&list.iter().map(String::as_str).collect::<Vec<_>>());
+	assert_eq!(list, cidr_list(&rebuilt));
+}
+
+// deterministic xorshift generator for property-style tests
+pub struct Rng(pub u64);
+
+impl Rng {
+	pub fn next(&mut self) -> u64 {
+		self.0 ^= self.0 << 13;
+		self.0 ^= self.0 >> 7;
+		self.0 ^= self.0 << 17;
+		self.0
+	}
+
+	// random prefix clustered in a few small regions so prefixes overlap often
+	pub fn cidr(&mut self) -> AnyIpCidr {
+		let r = self.next();
+		let len = (r >> 56) as u8;
+		if r & 1 == 0 {
+			let len = 8 + len % 17;
+			let addr = 0x0a00_0000 | ((r >> 8) as u32 & 0x00ff_ffff);
+			let addr = addr & !(u32::MAX >> len);
+			Ipv4Cidr::new(addr.into(), len).unwrap().into()
+		} else {
+			let len = 32 + len % 33;
+			let addr = (0x2001_0db8_u128 << 96) | (((r >> 8) as u128 & 0xffff_ffff) << 64);
+			let addr = addr & !(u128::MAX >> len);
+			Ipv6Cidr::new(addr.into(), len).unwrap().into()
+		}
+	}
+
+	// random host address in the same regions as `cidr`
+	pub fn host(&mut self) -> AnyIpCidr {
+		let r = self.next();
+		if r & 1 == 0 {
+			AnyIpCidr::from(std::net::Ipv4Addr::from(
+				0x0a00_0000 | ((r >> 8) as u32 & 0x00ff_ffff),
+			))
+		} else {
+			AnyIpCidr::from(std::net::Ipv6Addr::from(
+				(0x2001_0db8_u128 << 96) | (((r >> 8) as u128 & 0xffff_ffff) << 64) | r as u128,
+			))
+		}
+	}
+
+	pub fn set(&mut self, max_len: u64) -> bitstring_trees::set::Set<AnyIpCidr> {
+		let mut set = bitstring_trees::set::Set::new();
+		for _ in 0..self.next() % max_len {
+			set.insert(self.cidr());
+		}
+		set
+	}
+}
+
+pub struct TpLeafCount;
+
+impl bitstring_trees::tree::TreeProperties for TpLeafCount {
+	type Key = AnyIpCidr;
+	type LeafValue = u32;
+	type LeafValueComparer = bitstring_trees::tree::DefaultCompare;
+	type Value = ();
+
+	const EMPTY: bool = true;
+	const IGNORE_LEAFS: bool = false;
+	const LEAF_EMPTY: bool = false;
+}
+
+pub struct TpInnerValue;
+
+impl bitstring_trees::tree::TreeProperties for TpInnerValue {
+	type Key = AnyIpCidr;
+	type LeafValue = ();
+	type LeafValueComparer = bitstring_trees::tree::DefaultCompare;
+	type Value = Option<u32>;
+
+	const EMPTY: bool = false;
+	const IGNORE_LEAFS: bool = true;
+	const LEAF_EMPTY: bool = true;
+}
@@ -0,0 +1,38 @@
+#![cfg(feature = "serde")]
+
+use bitstring_trees::map::Map;
+use cidr::Ipv4Cidr;
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+enum Action {
+	#[default]
+	Deny,
+	Allow,
+}
+
+#[test]
+fn roundtrip() {
+	let mut map = Map::<Ipv4Cidr, Action>::new();
+	map.insert("192.168.0.128/25".parse().unwrap(), Action::Allow);
+	map.insert("10.0.0.0/8".parse().unwrap(), Action::Deny);
+	map.insert("192.168.0.0/25".parse().unwrap(), Action::Allow);
+	let json = serde_json::to_string(&map).unwrap();
+	assert_eq!(json, r#"{"10.0.0.0/8":"Deny","192.168.0.0/24":"Allow"}"#);
+	assert_eq!(
+		serde_json::from_str::<Map<Ipv4Cidr, Action>>(&json).unwrap(),
+		map
+	);
+}
+
+#[test]
+fn deserialize_canonical() {
+	let map: Map<Ipv4Cidr, Action> = serde_json::from_str(
+		r#"{"10.0.0.0/8":"Deny","10.1.0.0/16":"Allow","10.0.0.0/9":"Allow","10.128.0.0/9":"Allow"}"#,
+	)
+	.unwrap();
+	assert_eq!(
+		serde_json::to_string(&map).unwrap(),
+		r#"{"10.0.0.0/8":"Allow"}"#
+	);
+	assert!(serde_json::from_str::<Map<Ipv4Cidr, Action>>(r#"{"10.0.0.0/33":"Deny"}"#).is_err());
+}
@@ -0,0 +1,24 @@
+#![cfg(feature = "serde")]
+
+use bitstring_trees::set::Set;
+use cidr::Ipv4Cidr;
+
+#[test]
+fn roundtrip() {
+	let mut set = Set::<Ipv4Cidr>::new();
+	for c in ["192.168.0.128/25", "10.0.0.0/8", "192.168.0.0/25"] {
+		set.insert(c.parse().unwrap());
+	}
+	let json = serde_json::to_string(&set).unwrap();
+	assert_eq!(json, r#"["10.0.0.0/8","192.168.0.0/24"]"#);
+	assert_eq!(serde_json::from_str::<Set<Ipv4Cidr>>(&json).unwrap(), set);
+}
+
+#[test]
+fn deserialize_canonical() {
+	let set: Set<Ipv4Cidr> =
+		serde_json::from_str(r#"["10.1.0.0/16","10.0.0.0/8","10.0.0.0/9","10.128.0.0/9"]"#)
+			.unwrap();
+	assert_eq!(serde_json::to_string(&set).unwrap(), r#"["10.0.0.0/8"]"#);
+	assert!(serde_json::from_str::<Set<Ipv4Cidr>>(r#"["10.0.0.0/33"]"#).is_err());
+}
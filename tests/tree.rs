@@ -0,0 +1,508 @@
+mod common;
+
+use cidr::AnyIpCidr;
+use common::{
+	c,
+	cidr_set,
+	Rng,
+	TpInnerValue,
+	TpLeafCount,
+};
+
+#[test]
+fn tree_delete() {
+	use bitstring_trees::tree::Tree;
+
+	let leafs = |tree: &Tree<TpLeafCount>| -> Vec<(String, u32)> {
+		tree.iter_leaf()
+			.map(|(node, value)| (node.get_key().to_string(), *value))
+			.collect()
+	};
+	let mut tree = Tree::<TpLeafCount>::new();
+	tree.delete(&c("10.0.0.0/8"));
+	assert!(tree.root().is_none());
+	tree.set_leaf_value(c("10.0.0.0/22"), 1);
+	tree.set_leaf_value(c("10.0.4.0/24"), 2);
+	// split covering leaf
+	tree.delete(&c("10.0.1.0/24"));
+	assert_eq!(
+		leafs(&tree),
+		vec![
+			("10.0.0.0/24".to_string(), 1),
+			("10.0.2.0/23".to_string(), 1),
+			("10.0.4.0/24".to_string(), 2),
+		]
+	);
+	// exact and contained nodes
+	tree.delete(&c("10.0.4.0/24"));
+	tree.delete(&c("10.0.2.0/24"));
+	assert_eq!(
+		leafs(&tree),
+		vec![
+			("10.0.0.0/24".to_string(), 1),
+			("10.0.3.0/24".to_string(), 1),
+		]
+	);
+	// not covered
+	tree.delete(&c("10.0.1.0/24"));
+	tree.delete(&c("192.168.0.0/16"));
+	assert_eq!(leafs(&tree).len(), 2);
+	tree.delete(&c("10.0.0.0/8"));
+	assert!(tree.root().is_none());
+
+	// inner values must survive deleting subtrees
+	let values = |tree: &Tree<TpInnerValue>| -> Vec<(String, u32)> {
+		tree.iter_in_order()
+			.filter_map(|node| Some((node.get_key().to_string(), (*node.get_value())?)))
+			.collect()
+	};
+	let mut tree = Tree::<TpInnerValue>::new();
+	for (key, value) in [("10.0.0.0/8", 1), ("10.1.0.0/16", 2), ("10.1.2.0/24", 3)] {
+		let mut walk = tree.walk_mut::<(), ()>();
+		walk.goto_insert(&c(key));
+		*walk.insert(c(key)).get_value_mut() = Some(value);
+	}
+	// shorter prefixes are not deleted
+	tree.delete(&c("10.1.2.128/25"));
+	assert_eq!(values(&tree).len(), 3);
+	tree.delete(&c("10.1.2.0/24"));
+	assert_eq!(
+		values(&tree),
+		vec![
+			("10.1.0.0/16".to_string(), 2),
+			("10.0.0.0/8".to_string(), 1)
+		]
+	);
+	tree.delete(&c("10.1.0.0/16"));
+	assert_eq!(values(&tree), vec![("10.0.0.0/8".to_string(), 1)]);
+	tree.delete(&c("10.0.0.0/8"));
+	assert!(tree.root().is_none());
+}
+
+#[test]
+fn tree_set_leaf_value_replacing() {
+	use bitstring_trees::tree::Tree;
+
+	let mut tree = Tree::<TpLeafCount>::new();
+	assert_eq!(
+		tree.set_leaf_value_replacing(c("10.0.0.0/24"), 1).count(),
+		0
+	);
+	assert_eq!(
+		tree.set_leaf_value_replacing(c("10.0.2.0/24"), 2).count(),
+		0
+	);
+	assert_eq!(
+		tree.set_leaf_value_replacing(c("10.0.3.0/24"), 3).count(),
+		0
+	);
+	// same value: nothing replaced
+	assert_eq!(
+		tree.set_leaf_value_replacing(c("10.0.0.0/24"), 1).count(),
+		0
+	);
+	assert_eq!(
+		tree.set_leaf_value_replacing(c("10.0.0.0/22"), 4)
+			.collect::<Vec<_>>(),
+		vec![
+			(c("10.0.0.0/24"), 1),
+			(c("10.0.2.0/24"), 2),
+			(c("10.0.3.0/24"), 3)
+		]
+	);
+	// covering leaf is reported with the new key
+	assert_eq!(
+		tree.set_leaf_value_replacing(c("10.0.1.0/24"), 5)
+			.collect::<Vec<_>>(),
+		vec![(c("10.0.1.0/24"), 4)]
+	);
+	assert_eq!(
+		tree.set_leaf_value_replacing(c("10.0.1.128/25"), 5).count(),
+		0
+	);
+	assert_eq!(
+		tree.iter_leaf()
+			.map(|(node, value)| (*node.get_key(), *value))
+			.collect::<Vec<_>>(),
+		vec![
+			(c("10.0.0.0/24"), 4),
+			(c("10.0.1.0/24"), 5),
+			(c("10.0.2.0/23"), 4)
+		]
+	);
+}
+
+#[test]
+fn tree_set_leaf_value_with() {
+	use bitstring_trees::tree::Tree;
+
+	let leafs = |tree: &Tree<TpLeafCount>| -> Vec<(AnyIpCidr, u32)> {
+		tree.iter_leaf()
+			.map(|(node, value)| (*node.get_key(), *value))
+			.collect()
+	};
+	let mut tree = Tree::<TpLeafCount>::new();
+	tree.set_leaf_value_with(c("10.0.0.0/24"), 1, |_, _| panic!("nothing to merge"));
+	tree.set_leaf_value(c("10.0.3.0/25"), 2);
+	// gaps get the new value, overlaps are merged
+	tree.set_leaf_value_with(c("10.0.0.0/22"), 1, |_, old| old + 1);
+	assert_eq!(
+		leafs(&tree),
+		vec![
+			(c("10.0.0.0/24"), 2),
+			(c("10.0.1.0/24"), 1),
+			(c("10.0.2.0/24"), 1),
+			(c("10.0.3.0/25"), 3),
+			(c("10.0.3.128/25"), 1),
+		]
+	);
+	// equal siblings get merged
+	tree.set_leaf_value_with(c("10.0.0.0/22"), 5, |key, old| {
+		assert!(key.network_length() >= Some(23));
+		if *old == 1 {
+			5
+		} else {
+			*old
+		}
+	});
+	assert_eq!(
+		leafs(&tree),
+		vec![
+			(c("10.0.0.0/24"), 2),
+			(c("10.0.1.0/24"), 5),
+			(c("10.0.2.0/24"), 5),
+			(c("10.0.3.0/25"), 3),
+			(c("10.0.3.128/25"), 5),
+		]
+	);
+	tree.set_leaf_value_with(c("10.0.0.0/22"), 0, |_, _| 7);
+	assert_eq!(leafs(&tree), vec![(c("10.0.0.0/22"), 7)]);
+	// covering leaf
+	tree.set_leaf_value_with(c("10.0.1.0/24"), 0, |key, old| {
+		assert_eq!(key, &c("10.0.1.0/24"));
+		old + 1
+	});
+	assert_eq!(
+		leafs(&tree),
+		vec![
+			(c("10.0.0.0/24"), 7),
+			(c("10.0.1.0/24"), 8),
+			(c("10.0.2.0/23"), 7),
+		]
+	);
+}
+
+#[test]
+fn tree_counts() {
+	use bitstring_trees::tree::Tree;
+
+	// `check_invariants` checks the maintained count against a traversal
+	let check_leafs = |tree: &Tree<TpLeafCount>| {
+		tree.check_invariants().unwrap();
+		assert_eq!(tree.leaf_count(), tree.iter_leaf().count());
+		assert_eq!(tree.node_count(), tree.iter_pre_order().count());
+	};
+	let check_inner = |tree: &Tree<TpInnerValue>| {
+		tree.check_invariants().unwrap();
+		assert_eq!(tree.leaf_count(), tree.iter_leaf().count());
+		assert_eq!(tree.node_count(), tree.iter_pre_order().count());
+	};
+
+	let mut rng = Rng(0x1eaf);
+	let mut tree = Tree::<TpLeafCount>::new();
+	check_leafs(&tree);
+	for _ in 0..400 {
+		let key = rng.cidr();
+		let value = (rng.next() % 3) as u32;
+		match rng.next() % 4 {
+			0 => tree.set_leaf_value(key, value),
+			1 => tree.set_leaf_value_with(key, value, |_, old| (old + 1) % 3),
+			2 => {
+				let _ = tree.set_leaf_value_replacing(key, value).count();
+			},
+			_ => tree.delete(&key),
+		}
+		check_leafs(&tree);
+	}
+	tree.clear();
+	check_leafs(&tree);
+
+	let mut tree = Tree::<TpInnerValue>::new();
+	for _ in 0..200 {
+		let key = rng.cidr();
+		match rng.next() % 3 {
+			0 => tree.delete(&key),
+			_ => {
+				// mutable access: counted by traversal until next tree-level update
+				let mut walk = tree.walk_mut::<(), ()>();
+				walk.goto_insert(&key);
+				*walk.insert(key).get_value_mut() = Some(1);
+			},
+		}
+		check_inner(&tree);
+	}
+}
+
+#[test]
+fn tree_stats() {
+	use bitstring::BitString as _;
+
+	let set = cidr_set(&[]);
+	assert_eq!(set.stats(), Default::default());
+
+	let set = cidr_set(&["10.0.0.0/24", "10.0.2.0/24", "10.0.3.128/25"]);
+	let stats = set.stats();
+	assert_eq!(stats.leaf_nodes, 3);
+	assert_eq!(stats.inner_nodes, 2);
+	assert_eq!(stats.max_depth, 3);
+	assert_eq!(stats.max_key_len, c("10.0.3.128/25").len());
+	assert!(stats.heap_bytes > 0);
+	assert_eq!(stats.heap_bytes % stats.inner_nodes, 0);
+
+	let mut rng = Rng(0x57a7);
+	let set = rng.set(100);
+	let stats = set.stats();
+	assert_eq!(stats.leaf_nodes, set.len());
+	assert_eq!(
+		stats.leaf_nodes + stats.inner_nodes,
+		set.tree().node_count()
+	);
+	assert_eq!(
+		stats.max_key_len,
+		set.iter().map(|k| k.len()).max().unwrap()
+	);
+
+	let mut map = bitstring_trees::full_map::FullMap::<AnyIpCidr, u32>::new();
+	map.insert(c("10.0.0.0/8"), 1);
+	assert_eq!(map.stats().leaf_nodes, 1);
+	assert_eq!(map.stats().heap_bytes, 0);
+}
+
+#[test]
+fn tree_clear_take() {
+	use bitstring_trees::tree::Tree;
+
+	let mut tree = Tree::<TpLeafCount>::new();
+	tree.set_leaf_value(c("10.0.0.0/24"), 1);
+	tree.set_leaf_value(c("10.0.2.0/24"), 2);
+	let taken = tree.take();
+	assert!(tree.root().is_none());
+	assert_eq!(tree.leaf_count(), 0);
+	assert_eq!(taken.leaf_count(), 2);
+	// reuse both trees
+	tree.set_leaf_value(c("10.1.0.0/16"), 3);
+	assert_eq!(tree.leaf_count(), 1);
+	let mut taken = taken;
+	taken.clear();
+	assert!(taken.root().is_none());
+	assert_eq!(taken.node_count(), 0);
+	taken.set_leaf_value(c("10.0.0.0/8"), 4);
+	assert_eq!(taken.iter_leaf().count(), 1);
+}
+
+#[test]
+fn tree_into_iter() {
+	use bitstring_trees::tree::Tree;
+
+	let mut tree = Tree::<TpLeafCount>::new();
+	tree.set_leaf_value(c("10.0.0.0/24"), 1);
+	tree.set_leaf_value(c("10.0.2.0/24"), 2);
+	tree.set_leaf_value(c("10.0.3.0/24"), 3);
+	let leafs: Vec<_> = tree.iter_leaf().map(|(n, v)| (*n.get_key(), *v)).collect();
+	assert_eq!(tree.clone().into_iter_leaf().collect::<Vec<_>>(), leafs);
+	assert_eq!(
+		tree.into_iter_in_order()
+			.map(|(key, ())| key)
+			.collect::<Vec<_>>(),
+		vec![
+			c("10.0.0.0/24"),
+			c("10.0.0.0/22"),
+			c("10.0.2.0/24"),
+			c("10.0.2.0/23"),
+			c("10.0.3.0/24"),
+		]
+	);
+
+	// values get dropped when dropping an iterator part-way
+	let value = std::rc::Rc::new(0u32);
+	let mut map = bitstring_trees::map::Map::<AnyIpCidr, std::rc::Rc<u32>>::new();
+	let mut rng = Rng(0xd20b);
+	for _ in 0..50 {
+		map.insert(rng.cidr(), value.clone());
+	}
+	assert!(std::rc::Rc::strong_count(&value) > 1);
+	let mut iter = map.clone().into_iter();
+	iter.next();
+	drop(iter);
+	drop(map);
+	assert_eq!(std::rc::Rc::strong_count(&value), 1);
+}
+
+#[test]
+fn tree_clone() {
+	use bitstring_trees::tree::Tree;
+
+	let mut rng = Rng(0xc10e);
+	let mut trees: Vec<Tree<TpInnerValue>> = Vec::new();
+	for _ in 0..6 {
+		let mut tree = Tree::<TpInnerValue>::new();
+		for value in 0..rng.next() % 40 {
+			let key = rng.cidr();
+			let mut walk = tree.walk_mut::<(), ()>();
+			walk.goto_insert(&key);
+			*walk.insert(key).get_value_mut() = Some(value as u32);
+		}
+		trees.push(tree);
+	}
+	let dump = |tree: &Tree<TpInnerValue>| format!("{:?}", tree);
+	for source in &trees {
+		assert_eq!(dump(&source.clone()), dump(source));
+		for target in &trees {
+			let mut target = target.clone();
+			target.clone_from(source);
+			assert_eq!(dump(&target), dump(source));
+		}
+	}
+
+	let mut map = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	for value in 0..30 {
+		map.insert(rng.cidr(), value % 4);
+	}
+	let mut copy = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	copy.insert(rng.cidr(), 1);
+	copy.clone_from(&map);
+	assert_eq!(copy, map);
+	assert_eq!(copy.len(), map.len());
+	copy.clone_from(&Default::default());
+	assert!(copy.is_empty());
+
+	let set = rng.set(40);
+	let mut copy = rng.set(40);
+	copy.clone_from(&set);
+	assert_eq!(copy, set);
+	assert_eq!(copy.len(), set.len());
+
+	let mut full_map = bitstring_trees::full_map::FullMap::<AnyIpCidr, u32>::new();
+	for value in 0..30 {
+		full_map.insert(rng.cidr(), value);
+	}
+	let mut copy = bitstring_trees::full_map::FullMap::<AnyIpCidr, u32>::new();
+	copy.insert(rng.cidr(), 1);
+	copy.clone_from(&full_map);
+	assert_eq!(format!("{:?}", copy), format!("{:?}", full_map));
+}
+
+#[test]
+fn tree_eq() {
+	use bitstring_trees::tree::Tree;
+
+	let mut rng = Rng(0xe9);
+	let mut trees: Vec<Tree<TpInnerValue>> = Vec::new();
+	for _ in 0..8 {
+		let mut tree = Tree::<TpInnerValue>::new();
+		for value in 0..rng.next() % 4 {
+			let key =
+				c(["10.0.0.0/8", "10.0.0.0/16", "10.128.0.0/9", "0.0.0.0/0"]
+					[rng.next() as usize % 4]);
+			let mut walk = tree.walk_mut::<(), ()>();
+			walk.goto_insert(&key);
+			*walk.insert(key).get_value_mut() = Some(value as u32 % 2);
+		}
+		trees.push(tree);
+	}
+	let dump = |tree: &Tree<TpInnerValue>| format!("{:?}", tree);
+	let leafs = |tree: &Tree<TpInnerValue>| {
+		tree.iter_leaf()
+			.map(|(node, _)| *node.get_key())
+			.collect::<Vec<_>>()
+	};
+	for a in &trees {
+		for b in &trees {
+			assert_eq!(a.structural_eq(b), dump(a) == dump(b));
+			assert_eq!(a.leaf_eq(b), leafs(a) == leafs(b));
+		}
+	}
+
+	let mut a = Tree::<TpLeafCount>::new();
+	let mut b = Tree::<TpLeafCount>::new();
+	assert!(a.leaf_eq(&b));
+	a.set_leaf_value(c("10.0.0.0/9"), 1);
+	a.set_leaf_value(c("10.128.0.0/9"), 1);
+	assert!(!a.leaf_eq(&b));
+	b.set_leaf_value(c("10.0.0.0/8"), 1);
+	assert!(a.leaf_eq(&b));
+	assert!(a.structural_eq(&b));
+	b.set_leaf_value(c("10.0.0.0/9"), 2);
+	assert!(!a.leaf_eq(&b));
+
+	// same content inserted in different order
+	let mut map_a = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	let mut map_b = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	let entries: Vec<(AnyIpCidr, u32)> = (0..20).map(|v| (rng.cidr(), v % 3)).collect();
+	for (key, value) in &entries {
+		map_a.insert(*key, *value);
+	}
+	assert_eq!(map_a, map_a.clone());
+	// aggregated entries don't overlap: insert in reverse order
+	let mut aggregated: Vec<(AnyIpCidr, u32)> = map_a.iter().map(|(k, v)| (*k, *v)).collect();
+	aggregated.reverse();
+	for (key, value) in aggregated {
+		map_b.insert(key, value);
+	}
+	assert_eq!(map_a, map_b);
+	map_b.insert(entries[0].0, 7);
+	assert_ne!(map_a, map_b);
+}
+
+#[test]
+fn tree_check_invariants() {
+	use bitstring_trees::tree::{
+		InvariantError,
+		Tree,
+	};
+
+	let mut tree = Tree::<TpLeafCount>::new();
+	assert_eq!(tree.check_invariants(), Ok(()));
+	tree.set_leaf_value(c("10.0.0.0/9"), 1);
+	tree.set_leaf_value(c("10.128.0.0/9"), 2);
+	assert_eq!(tree.check_invariants(), Ok(()));
+
+	// equal sibling leafs (modified in place) must be merged
+	let mut walk = tree.walk_mut::<(), ()>();
+	walk.goto_insert(&c("10.128.0.0/9"));
+	*walk
+		.current_mut()
+		.node()
+		.unwrap()
+		.get_leaf_value_mut()
+		.unwrap() = 1;
+	let err = tree.check_invariants().unwrap_err();
+	assert_eq!(
+		err,
+		InvariantError::UnmergedLeafs {
+			key: c("10.0.0.0/8")
+		}
+	);
+	assert_eq!(err.key(), Some(&c("10.0.0.0/8")));
+	assert_eq!(
+		err.to_string(),
+		"child leafs of V4(10.0.0.0/8) should have been merged"
+	);
+}
+
+#[test]
+fn tree_walk_mut_path() {
+	let mut map = bitstring_trees::map::Map::<AnyIpCidr, u32>::new();
+	map.insert(c("10.0.0.0/24"), 1);
+	map.insert(c("10.0.1.0/24"), 2);
+	let tree = map.raw_tree_mut();
+	let mut walk = tree.walk_mut::<(), ()>();
+	let keys: Vec<AnyIpCidr> = walk
+		.path(c("10.0.1.1"))
+		.into_iter()
+		.map(|(k, _, _)| *k)
+		.collect();
+	assert_eq!(keys, vec![c("10.0.0.0/23"), c("10.0.1.0/24")]);
+	let node = walk.current().node().unwrap();
+	assert_eq!(node.get_leaf_value(), Some(&2));
+}